@@ -0,0 +1,118 @@
+//! Named measurement captures
+//!
+//! A capture is a reading the user saved under a label to compare against
+//! later. Unlike the measurement store, captures are only added and
+//! removed explicitly and outlive the device's connection.
+
+use crate::device::Measurement;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A labelled reading
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Capture {
+    pub label: String,
+    pub captured_at: chrono::DateTime<chrono::Utc>,
+    pub measurement: Measurement,
+}
+
+/// Captures of every device, in the order they were first taken
+pub struct CaptureStore {
+    devices: HashMap<String, Vec<Capture>>,
+    /// Most captures kept per device
+    limit: usize,
+}
+
+impl CaptureStore {
+    pub fn new(limit: usize) -> Self {
+        Self {
+            devices: HashMap::new(),
+            limit,
+        }
+    }
+
+    /// Store `measurement` under `label`, replacing a capture with the same
+    /// label. A new label fails once the device has `limit` captures.
+    pub fn insert(
+        &mut self,
+        device_id: &str,
+        label: String,
+        measurement: Measurement,
+    ) -> std::result::Result<Capture, String> {
+        let captures = self.devices.entry(device_id.to_string()).or_default();
+        let capture = Capture {
+            label,
+            captured_at: chrono::Utc::now(),
+            measurement,
+        };
+        if let Some(existing) = captures.iter_mut().find(|c| c.label == capture.label) {
+            *existing = capture.clone();
+        } else if captures.len() >= self.limit {
+            return Err(format!(
+                "Device {} already has {} captures; clear some first",
+                device_id, self.limit
+            ));
+        } else {
+            captures.push(capture.clone());
+        }
+        Ok(capture)
+    }
+
+    pub fn list(&self, device_id: &str) -> Vec<Capture> {
+        self.devices.get(device_id).cloned().unwrap_or_default()
+    }
+
+    /// Remove every capture of a device; returns how many were removed
+    pub fn clear(&mut self, device_id: &str) -> usize {
+        self.devices
+            .remove(device_id)
+            .map_or(0, |captures| captures.len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{MeasurementAttribute, MeasurementState, Unit};
+
+    fn reading(value: f64) -> Measurement {
+        Measurement::new(
+            value,
+            Unit::VoltDc,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        )
+    }
+
+    #[test]
+    fn a_label_is_overwritten_in_place() {
+        let mut store = CaptureStore::new(5);
+        store
+            .insert("dev", "baseline".into(), reading(1.0))
+            .unwrap();
+        store.insert("dev", "loaded".into(), reading(2.0)).unwrap();
+        store
+            .insert("dev", "baseline".into(), reading(3.0))
+            .unwrap();
+
+        let captures = store.list("dev");
+        let labels: Vec<_> = captures.iter().map(|c| c.label.as_str()).collect();
+        assert_eq!(labels, ["baseline", "loaded"]);
+        assert_eq!(captures[0].measurement.value, 3.0);
+    }
+
+    #[test]
+    fn new_labels_are_refused_at_the_limit() {
+        let mut store = CaptureStore::new(2);
+        store.insert("dev", "a".into(), reading(1.0)).unwrap();
+        store.insert("dev", "b".into(), reading(2.0)).unwrap();
+
+        assert!(store.insert("dev", "c".into(), reading(3.0)).is_err());
+        // Replacing an existing label is still allowed, as are other devices
+        store.insert("dev", "a".into(), reading(4.0)).unwrap();
+        store.insert("other", "c".into(), reading(5.0)).unwrap();
+        assert_eq!(store.clear("dev"), 2);
+        assert!(store.list("dev").is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+//! Coalescing of identical concurrent requests
+//!
+//! While one request is fetching a result, others that arrive wait for it
+//! and share it instead of sending the same command to the device again.
+
+use std::future::Future;
+use std::sync::Mutex;
+use tokio::sync::broadcast;
+
+/// A fetch shared by every caller that arrives while it runs
+pub(crate) struct Coalescer<T> {
+    /// Channel the running fetch publishes its result on
+    inflight: Mutex<Option<broadcast::Sender<T>>>,
+}
+
+impl<T> Default for Coalescer<T> {
+    fn default() -> Self {
+        Self {
+            inflight: Mutex::new(None),
+        }
+    }
+}
+
+/// Clears the in-flight slot when the fetching caller finishes or is
+/// cancelled, so waiters never wait on an abandoned fetch
+struct Leader<'a, T> {
+    inflight: &'a Mutex<Option<broadcast::Sender<T>>>,
+}
+
+impl<T> Leader<'_, T> {
+    fn take(&self) -> Option<broadcast::Sender<T>> {
+        self.inflight
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+}
+
+impl<T> Drop for Leader<'_, T> {
+    fn drop(&mut self) {
+        self.take();
+    }
+}
+
+impl<T: Clone> Coalescer<T> {
+    /// Run `fetch`, or share the result of the fetch already running. A
+    /// waiter whose fetching caller was cancelled starts a fetch of its own.
+    pub(crate) async fn run<F>(&self, fetch: impl FnOnce() -> F) -> T
+    where
+        F: Future<Output = T>,
+    {
+        loop {
+            let mut receiver = {
+                let mut inflight = self.inflight.lock().unwrap_or_else(|e| e.into_inner());
+                match inflight.as_ref() {
+                    Some(sender) => sender.subscribe(),
+                    None => {
+                        *inflight = Some(broadcast::channel(1).0);
+                        break;
+                    }
+                }
+            };
+            if let Ok(result) = receiver.recv().await {
+                return result;
+            }
+        }
+
+        let leader = Leader {
+            inflight: &self.inflight,
+        };
+        let result = fetch().await;
+        // Callers arriving from here on start a new fetch.
+        if let Some(sender) = leader.take() {
+            let _ = sender.send(result.clone());
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn concurrent_callers_share_one_fetch() {
+        let coalescer = Arc::new(Coalescer::default());
+        let fetches = Arc::new(AtomicUsize::new(0));
+
+        let callers: Vec<_> = (0..8)
+            .map(|_| {
+                let coalescer = coalescer.clone();
+                let fetches = fetches.clone();
+                tokio::spawn(async move {
+                    coalescer
+                        .run(|| async {
+                            let fetch = fetches.fetch_add(1, Ordering::SeqCst);
+                            tokio::time::sleep(Duration::from_millis(50)).await;
+                            fetch
+                        })
+                        .await
+                })
+            })
+            .collect();
+        for caller in callers {
+            assert_eq!(caller.await.unwrap(), 0);
+        }
+        assert_eq!(fetches.load(Ordering::SeqCst), 1);
+
+        // A caller after the shared fetch finished fetches afresh.
+        let next = coalescer
+            .run(|| async { fetches.fetch_add(1, Ordering::SeqCst) })
+            .await;
+        assert_eq!(next, 1);
+    }
+
+    #[tokio::test]
+    async fn waiters_fetch_again_when_the_fetching_caller_is_cancelled() {
+        let coalescer = Arc::new(Coalescer::default());
+
+        let leader = {
+            let coalescer = coalescer.clone();
+            tokio::spawn(async move { coalescer.run(std::future::pending::<u32>).await })
+        };
+        tokio::task::yield_now().await;
+        let waiter = {
+            let coalescer = coalescer.clone();
+            tokio::spawn(async move { coalescer.run(|| async { 7 }).await })
+        };
+        tokio::task::yield_now().await;
+        leader.abort();
+
+        assert_eq!(waiter.await.unwrap(), 7);
+    }
+}
@@ -0,0 +1,68 @@
+//! Cross-device connection lifecycle log
+
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Number of connection events retained
+pub const EVENT_LOG_CAPACITY: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ConnectionEventKind {
+    Connected,
+    Disconnected,
+    ReconnectAttempt,
+    ConnectionLost,
+    /// A background connect gave up; the detail holds the error
+    ConnectFailed,
+    /// Measurements kept failing past `max_consecutive_errors`, so the
+    /// device is being disconnected; the detail holds the last error
+    ErrorLimitReached,
+}
+
+/// One entry of the connection log
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct ConnectionEvent {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub device_id: String,
+    pub kind: ConnectionEventKind,
+    /// Extra context such as the port or an error message
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+/// Bounded log of connection events, oldest first
+pub struct EventLog {
+    events: VecDeque<ConnectionEvent>,
+    capacity: usize,
+}
+
+impl EventLog {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            events: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Record an event stamped now, evicting the oldest one at capacity
+    pub fn push(&mut self, device_id: &str, kind: ConnectionEventKind, detail: Option<String>) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.events.len() >= self.capacity {
+            self.events.pop_front();
+        }
+        self.events.push_back(ConnectionEvent {
+            timestamp: chrono::Utc::now(),
+            device_id: device_id.to_string(),
+            kind,
+            detail,
+        });
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &ConnectionEvent> {
+        self.events.iter()
+    }
+}
@@ -0,0 +1,687 @@
+//! Export of stored measurements as standalone files
+//!
+//! CSV has one row per reading, JSONL one measurement object per line. An
+//! optional preamble describes the device and session so a shared file is
+//! self-documenting: `#` comment lines in CSV, a leading `{"metadata": ...}`
+//! line in JSONL. InfluxDB line protocol carries the device in its tags
+//! instead and has no preamble, as does the binary NumPy format.
+//!
+//! JSONL is rendered line by line as it is sent, so a long recording is
+//! never rendered into one string. The readings themselves are still read
+//! from the store up front, so memory is bounded by the stored history.
+//!
+//! Timestamps in CSV and JSONL can be written in another time zone; the
+//! store keeps them in UTC either way. CSV spells units, states and
+//! attributes as the Fluke remote protocol does, e.g. `VDC` and `OL`.
+
+use super::{managed_device, AppState};
+use crate::device::{DeviceType, Measurement, MeasurementState};
+use crate::error::Result;
+use chrono::{DateTime, FixedOffset, Utc};
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Jsonl,
+    /// InfluxDB line protocol
+    Influx,
+    /// NumPy `.npy` structured array of timestamps and values
+    Npy,
+}
+
+impl ExportFormat {
+    /// Parse the `format` query parameter
+    pub fn parse(format: &str) -> std::result::Result<Self, String> {
+        match format {
+            "csv" => Ok(Self::Csv),
+            "jsonl" => Ok(Self::Jsonl),
+            "influx" => Ok(Self::Influx),
+            "npy" => Ok(Self::Npy),
+            _ => Err(format!(
+                "Invalid export format '{}', expected csv, jsonl, influx or npy",
+                format
+            )),
+        }
+    }
+
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Csv => "text/csv",
+            Self::Jsonl => "application/x-ndjson",
+            Self::Influx => "text/plain; charset=utf-8",
+            Self::Npy => "application/octet-stream",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Csv => "csv",
+            Self::Jsonl => "jsonl",
+            Self::Influx => "lp",
+            Self::Npy => "npy",
+        }
+    }
+}
+
+/// A rendered export and the name to download it as
+#[derive(Debug, Clone)]
+pub struct ExportFile {
+    /// `<serial number>_<export time>.<extension>`, e.g. `12345678_20240102T030405.csv`
+    pub filename: String,
+    pub body: Vec<u8>,
+}
+
+/// Lines of a JSONL export, each with its newline, rendered as they are
+/// taken
+pub type JsonlLines = Box<dyn Iterator<Item = Result<Vec<u8>>> + Send>;
+
+/// A JSONL export streamed line by line, and the name to download it as
+pub struct JsonlExport {
+    pub filename: String,
+    pub lines: JsonlLines,
+}
+
+/// Time zone of the timestamps in an export
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ExportTimezone {
+    #[default]
+    Utc,
+    /// Fixed offset from UTC, e.g. `+02:00`
+    Offset(FixedOffset),
+    /// IANA zone with its daylight saving rules, e.g. `Europe/Berlin`
+    Named(chrono_tz::Tz),
+}
+
+impl ExportTimezone {
+    /// Parse the `tz` query parameter
+    pub fn parse(tz: &str) -> std::result::Result<Self, String> {
+        if tz.eq_ignore_ascii_case("UTC") || tz == "Z" {
+            return Ok(Self::Utc);
+        }
+        if let Ok(offset) = tz.parse::<FixedOffset>() {
+            return Ok(Self::Offset(offset));
+        }
+        tz.parse::<chrono_tz::Tz>().map(Self::Named).map_err(|_| {
+            format!(
+                "Invalid time zone '{}', expected an IANA name such as Europe/Berlin or an offset such as +02:00",
+                tz
+            )
+        })
+    }
+
+    /// `time` as a local time in this zone, with its offset
+    pub fn localize(self, time: DateTime<Utc>) -> DateTime<FixedOffset> {
+        match self {
+            Self::Utc => time.fixed_offset(),
+            Self::Offset(offset) => time.with_timezone(&offset),
+            Self::Named(tz) => time.with_timezone(&tz).fixed_offset(),
+        }
+    }
+}
+
+/// Device and session described by an export preamble
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct ExportMetadata {
+    pub device_id: String,
+    pub device_type: DeviceType,
+    /// Model name reported by the device
+    pub label: String,
+    pub serial_number: String,
+    pub firmware: String,
+    /// When the device was connected
+    pub session_start: DateTime<FixedOffset>,
+    pub exported_at: DateTime<FixedOffset>,
+}
+
+const CSV_HEADER: &str = "timestamp,sequence,value,unit,state,attribute";
+
+/// Line protocol measurement name of every exported point
+const INFLUX_MEASUREMENT: &str = "measurement";
+
+/// Unit of a reading as the meter spells it, keeping a token the backend
+/// didn't recognise
+fn unit_symbol(m: &Measurement) -> &str {
+    m.unit.symbol()
+}
+
+/// Escape a line protocol tag value
+fn influx_tag(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        if matches!(c, ',' | '=' | ' ' | '\\') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Encode `measurements` as a version 1.0 `.npy` file holding a structured
+/// array of little-endian `f64` pairs: `timestamp` in seconds since the Unix
+/// epoch and `value`. Missing timestamps and readings other than `Normal`
+/// are NaN, so overload sentinels never end up in an analysis.
+fn npy(measurements: &[Measurement]) -> Vec<u8> {
+    const MAGIC: &[u8] = b"\x93NUMPY\x01\x00";
+    // Magic, version and the header length field precede the header, and
+    // the data starts at a multiple of 64 bytes.
+    const PREFIX_LEN: usize = MAGIC.len() + 2;
+    const ALIGNMENT: usize = 64;
+
+    let mut header = format!(
+        "{{'descr': [('timestamp', '<f8'), ('value', '<f8')], 'fortran_order': False, 'shape': ({},), }}",
+        measurements.len()
+    );
+    let padded_len = (PREFIX_LEN + header.len() + 1).div_ceil(ALIGNMENT) * ALIGNMENT;
+    header.extend(std::iter::repeat_n(
+        ' ',
+        padded_len - PREFIX_LEN - header.len() - 1,
+    ));
+    header.push('\n');
+
+    let mut out = Vec::with_capacity(padded_len + measurements.len() * 16);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    out.extend_from_slice(header.as_bytes());
+    for m in measurements {
+        let timestamp = m
+            .timestamp
+            .map_or(f64::NAN, |t| t.timestamp_micros() as f64 / 1e6);
+        let value = if m.state == MeasurementState::Normal {
+            m.value
+        } else {
+            f64::NAN
+        };
+        out.extend_from_slice(&timestamp.to_le_bytes());
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out
+}
+
+/// Render `measurements` of the device in `metadata` in `format`, preceded
+/// by the preamble if requested and the format has one. CSV and JSONL
+/// timestamps are written in `timezone`; the other formats count from the
+/// epoch.
+pub fn render(
+    format: ExportFormat,
+    measurements: &[Measurement],
+    metadata: &ExportMetadata,
+    preamble: bool,
+    timezone: ExportTimezone,
+) -> Result<Vec<u8>> {
+    let mut out = String::new();
+    let preamble = preamble.then_some(metadata);
+    match format {
+        ExportFormat::Csv => {
+            if let Some(metadata) = preamble {
+                // The metadata serializes to a flat object of strings.
+                if let serde_json::Value::Object(fields) = serde_json::to_value(metadata)? {
+                    for (key, value) in fields {
+                        let value = value.as_str().map(str::to_string).unwrap_or_default();
+                        let _ = writeln!(out, "# {}: {}", key, value);
+                    }
+                }
+            }
+            out.push_str(CSV_HEADER);
+            out.push('\n');
+            for m in measurements {
+                let _ = writeln!(
+                    out,
+                    "{},{},{},{},{},{}",
+                    m.timestamp
+                        .map(|t| timezone.localize(t).to_rfc3339())
+                        .unwrap_or_default(),
+                    m.sequence,
+                    m.value,
+                    unit_symbol(m),
+                    m.state.symbol(),
+                    m.attribute.symbol()
+                );
+            }
+        }
+        ExportFormat::Jsonl => {
+            if let Some(metadata) = preamble {
+                out.push_str(&jsonl_preamble(metadata)?);
+                out.push('\n');
+            }
+            for m in measurements {
+                out.push_str(&jsonl_line(m, timezone)?);
+                out.push('\n');
+            }
+        }
+        ExportFormat::Influx => {
+            let device = influx_tag(&metadata.device_id);
+            let serial = influx_tag(&metadata.serial_number);
+            // Points need a value and a time; the timestamp is in nanoseconds.
+            for m in measurements
+                .iter()
+                .filter(|m| m.state == MeasurementState::Normal)
+            {
+                let Some(timestamp) = m.timestamp.and_then(|t| t.timestamp_nanos_opt()) else {
+                    continue;
+                };
+                let _ = writeln!(
+                    out,
+                    "{},device={},serial={},unit={} value={} {}",
+                    INFLUX_MEASUREMENT,
+                    device,
+                    serial,
+                    influx_tag(unit_symbol(m)),
+                    m.value,
+                    timestamp
+                );
+            }
+        }
+        ExportFormat::Npy => return Ok(npy(measurements)),
+    }
+    Ok(out.into_bytes())
+}
+
+/// The `{"metadata": ...}` first line of a JSONL export, without newline
+fn jsonl_preamble(metadata: &ExportMetadata) -> Result<String> {
+    Ok(serde_json::to_string(
+        &serde_json::json!({ "metadata": metadata }),
+    )?)
+}
+
+/// One reading as a JSONL line, without newline
+fn jsonl_line(m: &Measurement, timezone: ExportTimezone) -> Result<String> {
+    match m.timestamp {
+        // UTC keeps the usual `Z` suffix of serialized readings.
+        Some(timestamp) if timezone != ExportTimezone::Utc => {
+            let mut line = serde_json::to_value(m)?;
+            line["timestamp"] = serde_json::to_value(timezone.localize(timestamp))?;
+            Ok(serde_json::to_string(&line)?)
+        }
+        _ => Ok(serde_json::to_string(m)?),
+    }
+}
+
+/// Characters of a serial number safe to put in a download filename
+fn filename_part(serial_number: &str) -> String {
+    serial_number
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'))
+        .collect()
+}
+
+/// Read the stored measurements of a device taken between `from` and `to`
+/// and describe the export of them, returning its metadata and filename
+async fn prepare(
+    device_id: String,
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    timezone: ExportTimezone,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<(Vec<Measurement>, ExportMetadata, String), String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let measurements = managed_device
+        .store
+        .lock()
+        .await
+        .range(&device_id, from, to)
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+
+    let metadata = ExportMetadata {
+        device_id: device_id.clone(),
+        device_type: managed_device.device_type,
+        label: managed_device.info.model.clone(),
+        serial_number: managed_device.info.serial_number.clone(),
+        firmware: managed_device.info.software_version.clone(),
+        session_start: timezone.localize(managed_device.connected_at),
+        exported_at: timezone.localize(Utc::now()),
+    };
+    let filename = format!(
+        "{}_{}.{}",
+        filename_part(&metadata.serial_number),
+        metadata.exported_at.format("%Y%m%dT%H%M%S"),
+        format.extension()
+    );
+    Ok((measurements, metadata, filename))
+}
+
+/// Export the stored measurements of a device taken between `from` and
+/// `to`, with timestamps in `timezone`
+pub async fn export(
+    device_id: String,
+    format: ExportFormat,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    preamble: bool,
+    timezone: ExportTimezone,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<ExportFile, String> {
+    let (measurements, metadata, filename) =
+        prepare(device_id, format, from, to, timezone, state).await?;
+    let body = render(format, &measurements, &metadata, preamble, timezone)
+        .map_err(|e| format!("Failed to export measurements: {}", e))?;
+    Ok(ExportFile { filename, body })
+}
+
+/// Export the stored measurements of a device as JSONL, rendering each
+/// line only as the caller takes it. The measurements are read from the
+/// store before the first line.
+pub async fn export_jsonl(
+    device_id: String,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    preamble: bool,
+    timezone: ExportTimezone,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<JsonlExport, String> {
+    let (measurements, metadata, filename) =
+        prepare(device_id, ExportFormat::Jsonl, from, to, timezone, state).await?;
+    let preamble = preamble
+        .then(|| jsonl_preamble(&metadata))
+        .transpose()
+        .map_err(|e| format!("Failed to export measurements: {}", e))?;
+    let readings = measurements
+        .into_iter()
+        .map(move |m| jsonl_line(&m, timezone));
+    let lines = preamble.map(Ok).into_iter().chain(readings).map(|line| {
+        line.map(|mut line| {
+            line.push('\n');
+            line.into_bytes()
+        })
+    });
+    Ok(JsonlExport {
+        filename,
+        lines: Box::new(lines),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::take_measurement;
+    use crate::communication::tests::{connect_mock, shared_state};
+    use crate::device::{MeasurementAttribute, Unit};
+
+    /// 2024-03-14 09:30:00 UTC
+    const EPOCH_SECS: i64 = 1_710_408_600;
+
+    fn metadata(device_id: &str, serial_number: &str) -> ExportMetadata {
+        let start = DateTime::from_timestamp(EPOCH_SECS, 0)
+            .unwrap()
+            .fixed_offset();
+        ExportMetadata {
+            device_id: device_id.to_string(),
+            device_type: DeviceType::Fluke289,
+            label: "FLUKE 289".to_string(),
+            serial_number: serial_number.to_string(),
+            firmware: "V1.16".to_string(),
+            session_start: start,
+            exported_at: start,
+        }
+    }
+
+    /// A reading taken `offset_secs` after `EPOCH_SECS`
+    fn reading(
+        sequence: u64,
+        value: f64,
+        state: MeasurementState,
+        offset_secs: i64,
+    ) -> Measurement {
+        let mut m = Measurement::new(value, Unit::VoltDc, state, MeasurementAttribute::None);
+        m.sequence = sequence;
+        m.timestamp = DateTime::from_timestamp(EPOCH_SECS + offset_secs, 0);
+        m
+    }
+
+    #[tokio::test]
+    async fn every_jsonl_line_is_a_measurement() {
+        let state = shared_state();
+        let device = connect_mock("/dev/ttyTEST0", &state).await.unwrap();
+        for _ in 0..3 {
+            take_measurement(&device.id, None, false, None, &state)
+                .await
+                .unwrap();
+        }
+
+        let export = export_jsonl(device.id, None, None, false, ExportTimezone::Utc, &state)
+            .await
+            .unwrap();
+        let lines: Vec<Vec<u8>> = export.lines.collect::<Result<_>>().unwrap();
+
+        assert_eq!(lines.len(), 3);
+        for line in lines {
+            assert_eq!(line.last(), Some(&b'\n'));
+            serde_json::from_slice::<Measurement>(&line).unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn jsonl_preamble_comes_first() {
+        let state = shared_state();
+        let device = connect_mock("/dev/ttyTEST0", &state).await.unwrap();
+        take_measurement(&device.id, None, false, None, &state)
+            .await
+            .unwrap();
+
+        let export = export_jsonl(
+            device.id.clone(),
+            None,
+            None,
+            true,
+            ExportTimezone::Utc,
+            &state,
+        )
+        .await
+        .unwrap();
+        let lines: Vec<Vec<u8>> = export.lines.collect::<Result<_>>().unwrap();
+
+        let preamble: serde_json::Value = serde_json::from_slice(&lines[0]).unwrap();
+        let metadata = preamble["metadata"].as_object().unwrap();
+        // Multi-word fields are camel case with the `camel-case` feature
+        let field = |snake: &str, camel: &str| {
+            metadata
+                .get(snake)
+                .or_else(|| metadata.get(camel))
+                .cloned()
+                .unwrap_or_default()
+        };
+        assert_eq!(metadata.len(), 7, "{:?}", metadata);
+        assert_eq!(field("device_id", "deviceId"), device.id.as_str());
+        assert_eq!(field("device_type", "deviceType"), "Mock");
+        assert_eq!(metadata["label"], "MOCK-MULTIMETER");
+        assert_eq!(field("serial_number", "serialNumber"), "MOCK123456");
+        assert_eq!(metadata["firmware"], "V1.0.0-MOCK");
+        for (snake, camel) in [
+            ("session_start", "sessionStart"),
+            ("exported_at", "exportedAt"),
+        ] {
+            let time = field(snake, camel);
+            let time = DateTime::parse_from_rfc3339(time.as_str().unwrap()).unwrap();
+            assert_eq!(time.offset().local_minus_utc(), 0);
+        }
+        serde_json::from_slice::<Measurement>(&lines[1]).unwrap();
+    }
+
+    #[test]
+    fn influx_lines_are_well_formed() {
+        let measurements = [
+            reading(1, 1.5, MeasurementState::Normal, 0),
+            reading(2, 0.0, MeasurementState::Overload, 1),
+            reading(3, -0.25, MeasurementState::Normal, 2),
+        ];
+        let out = render(
+            ExportFormat::Influx,
+            &measurements,
+            &metadata("dev 1", "SN,1=2"),
+            true,
+            ExportTimezone::Utc,
+        )
+        .unwrap();
+        let out = String::from_utf8(out).unwrap();
+
+        // No preamble, and the overload has no value to write
+        assert_eq!(
+            out,
+            "measurement,device=dev\\ 1,serial=SN\\,1\\=2,unit=VDC value=1.5 1710408600000000000\n\
+             measurement,device=dev\\ 1,serial=SN\\,1\\=2,unit=VDC value=-0.25 1710408602000000000\n"
+        );
+        for line in out.lines() {
+            // measurement+tags, fields and timestamp, split on unescaped spaces
+            let parts: Vec<_> = line
+                .replace("\\ ", "_")
+                .split(' ')
+                .map(String::from)
+                .collect();
+            assert_eq!(parts.len(), 3, "{}", line);
+            assert!(parts[1].starts_with("value="), "{}", line);
+            parts[2].parse::<i64>().unwrap();
+        }
+    }
+
+    #[test]
+    fn npy_header_and_data_round_trip() {
+        let mut unstamped = reading(3, 2.5, MeasurementState::Normal, 0);
+        unstamped.timestamp = None;
+        let measurements = [
+            reading(1, 1.5, MeasurementState::Normal, 0),
+            reading(2, 9.99e37, MeasurementState::Overload, 1),
+            unstamped,
+        ];
+        let out = render(
+            ExportFormat::Npy,
+            &measurements,
+            &metadata("device_0001", "SN1"),
+            true,
+            ExportTimezone::Utc,
+        )
+        .unwrap();
+
+        assert_eq!(&out[..8], b"\x93NUMPY\x01\x00");
+        let header_len = u16::from_le_bytes([out[8], out[9]]) as usize;
+        let data_start = 10 + header_len;
+        assert_eq!(data_start % 64, 0);
+        let header = std::str::from_utf8(&out[10..data_start]).unwrap();
+        assert_eq!(
+            header.trim_end(),
+            "{'descr': [('timestamp', '<f8'), ('value', '<f8')], 'fortran_order': False, 'shape': (3,), }"
+        );
+        assert!(header.ends_with('\n'));
+
+        let data: Vec<f64> = out[data_start..]
+            .chunks_exact(8)
+            .map(|bytes| f64::from_le_bytes(bytes.try_into().unwrap()))
+            .collect();
+        assert_eq!(data.len(), 6);
+        assert_eq!(data[..2], [EPOCH_SECS as f64, 1.5]);
+        // The overload has no value and the unstamped reading no time
+        assert_eq!(data[2], (EPOCH_SECS + 1) as f64);
+        assert!(data[3].is_nan());
+        assert!(data[4].is_nan());
+        assert_eq!(data[5], 2.5);
+    }
+
+    #[test]
+    fn timestamps_are_written_with_the_zone_offset() {
+        let offset = ExportTimezone::parse("+05:30").unwrap();
+        assert!(matches!(offset, ExportTimezone::Offset(_)), "{:?}", offset);
+        let out = render(
+            ExportFormat::Csv,
+            &[reading(1, 1.5, MeasurementState::Normal, 0)],
+            &metadata("device_0001", "SN1"),
+            false,
+            offset,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            format!(
+                "{}\n2024-03-14T15:00:00+05:30,1,1.5,VDC,NORMAL,NONE\n",
+                CSV_HEADER
+            )
+        );
+
+        // Berlin moves from +01:00 to +02:00 on 31 March
+        let berlin = ExportTimezone::parse("Europe/Berlin").unwrap();
+        assert_eq!(berlin, ExportTimezone::Named(chrono_tz::Europe::Berlin));
+        let out = render(
+            ExportFormat::Jsonl,
+            &[
+                reading(1, 1.5, MeasurementState::Normal, 0),
+                reading(2, 1.5, MeasurementState::Normal, 17 * 86_400),
+            ],
+            &metadata("device_0001", "SN1"),
+            false,
+            berlin,
+        )
+        .unwrap();
+        let times: Vec<String> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                let line: serde_json::Value = serde_json::from_str(line).unwrap();
+                line["timestamp"].as_str().unwrap().to_string()
+            })
+            .collect();
+        assert_eq!(
+            times,
+            ["2024-03-14T10:30:00+01:00", "2024-03-31T11:30:00+02:00"]
+        );
+    }
+
+    #[tokio::test]
+    async fn csv_of_an_empty_buffer_is_only_the_header() {
+        let state = shared_state();
+        let device = connect_mock("/dev/ttyTEST0", &state).await.unwrap();
+
+        let export = export(
+            device.id,
+            ExportFormat::Csv,
+            None,
+            None,
+            false,
+            ExportTimezone::Utc,
+            &state,
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(
+            String::from_utf8(export.body).unwrap(),
+            format!("{}\n", CSV_HEADER)
+        );
+        assert!(export.filename.ends_with(".csv"), "{}", export.filename);
+    }
+
+    #[test]
+    fn csv_rows_use_the_meter_names_of_states() {
+        let mut unrecognised = reading(3, 0.5, MeasurementState::Normal, 2);
+        unrecognised.unit = Unit::Unknown("VAC_HF".to_string());
+        let measurements = [
+            reading(1, 1.5, MeasurementState::Normal, 0),
+            reading(2, 9.99e37, MeasurementState::Overload, 1),
+            unrecognised,
+        ];
+
+        let out = render(
+            ExportFormat::Csv,
+            &measurements,
+            &metadata("device_0001", "SN1"),
+            false,
+            ExportTimezone::Utc,
+        )
+        .unwrap();
+
+        let out = String::from_utf8(out).unwrap();
+        let rows: Vec<_> = out.lines().collect();
+        assert_eq!(
+            rows,
+            [
+                CSV_HEADER,
+                "2024-03-14T09:30:00+00:00,1,1.5,VDC,NORMAL,NONE",
+                "2024-03-14T09:30:01+00:00,2,99900000000000000000000000000000000000,VDC,OL,NONE",
+                "2024-03-14T09:30:02+00:00,3,0.5,VAC_HF,NORMAL,NONE",
+            ]
+        );
+    }
+}
@@ -0,0 +1,106 @@
+//! Grafana SimpleJSON datasource support
+//!
+//! Grafana's SimpleJSON plugin pings `/`, lists metrics with `/search` and
+//! fetches series with `/query`. Targets are device ids; datapoints are
+//! `[value, epoch_ms]` pairs taken from the measurement store.
+
+use super::{get_connected_devices, get_history, AppState};
+use crate::device::{Measurement, MeasurementState};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Body of a SimpleJSON `/query` request; unknown fields are ignored
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GrafanaQuery {
+    pub range: GrafanaRange,
+    pub targets: Vec<GrafanaTarget>,
+    #[serde(default)]
+    pub max_data_points: Option<usize>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrafanaRange {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GrafanaTarget {
+    /// Device id
+    pub target: String,
+}
+
+/// One time series in the SimpleJSON response
+#[derive(Debug, Clone, Serialize)]
+pub struct GrafanaSeries {
+    pub target: String,
+    pub datapoints: Vec<(f64, i64)>,
+}
+
+/// `[value, epoch_ms]` pairs of the stamped `Normal` readings, thinned to
+/// at most `max_points`
+fn datapoints(measurements: &[Measurement], max_points: Option<usize>) -> Vec<(f64, i64)> {
+    let points: Vec<(f64, i64)> = measurements
+        .iter()
+        .filter(|m| m.state == MeasurementState::Normal)
+        .filter_map(|m| Some((m.value, m.timestamp?.timestamp_millis())))
+        .collect();
+
+    match max_points {
+        Some(max) if max > 0 && points.len() > max => {
+            let stride = points.len().div_ceil(max);
+            points.into_iter().step_by(stride).collect()
+        }
+        _ => points,
+    }
+}
+
+/// Series of one device between `from` and `to`
+pub async fn series(
+    device_id: String,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    max_points: Option<usize>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<GrafanaSeries, String> {
+    let measurements = get_history(device_id.clone(), from, to, None, state).await?;
+    Ok(GrafanaSeries {
+        target: device_id,
+        datapoints: datapoints(&measurements, max_points),
+    })
+}
+
+/// Answer a SimpleJSON `/query` with one series per target
+pub async fn query(
+    query: GrafanaQuery,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<GrafanaSeries>, String> {
+    let mut result = Vec::with_capacity(query.targets.len());
+    for target in query.targets {
+        result.push(
+            series(
+                target.target,
+                Some(query.range.from),
+                Some(query.range.to),
+                query.max_data_points,
+                state,
+            )
+            .await?,
+        );
+    }
+    Ok(result)
+}
+
+/// Metric names offered by `/search`: the connected device ids
+pub async fn search(state: &Arc<Mutex<AppState>>) -> std::result::Result<Vec<String>, String> {
+    let mut ids: Vec<String> = get_connected_devices(state)
+        .await?
+        .into_iter()
+        .map(|device| device.id)
+        .collect();
+    ids.sort();
+    Ok(ids)
+}
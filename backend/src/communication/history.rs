@@ -0,0 +1,272 @@
+//! Per-device measurement history
+//!
+//! Keeps a bounded buffer of recent readings and derives trends and
+//! statistics from it.
+
+use crate::device::{Measurement, MeasurementState, Unit};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of measurements retained per device
+pub const DEFAULT_HISTORY_CAPACITY: usize = 1000;
+
+/// Bounded buffer of recent measurements, oldest first
+pub struct MeasurementHistory {
+    samples: VecDeque<Measurement>,
+    capacity: usize,
+}
+
+impl MeasurementHistory {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Append a measurement, evicting the oldest one at capacity
+    pub fn push(&mut self, measurement: Measurement) {
+        if self.capacity == 0 {
+            return;
+        }
+        while self.samples.len() >= self.capacity {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(measurement);
+    }
+
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Measurement> {
+        self.samples.iter()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Change the capacity, dropping the oldest samples beyond it
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        while self.samples.len() > capacity {
+            self.samples.pop_front();
+        }
+        self.samples.shrink_to(capacity);
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+}
+
+/// Rate of change over the recent history
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Trend {
+    /// Units per second, `None` when there is not enough data
+    pub slope: Option<f64>,
+    pub unit: Option<Unit>,
+    pub sample_count: usize,
+    pub window_secs: f64,
+}
+
+impl MeasurementHistory {
+    /// Least-squares slope over the `window` preceding the newest sample
+    pub fn trend(&self, window: Duration) -> Trend {
+        trend(self.samples.iter(), window)
+    }
+}
+
+/// Least-squares slope over the `window` preceding the newest of `samples`,
+/// which are ordered oldest first.
+///
+/// Only `Normal` samples in the same unit as the newest `Normal` sample
+/// contribute; older samples in another unit end the window.
+pub fn trend<'a>(
+    samples: impl DoubleEndedIterator<Item = &'a Measurement>,
+    window: Duration,
+) -> Trend {
+    let mut points = Vec::new();
+    let mut unit = None;
+
+    let normal = samples
+        .rev()
+        .filter(|m| m.state == MeasurementState::Normal);
+    let mut newest = None;
+    for measurement in normal {
+        let Some(timestamp) = measurement.timestamp else {
+            continue;
+        };
+        if *unit.get_or_insert(measurement.unit.clone()) != measurement.unit {
+            break;
+        }
+        let newest = *newest.get_or_insert(timestamp);
+        let age = (newest - timestamp).to_std().unwrap_or_default();
+        if age > window {
+            break;
+        }
+        points.push((-age.as_secs_f64(), measurement.value));
+    }
+
+    Trend {
+        slope: linear_regression_slope(&points),
+        unit,
+        sample_count: points.len(),
+        window_secs: window.as_secs_f64(),
+    }
+}
+
+/// Span of the readings a statistic covers
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct SampleWindow {
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Value statistics over the recent history; the aggregates are `None`
+/// when no in-range reading contributed
+#[derive(Debug, Clone, Default, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Statistics {
+    pub unit: Option<Unit>,
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    pub mean: Option<f64>,
+    /// Population standard deviation
+    pub stddev: Option<f64>,
+    /// In-range readings aggregated
+    pub count: usize,
+    /// Overloads and other readings without a usable value, left out of
+    /// the aggregates
+    pub excluded_count: usize,
+    pub sample_window: SampleWindow,
+}
+
+/// Statistics of `samples`, ordered oldest first, back to the last change
+/// of unit.
+///
+/// As for `trend`, the newest sample picks the unit and an older sample in
+/// another unit ends the window. Only `Normal` readings are aggregated;
+/// the others are counted in `excluded_count`.
+pub fn statistics<'a>(samples: impl DoubleEndedIterator<Item = &'a Measurement>) -> Statistics {
+    let mut stats = Statistics::default();
+    let mut values = Vec::new();
+    for measurement in samples.rev() {
+        if *stats.unit.get_or_insert(measurement.unit.clone()) != measurement.unit {
+            break;
+        }
+        if let Some(timestamp) = measurement.timestamp {
+            stats.sample_window.to.get_or_insert(timestamp);
+            stats.sample_window.from = Some(timestamp);
+        }
+        if measurement.state == MeasurementState::Normal {
+            values.push(measurement.value);
+        } else {
+            stats.excluded_count += 1;
+        }
+    }
+    if values.is_empty() {
+        return stats;
+    }
+
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / n;
+    stats.count = values.len();
+    stats.min = values.iter().copied().reduce(f64::min);
+    stats.max = values.iter().copied().reduce(f64::max);
+    stats.mean = Some(mean);
+    stats.stddev = Some(variance.sqrt());
+    stats
+}
+
+fn linear_regression_slope(points: &[(f64, f64)]) -> Option<f64> {
+    if points.len() < 2 {
+        return None;
+    }
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let (covariance, variance) = points.iter().fold((0.0, 0.0), |(cov, var), (x, y)| {
+        let dx = x - mean_x;
+        (cov + dx * (y - mean_y), var + dx * dx)
+    });
+
+    if variance == 0.0 {
+        None
+    } else {
+        Some(covariance / variance)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::OVERLOAD_VALUE;
+    use crate::device::MeasurementAttribute;
+
+    fn reading(value: f64, state: MeasurementState) -> Measurement {
+        Measurement::new(value, Unit::VoltDc, state, MeasurementAttribute::None)
+    }
+
+    #[test]
+    fn overloads_are_excluded_from_the_aggregates() {
+        let samples = [
+            reading(1.0, MeasurementState::Normal),
+            reading(OVERLOAD_VALUE, MeasurementState::Overload),
+            reading(3.0, MeasurementState::Normal),
+        ];
+
+        let stats = statistics(samples.iter());
+        assert_eq!(stats.count, 2);
+        assert_eq!(stats.excluded_count, 1);
+        assert_eq!(stats.mean, Some(2.0));
+        assert_eq!(stats.max, Some(3.0));
+    }
+
+    #[test]
+    fn only_overloads_leave_no_aggregates() {
+        let samples = [reading(OVERLOAD_VALUE, MeasurementState::Overload)];
+
+        let stats = statistics(samples.iter());
+        assert_eq!(stats.count, 0);
+        assert_eq!(stats.excluded_count, 1);
+        assert_eq!(stats.mean, None);
+    }
+
+    #[test]
+    fn trend_of_a_ramp_is_its_slope() {
+        let start = Utc::now();
+        let mut history = MeasurementHistory::new(DEFAULT_HISTORY_CAPACITY);
+        for second in 0..10 {
+            let mut measurement = reading(1.0 + 0.5 * second as f64, MeasurementState::Normal);
+            measurement.timestamp = Some(start + chrono::Duration::seconds(second));
+            history.push(measurement);
+        }
+
+        let trend = history.trend(Duration::from_secs(60));
+        assert!(
+            (trend.slope.unwrap() - 0.5).abs() < 1e-9,
+            "{:?}",
+            trend.slope
+        );
+        assert_eq!(trend.sample_count, 10);
+        assert_eq!(trend.unit, Some(Unit::VoltDc));
+
+        // A window of 4 s only sees the last 5 samples.
+        assert_eq!(history.trend(Duration::from_secs(4)).sample_count, 5);
+    }
+
+    #[test]
+    fn a_single_sample_has_no_trend() {
+        let mut history = MeasurementHistory::new(DEFAULT_HISTORY_CAPACITY);
+        history.push(reading(1.0, MeasurementState::Normal));
+        assert_eq!(history.trend(Duration::from_secs(60)).slope, None);
+    }
+}
@@ -0,0 +1,3310 @@
+//! IPC communication module
+//!
+//! This module handles communication between the Rust backend and the TypeScript frontend
+//! using Tauri's IPC system.
+
+pub mod captures;
+mod coalesce;
+pub mod events;
+pub mod export;
+pub mod grafana;
+pub mod history;
+pub mod polling;
+pub mod profile;
+pub mod replay;
+mod session;
+pub mod settings;
+pub mod shutdown;
+mod smoothing;
+pub mod store;
+pub mod stream;
+pub mod summary;
+pub mod timing;
+
+use crate::config::{Config, DisplayPreferences, Limits, SignInversion, Smoothing};
+use crate::conversion::validate_temperature_offset;
+use crate::device::function::MeasurementFunction;
+use crate::device::mock::MockConfig;
+use crate::device::wire::{WireChunk, WireTap, WIRE_TAP_CAPACITY};
+use crate::device::{
+    create_device, device_commands, fluke, CalibrationInfo, Capabilities, CommandInfo, DbReference,
+    Device, DeviceInfo, DeviceOptions, DeviceSettings, DeviceType, DisplayData, DisplayReading,
+    FrequencyFilter, Measurement, MeasurementState, RecordingSummary, SavedMeasurement,
+    SavedSetupInfo, SecondaryFunction, SelfTestResult, TimestampSource, Unit,
+};
+use crate::error::Error;
+use captures::{Capture, CaptureStore};
+use coalesce::Coalescer;
+use events::{ConnectionEvent, ConnectionEventKind, EventLog, EVENT_LOG_CAPACITY};
+use history::{Statistics, Trend, DEFAULT_HISTORY_CAPACITY};
+use polling::{Subscribers, TimestampGrid};
+use serde::Serialize;
+pub use session::VerdictCounts;
+use session::{DeviceSession, MeasurementStats};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use store::{InMemoryStore, MeasurementStore, MemoryUsage};
+use summary::SessionSummary;
+use timing::{CommandTiming, CommandTimings};
+use tokio::sync::{broadcast, Mutex, MutexGuard, Notify, Semaphore, SemaphorePermit};
+
+/// A registered device.
+///
+/// Cloning is cheap, so handlers take a copy and release the global state
+/// lock before talking to the hardware. Lock order is `device` before
+/// `session` before `store`, and none is awaited while the global lock is
+/// held.
+#[derive(Clone)]
+struct ManagedDevice {
+    device_type: DeviceType,
+    info: DeviceInfo,
+    capabilities: Capabilities,
+    connected_at: chrono::DateTime<chrono::Utc>,
+    port: Option<String>,
+    /// Bus address on `port`, which other addressed devices may share
+    address: Option<u8>,
+    /// Clock stamping live readings, `Host` when the device has none
+    timestamp_source: TimestampSource,
+    /// Serialises commands to the device. Tokio's mutex is fair: waiters
+    /// are woken in the order they called `lock`, so commands run in the
+    /// order they arrived and none is starved by later ones.
+    device: SharedDevice,
+    /// Bounds the commands waiting for `device`
+    queue: Arc<Semaphore>,
+    queue_limit: usize,
+    /// Set while a reset is queued or running; other commands are refused
+    /// rather than run against a rebooting meter
+    resetting: Arc<AtomicBool>,
+    /// Signalled by `disconnect_device`; queued and in-flight operations
+    /// give up rather than run against a port about to close
+    disconnecting: Arc<Cancellation>,
+    /// Reopen the device with backoff when its connection drops
+    auto_reconnect: bool,
+    session: Arc<Mutex<DeviceSession>>,
+    stats: Arc<MeasurementStats>,
+    store: SharedStore,
+    /// Live consumers of every recorded measurement
+    subscribers: Arc<Subscribers>,
+    timings: Arc<CommandTimings>,
+    /// Raw serial traffic, for debugging endpoints
+    wire_tap: WireTap,
+    /// Live reading shared by concurrent measurement requests
+    reading: Arc<Coalescer<std::result::Result<Measurement, String>>>,
+}
+
+type SharedStore = Arc<Mutex<Box<dyn MeasurementStore>>>;
+type SharedDevice = Arc<Mutex<Box<dyn Device>>>;
+
+/// A disconnected device whose port is kept open for `port_linger_ms`
+struct ParkedDevice {
+    device_type: DeviceType,
+    device: SharedDevice,
+}
+
+/// Global application state
+pub struct AppState {
+    devices: HashMap<String, ManagedDevice>,
+    next_device_id: u32,
+    config: Config,
+    store: SharedStore,
+    events: EventLog,
+    captures: CaptureStore,
+    /// Background connects in progress, by the id they will register as
+    connecting: HashMap<String, PendingConnection>,
+    /// Recently disconnected devices by port and address
+    parked: HashMap<(String, Option<u8>), ParkedDevice>,
+    /// Ids whose connect failed, so they aren't reported as disconnected
+    failed_connects: HashSet<String>,
+}
+
+impl Default for AppState {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AppState {
+    pub fn new() -> Self {
+        Self::with_store(
+            Config::default(),
+            Box::new(InMemoryStore::new(DEFAULT_HISTORY_CAPACITY, None)),
+        )
+    }
+
+    /// Create the state, opening the measurement store the config selects
+    pub fn with_config(config: Config) -> crate::error::Result<Self> {
+        let store = store::open(&config.store)?;
+        Ok(Self::with_store(config, store))
+    }
+
+    pub fn with_store(config: Config, store: Box<dyn MeasurementStore>) -> Self {
+        Self {
+            devices: HashMap::new(),
+            next_device_id: 1,
+            captures: CaptureStore::new(config.max_captures),
+            config,
+            store: Arc::new(Mutex::new(store)),
+            events: EventLog::new(EVENT_LOG_CAPACITY),
+            connecting: HashMap::new(),
+            parked: HashMap::new(),
+            failed_connects: HashSet::new(),
+        }
+    }
+
+    fn device(&self, device_id: &str) -> std::result::Result<ManagedDevice, String> {
+        self.devices
+            .get(device_id)
+            .cloned()
+            .ok_or_else(|| self.unavailable(device_id))
+    }
+
+    /// Error for an id without a registered device: not ready while it is
+    /// connecting, after its connect failed or after it was disconnected,
+    /// otherwise not found
+    fn unavailable(&self, device_id: &str) -> String {
+        let state = if self.connecting.contains_key(device_id) {
+            "connecting"
+        } else if self.failed_connects.contains(device_id) {
+            "not connected, its connect failed"
+        } else if self.was_issued(device_id) && !self.devices.contains_key(device_id) {
+            "disconnected"
+        } else {
+            return format!("Device {} not found", device_id);
+        };
+        Error::NotReady(format!("device {} is {}", device_id, state)).to_string()
+    }
+
+    /// Refuse `port` if a connected or connecting device holds it, unless both are
+    /// addressed and at different addresses on the shared bus
+    fn check_port_free(&self, port: &str, address: Option<u8>) -> std::result::Result<(), String> {
+        let devices = self
+            .devices
+            .iter()
+            .map(|(id, device)| (id, device.port.as_deref(), device.address));
+        let connecting = self
+            .connecting
+            .iter()
+            .map(|(id, pending)| (id, pending.port.as_deref(), pending.address));
+        for (id, held_port, held_address) in devices.chain(connecting) {
+            if held_port != Some(port) {
+                continue;
+            }
+            match (held_address, address) {
+                (Some(existing), Some(address)) if existing == address => {
+                    return Err(format!(
+                        "Address {} on {} is already in use by {}",
+                        address, port, id
+                    ));
+                }
+                (Some(_), Some(_)) => {}
+                _ => {
+                    return Err(format!(
+                        "Port {} is already in use by {}; devices sharing a port each need an address",
+                        port, id
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Exclusive access to a device, holding a slot in its command queue
+struct DeviceGuard<'a> {
+    device: MutexGuard<'a, Box<dyn Device>>,
+    _slot: SemaphorePermit<'a>,
+}
+
+impl std::ops::Deref for DeviceGuard<'_> {
+    type Target = Box<dyn Device>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.device
+    }
+}
+
+impl std::ops::DerefMut for DeviceGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.device
+    }
+}
+
+/// One-shot signal that a device is going away
+#[derive(Default)]
+struct Cancellation {
+    cancelled: AtomicBool,
+    notify: Notify,
+}
+
+impl Cancellation {
+    fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Release);
+        self.notify.notify_waiters();
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Acquire)
+    }
+
+    /// Wait out `duration`, returning early with an error on cancellation
+    async fn sleep(&self, duration: Duration) -> std::result::Result<(), String> {
+        let notified = self.notify.notified();
+        if self.is_cancelled() {
+            return Err(disconnecting_error());
+        }
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => Ok(()),
+            _ = notified => Err(disconnecting_error()),
+        }
+    }
+}
+
+/// Id of the `number`th device connected, e.g. `device_0001`
+fn format_device_id(number: u32) -> String {
+    format!("device_{:04}", number)
+}
+
+fn disconnecting_error() -> String {
+    Error::Connection("device is being disconnected".to_string()).to_string()
+}
+
+/// Clears the reset flag when the reset finishes or is abandoned
+struct ResetInProgress<'a>(&'a AtomicBool);
+
+impl Drop for ResetInProgress<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
+
+impl ManagedDevice {
+    /// Queue for the device, failing right away when the queue is full
+    /// rather than piling up requests behind a slow or stuck device, and
+    /// while the device is resetting
+    async fn lock_device(&self) -> std::result::Result<DeviceGuard<'_>, String> {
+        if self.resetting.load(Ordering::Acquire) {
+            return Err(Error::Busy("device is resetting".to_string()).to_string());
+        }
+        self.queue_slot().await
+    }
+
+    /// Live consumers a disconnect would cut off, e.g. `2 stream subscribers`
+    fn consumers(&self) -> Vec<String> {
+        let mut consumers = Vec::new();
+        let subscribers = self.subscribers.count();
+        if subscribers > 0 {
+            consumers.push(format!("{} stream subscribers", subscribers));
+        }
+        let wire_listeners = self.wire_tap.receiver_count();
+        if wire_listeners > 0 {
+            consumers.push(format!("{} wire traffic listeners", wire_listeners));
+        }
+        consumers
+    }
+
+    /// Await a device operation, recording its latency under `command`
+    async fn timed<T>(&self, command: &str, operation: impl std::future::Future<Output = T>) -> T {
+        let started = std::time::Instant::now();
+        let result = operation.await;
+        self.timings.record(command, started.elapsed());
+        result
+    }
+
+    async fn queue_slot(&self) -> std::result::Result<DeviceGuard<'_>, String> {
+        let slot = self.queue.try_acquire().map_err(|_| {
+            Error::Busy(format!(
+                "too many commands queued (limit {})",
+                self.queue_limit
+            ))
+            .to_string()
+        })?;
+        let device = self.device.lock().await;
+        // The device may have been disconnected while this command waited.
+        if self.disconnecting.is_cancelled() {
+            return Err(disconnecting_error());
+        }
+        Ok(DeviceGuard {
+            device,
+            _slot: slot,
+        })
+    }
+
+    fn list_item(&self, id: String) -> DeviceListItem {
+        // A device busy with a command is in use and therefore connected;
+        // don't make status queries wait for the serial round trip.
+        let connected = match self.device.try_lock() {
+            Ok(device) => device.is_connected(),
+            Err(_) => true,
+        };
+
+        DeviceListItem {
+            id,
+            device_type: self.device_type,
+            info: self.info.clone(),
+            capabilities: self.capabilities,
+            port: self.port.clone(),
+            address: self.address,
+            timestamp_source: self.timestamp_source,
+            connected,
+            subscribers: self.subscribers.count(),
+            paused: self.subscribers.is_paused(),
+            measurement_count: self.stats.measurement_count(),
+            total_samples: self.stats.total_samples(),
+        }
+    }
+
+    /// Process a reading through the session, count, store and publish it.
+    /// Callers hold the device so sequence order matches the order the
+    /// readings were taken in.
+    async fn record(
+        &self,
+        device_id: &str,
+        measurement: Measurement,
+        global_preferences: DisplayPreferences,
+    ) -> Measurement {
+        let measurement = self
+            .session
+            .lock()
+            .await
+            .record(measurement, global_preferences);
+        self.stats.record(1);
+        if let Err(error) = self.store.lock().await.insert(device_id, &measurement) {
+            tracing::warn!(device_id, %error, "Failed to store measurement");
+        }
+        self.subscribers.publish(&measurement);
+        measurement
+    }
+}
+
+/// Look up a registered device, releasing the state lock before returning
+async fn managed_device(
+    device_id: &str,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<ManagedDevice, String> {
+    state.lock().await.device(device_id)
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DisconnectResponse {
+    pub message: String,
+    pub summary: SessionSummary,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct ConnectDeviceResponse {
+    pub id: String,
+    pub device_type: DeviceType,
+    pub info: DeviceInfo,
+    pub capabilities: Capabilities,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DeviceListItem {
+    pub id: String,
+    pub device_type: DeviceType,
+    pub info: DeviceInfo,
+    pub capabilities: Capabilities,
+    pub port: Option<String>,
+    pub address: Option<u8>,
+    pub timestamp_source: TimestampSource,
+    pub connected: bool,
+    /// Live measurement subscribers, e.g. stream clients
+    pub subscribers: usize,
+    /// Background polling is paused
+    pub paused: bool,
+    /// Successful measurement requests since connect or the last reset
+    pub measurement_count: u64,
+    /// Readings returned by those requests
+    pub total_samples: u64,
+}
+
+/// Device type names accepted by the API
+pub const DEVICE_TYPE_NAMES: [&str; 4] = ["Fluke289", "Fluke287", "KeysightU124x", "Mock"];
+
+/// Map a device type string from the API onto a `DeviceType`
+pub fn parse_device_type(device_type: &str) -> std::result::Result<DeviceType, String> {
+    match device_type {
+        "Fluke289" => Ok(DeviceType::Fluke289),
+        "Fluke287" => Ok(DeviceType::Fluke287),
+        "KeysightU124x" => Ok(DeviceType::KeysightU124x),
+        "Mock" => Ok(DeviceType::Mock),
+        _ => Err(format!(
+            "Invalid device type '{}', expected one of: {}",
+            device_type,
+            DEVICE_TYPE_NAMES.join(", ")
+        )),
+    }
+}
+
+/// A connection being established in the background
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct PendingConnection {
+    /// Id the device is registered under once connected
+    pub id: String,
+    pub device_type: DeviceType,
+    pub port: Option<String>,
+    pub address: Option<u8>,
+    pub started_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// A connect request whose options passed validation
+struct ConnectRequest {
+    device_type: DeviceType,
+    port: Option<String>,
+    options: DeviceOptions,
+    settle: Duration,
+    warm_up: Duration,
+}
+
+impl ConnectRequest {
+    fn new(
+        device_type: &str,
+        port: Option<String>,
+        options: DeviceOptions,
+    ) -> std::result::Result<Self, String> {
+        let device_type = parse_device_type(device_type)?;
+        options
+            .display_preferences
+            .validate()
+            .map_err(|e| e.to_string())?;
+        let settle = settle_duration(options.settle_ms.unwrap_or(0))?;
+        let warm_up = warm_up_duration(options.warm_up_secs.unwrap_or(0))?;
+        if let Some(smoothing) = &options.smoothing {
+            smoothing.validate().map_err(|e| e.to_string())?;
+        }
+        options.write_pacing.validate().map_err(|e| e.to_string())?;
+        if let Some(baud) = options.baud {
+            fluke::validate_baud_rate(baud).map_err(|e| e.to_string())?;
+        }
+        Ok(Self {
+            device_type,
+            port,
+            options,
+            settle,
+            warm_up,
+        })
+    }
+
+    /// Connect and identify the device, without registering it. A `parked`
+    /// device still holding the port is identified without reconnecting.
+    async fn open(self, parked: Option<SharedDevice>) -> std::result::Result<OpenedDevice, String> {
+        let reused = parked.is_some();
+        let device = match parked {
+            Some(device) => device,
+            None => {
+                let mut device = create_device(self.device_type, self.port.clone(), &self.options);
+                device
+                    .connect()
+                    .await
+                    .map_err(|e| format!("Failed to connect: {}", e))?;
+                Arc::new(Mutex::new(device))
+            }
+        };
+
+        let mut session = DeviceSession::new(
+            self.options.display_preferences,
+            self.settle,
+            self.options.smoothing,
+        );
+        session.invert_sign = self.options.invert_sign.unwrap_or_default();
+        session.warm_up = self.warm_up;
+
+        let timings = Arc::new(CommandTimings::default());
+        let (wire_tap, _) = broadcast::channel(WIRE_TAP_CAPACITY);
+        let (info, capabilities) = {
+            let mut device = device.lock().await;
+            device.set_wire_tap(wire_tap.clone());
+            let started = std::time::Instant::now();
+            let info = device.identify().await;
+            timings.record("identify", started.elapsed());
+            let info = info.map_err(|e| format!("Failed to identify device: {}", e))?;
+            (info, device.capabilities())
+        };
+
+        Ok(OpenedDevice {
+            device_type: self.device_type,
+            port: self.port,
+            address: self.options.address,
+            timestamp_source: self.options.timestamp_source,
+            auto_reconnect: self.options.auto_reconnect,
+            device,
+            reused,
+            session,
+            info,
+            capabilities,
+            timings,
+            wire_tap,
+        })
+    }
+
+    /// Take over the parked device on this request's port, if it is the
+    /// same kind of device; any other device parked there is closed
+    async fn take_parked(&self, state: &Arc<Mutex<AppState>>) -> Option<SharedDevice> {
+        let port = self.port.clone()?;
+        let parked = state
+            .lock()
+            .await
+            .parked
+            .remove(&(port, self.options.address))?;
+        if parked.device_type == self.device_type {
+            return Some(parked.device);
+        }
+        close_parked(parked.device).await;
+        None
+    }
+}
+
+/// A connected and identified device, ready to register
+struct OpenedDevice {
+    device_type: DeviceType,
+    port: Option<String>,
+    address: Option<u8>,
+    /// Requested clock for live readings
+    timestamp_source: TimestampSource,
+    /// Per-device override of the configured `auto_reconnect`
+    auto_reconnect: Option<bool>,
+    device: SharedDevice,
+    /// The port was still open from a recent disconnect
+    reused: bool,
+    session: DeviceSession,
+    info: DeviceInfo,
+    capabilities: Capabilities,
+    timings: Arc<CommandTimings>,
+    wire_tap: WireTap,
+}
+
+impl AppState {
+    fn allocate_device_id(&mut self) -> String {
+        let device_id = format_device_id(self.next_device_id);
+        self.next_device_id += 1;
+        device_id
+    }
+
+    /// Whether `device_id` was handed out by `allocate_device_id`. Ids are
+    /// never reused, so an issued id that is neither registered nor
+    /// connecting belongs to a device that is gone for good.
+    fn was_issued(&self, device_id: &str) -> bool {
+        device_id
+            .strip_prefix("device_")
+            .and_then(|number| number.parse::<u32>().ok())
+            .is_some_and(|number| {
+                number < self.next_device_id && format_device_id(number) == device_id
+            })
+    }
+
+    /// Check the port of `request` is free and list the device as
+    /// connecting under a fresh id, which holds the port until the device
+    /// is registered or the connect fails
+    fn reserve(
+        &mut self,
+        request: &ConnectRequest,
+    ) -> std::result::Result<PendingConnection, String> {
+        if let Some(port) = &request.port {
+            self.check_port_free(port, request.options.address)?;
+        }
+        let pending = PendingConnection {
+            id: self.allocate_device_id(),
+            device_type: request.device_type,
+            port: request.port.clone(),
+            address: request.options.address,
+            started_at: chrono::Utc::now(),
+        };
+        self.connecting.insert(pending.id.clone(), pending.clone());
+        Ok(pending)
+    }
+
+    /// Register an opened device under `device_id` and log the connection
+    fn register(&mut self, device_id: String, opened: OpenedDevice) -> ConnectDeviceResponse {
+        let queue_limit = self.config.max_queued_commands;
+        let detail = match (&opened.port, opened.address) {
+            (Some(port), Some(address)) => {
+                format!(
+                    "{:?} on {} at address {}",
+                    opened.device_type, port, address
+                )
+            }
+            (Some(port), None) => format!("{:?} on {}", opened.device_type, port),
+            (None, _) => format!("{:?}", opened.device_type),
+        };
+        let detail = if opened.reused {
+            format!("{} (reused open port)", detail)
+        } else {
+            detail
+        };
+        let timestamp_source = match opened.timestamp_source {
+            TimestampSource::Device if !opened.capabilities.clock => {
+                tracing::warn!(
+                    device_id,
+                    "Device has no clock, timestamping readings on the host"
+                );
+                TimestampSource::Host
+            }
+            source => source,
+        };
+
+        self.devices.insert(
+            device_id.clone(),
+            ManagedDevice {
+                device_type: opened.device_type,
+                info: opened.info.clone(),
+                capabilities: opened.capabilities,
+                connected_at: chrono::Utc::now(),
+                port: opened.port,
+                address: opened.address,
+                timestamp_source,
+                device: opened.device,
+                queue: Arc::new(Semaphore::new(queue_limit)),
+                queue_limit,
+                resetting: Arc::new(AtomicBool::new(false)),
+                disconnecting: Arc::new(Cancellation::default()),
+                auto_reconnect: opened.auto_reconnect.unwrap_or(self.config.auto_reconnect),
+                session: Arc::new(Mutex::new(opened.session)),
+                stats: Arc::new(MeasurementStats::default()),
+                store: self.store.clone(),
+                subscribers: Arc::new(Subscribers::default()),
+                timings: opened.timings,
+                wire_tap: opened.wire_tap,
+                reading: Arc::default(),
+            },
+        );
+        self.events
+            .push(&device_id, ConnectionEventKind::Connected, Some(detail));
+
+        ConnectDeviceResponse {
+            id: device_id,
+            device_type: opened.device_type,
+            info: opened.info,
+            capabilities: opened.capabilities,
+        }
+    }
+}
+
+/// Connect to a device. Until it is registered the device is listed as
+/// connecting, which holds its port against concurrent connects.
+pub async fn connect_device(
+    device_type: String,
+    port: Option<String>,
+    options: DeviceOptions,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<ConnectDeviceResponse, String> {
+    let request = ConnectRequest::new(&device_type, port, options)?;
+    let device_id = state.lock().await.reserve(&request)?.id;
+
+    let parked = request.take_parked(state).await;
+    let result = request.open(parked).await;
+    let mut state_guard = state.lock().await;
+    state_guard.connecting.remove(&device_id);
+    match result {
+        Ok(opened) => Ok(state_guard.register(device_id, opened)),
+        Err(error) => {
+            state_guard.failed_connects.insert(device_id);
+            Err(error)
+        }
+    }
+}
+
+/// Start connecting to a device in the background. The request is
+/// validated up front; the device is listed as connecting under the
+/// returned id until it is registered, or the failure is logged as a
+/// `connect_failed` event.
+pub async fn connect_device_async(
+    device_type: String,
+    port: Option<String>,
+    options: DeviceOptions,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<PendingConnection, String> {
+    let request = ConnectRequest::new(&device_type, port, options)?;
+    let pending = state.lock().await.reserve(&request)?;
+
+    let state = state.clone();
+    let device_id = pending.id.clone();
+    tokio::spawn(async move {
+        let parked = request.take_parked(&state).await;
+        let result = request.open(parked).await;
+        let mut state_guard = state.lock().await;
+        state_guard.connecting.remove(&device_id);
+        match result {
+            Ok(opened) => {
+                state_guard.register(device_id, opened);
+            }
+            Err(error) => {
+                tracing::warn!(device_id, %error, "Background connect failed");
+                state_guard.failed_connects.insert(device_id.clone());
+                state_guard.events.push(
+                    &device_id,
+                    ConnectionEventKind::ConnectFailed,
+                    Some(error),
+                );
+            }
+        }
+    });
+
+    Ok(pending)
+}
+
+/// Connections still being established by `connect_device_async`
+pub async fn get_connecting_devices(state: &Arc<Mutex<AppState>>) -> Vec<PendingConnection> {
+    state.lock().await.connecting.values().cloned().collect()
+}
+
+/// Open a port, identify the device on it and close it again without
+/// registering anything in `AppState`
+pub async fn probe_port(
+    port: &str,
+    device_type: DeviceType,
+    timeout: Duration,
+) -> std::result::Result<DeviceInfo, String> {
+    let mut device = create_device(
+        device_type,
+        Some(port.to_string()),
+        &DeviceOptions::default(),
+    );
+
+    let result = tokio::time::timeout(timeout, async {
+        device
+            .connect()
+            .await
+            .map_err(|e| format!("Failed to connect: {}", e))?;
+        device
+            .identify()
+            .await
+            .map_err(|e| format!("Failed to identify device: {}", e))
+    })
+    .await
+    .unwrap_or_else(|_| Err(format!("Probe of {} timed out", port)));
+
+    // Always release the port, whether or not the probe succeeded.
+    if let Err(error) = device.disconnect().await {
+        tracing::warn!(%error, port, "Failed to close probed port");
+    }
+
+    result
+}
+
+/// Outcome of probing one port during a scan
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct ScanResult {
+    pub port: String,
+    /// Identity of the Fluke that answered; `None` if nothing did
+    pub info: Option<DeviceInfo>,
+}
+
+/// Probe every serial port at once for a Fluke meter. Each probe closes its
+/// port and gives up after `timeout`, so the scan takes about `timeout`
+/// however many ports there are.
+pub async fn scan_ports(timeout: Duration) -> std::result::Result<Vec<ScanResult>, String> {
+    scan(get_available_ports()?, move |port| async move {
+        probe_port(&port, DeviceType::Fluke289, timeout).await
+    })
+    .await
+}
+
+/// Run `probe` on every port concurrently, in the order of `ports`
+async fn scan<P, F>(ports: Vec<String>, probe: P) -> std::result::Result<Vec<ScanResult>, String>
+where
+    P: Fn(String) -> F,
+    F: std::future::Future<Output = std::result::Result<DeviceInfo, String>> + Send + 'static,
+{
+    let probes: Vec<_> = ports
+        .into_iter()
+        .map(|port| {
+            let info = probe(port.clone());
+            tokio::spawn(async move {
+                let info = info.await;
+                if let Err(error) = &info {
+                    tracing::debug!(port, %error, "No Fluke found");
+                }
+                ScanResult {
+                    port,
+                    info: info.ok(),
+                }
+            })
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(probes.len());
+    for probe in probes {
+        results.push(
+            probe
+                .await
+                .map_err(|e| format!("Probe task failed: {}", e))?,
+        );
+    }
+    Ok(results)
+}
+
+/// Disconnect from a device
+pub async fn disconnect_device(
+    device_id: String,
+    force: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DisconnectResponse, String> {
+    disconnect(device_id, force, true, state).await
+}
+
+/// Disconnect a device, keeping its port open for the configured linger if
+/// `linger`. Unless `force`d, a device with consumers is refused; the check
+/// and the removal happen under one lock so none can join in between.
+async fn disconnect(
+    device_id: String,
+    force: bool,
+    linger: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DisconnectResponse, String> {
+    let (managed_device, linger, summary_dir) = {
+        let mut state_guard = state.lock().await;
+        if !force {
+            let consumers = state_guard.device(&device_id)?.consumers();
+            if !consumers.is_empty() {
+                return Err(Error::Busy(format!(
+                    "device {} is in use by {}; disconnect with force to cut them off",
+                    device_id,
+                    consumers.join(", ")
+                ))
+                .to_string());
+            }
+        }
+        let managed_device = state_guard
+            .devices
+            .remove(&device_id)
+            .ok_or_else(|| state_guard.unavailable(&device_id))?;
+        (
+            managed_device,
+            state_guard.config.port_linger().filter(|_| linger),
+            state_guard.config.session_summary_dir.clone(),
+        )
+    };
+
+    // Settling waits end early and queued commands give up; a command
+    // already talking to the device finishes before the port closes.
+    managed_device.disconnecting.cancel();
+    managed_device.subscribers.close();
+    let result = match (&managed_device.port, linger) {
+        (Some(port), Some(linger)) => {
+            park_device(port.clone(), &managed_device, linger, state).await;
+            Ok(Some(format!(
+                "port kept open for {} ms",
+                linger.as_millis()
+            )))
+        }
+        _ => managed_device
+            .device
+            .lock()
+            .await
+            .disconnect()
+            .await
+            .map(|()| None),
+    };
+    let summary = {
+        let mut store = managed_device.store.lock().await;
+        let buffered = store
+            .range(&device_id, Some(managed_device.connected_at), None)
+            .unwrap_or_else(|error| {
+                tracing::warn!(device_id, %error, "Session summary without buffered readings");
+                Vec::new()
+            });
+        store.device_removed(&device_id);
+        SessionSummary::new(
+            device_id.clone(),
+            managed_device.connected_at,
+            managed_device.stats.measurement_count(),
+            managed_device.stats.total_samples(),
+            managed_device.stats.error_count(),
+            &buffered,
+        )
+    };
+    if let Some(dir) = summary_dir {
+        if let Err(error) = summary.write_to(&dir) {
+            tracing::warn!(device_id, %error, dir = %dir.display(), "Failed to write session summary");
+        }
+    }
+    let detail = match &result {
+        Ok(detail) => detail.clone(),
+        Err(e) => Some(e.to_string()),
+    };
+    state
+        .lock()
+        .await
+        .events
+        .push(&device_id, ConnectionEventKind::Disconnected, detail);
+    result.map_err(|e| format!("Failed to disconnect: {}", e))?;
+    Ok(DisconnectResponse {
+        message: format!("Disconnected device {}", device_id),
+        summary,
+    })
+}
+
+/// Keep a disconnected device's port open for `linger`. A connect to the
+/// same port and address within that time takes the device over instead of
+/// reopening the port; otherwise it is closed once `linger` has passed.
+async fn park_device(
+    port: String,
+    managed_device: &ManagedDevice,
+    linger: Duration,
+    state: &Arc<Mutex<AppState>>,
+) {
+    let key = (port, managed_device.address);
+    let device = managed_device.device.clone();
+    let displaced = state.lock().await.parked.insert(
+        key.clone(),
+        ParkedDevice {
+            device_type: managed_device.device_type,
+            device: device.clone(),
+        },
+    );
+    if let Some(displaced) = displaced {
+        close_parked(displaced.device).await;
+    }
+
+    let state = state.clone();
+    tokio::spawn(async move {
+        tokio::time::sleep(linger).await;
+        let expired = {
+            let mut state_guard = state.lock().await;
+            match state_guard.parked.get(&key) {
+                Some(parked) if Arc::ptr_eq(&parked.device, &device) => {
+                    state_guard.parked.remove(&key)
+                }
+                _ => None,
+            }
+        };
+        if let Some(parked) = expired {
+            close_parked(parked.device).await;
+        }
+    });
+}
+
+async fn close_parked(device: SharedDevice) {
+    if let Err(error) = device.lock().await.disconnect().await {
+        tracing::warn!(%error, "Failed to close parked port");
+    }
+}
+
+/// Longest settling delay accepted per device or per request
+pub const MAX_SETTLE_MS: u64 = 10_000;
+
+/// Validate a settling delay in milliseconds
+pub fn settle_duration(settle_ms: u64) -> std::result::Result<Duration, String> {
+    if settle_ms > MAX_SETTLE_MS {
+        return Err(format!(
+            "Settling delay {} ms exceeds the maximum of {} ms",
+            settle_ms, MAX_SETTLE_MS
+        ));
+    }
+    Ok(Duration::from_millis(settle_ms))
+}
+
+/// Longest warm-up period accepted per device
+pub const MAX_WARM_UP_SECS: u64 = 4 * 60 * 60;
+
+/// Validate a warm-up period in seconds
+fn warm_up_duration(warm_up_secs: u64) -> std::result::Result<Duration, String> {
+    if warm_up_secs > MAX_WARM_UP_SECS {
+        return Err(format!(
+            "Warm-up period {} s exceeds the maximum of {} s",
+            warm_up_secs, MAX_WARM_UP_SECS
+        ));
+    }
+    Ok(Duration::from_secs(warm_up_secs))
+}
+
+/// Get current measurement.
+///
+/// With `settle`, readings during that delay are discarded and the first one
+/// after it is returned. Without it the device's own settling delay applies,
+/// but only when the measured function has just changed.
+///
+/// With `expected_unit`, a reading in another unit is converted if possible
+/// and rejected otherwise, e.g. when the meter is still in the previous
+/// function. The reading is recorded either way.
+///
+/// `reconnect: false` opts out of the configured auto-reconnect.
+///
+/// Requests without `settle` that arrive while one is reading share its
+/// reading rather than each querying the device. While the device is being
+/// polled they get the poller's latest reading instead.
+pub async fn get_measurement(
+    device_id: String,
+    settle: Option<Duration>,
+    expected_unit: Option<Unit>,
+    reference_ohms: f64,
+    reconnect: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<serde_json::Value, String> {
+    let mut measurement = match settle {
+        Some(_) => take_measurement(&device_id, settle, reconnect, None, state).await?,
+        // Requests without a settling wait want the same reading, so ones
+        // arriving while a read is in flight share it. While polling, the
+        // poller's reading is returned, or its error if its last read failed.
+        None => {
+            let managed_device = managed_device(&device_id, state).await?;
+            let polled = if managed_device.subscribers.is_polling() {
+                if let Some(error) = managed_device.subscribers.poll_error() {
+                    return Err(error);
+                }
+                managed_device
+                    .session
+                    .lock()
+                    .await
+                    .last_measurement()
+                    .cloned()
+            } else {
+                None
+            };
+            match polled {
+                Some(measurement) => measurement,
+                None => {
+                    managed_device
+                        .reading
+                        .run(|| take_measurement(&device_id, None, reconnect, None, state))
+                        .await?
+                }
+            }
+        }
+    };
+    if let Some(expected) = expected_unit {
+        measurement = measurement
+            .expect_unit_at(expected, reference_ohms)
+            .map_err(|e| e.to_string())?;
+    }
+    serde_json::to_value(measurement).map_err(|e| format!("Serialization error: {}", e))
+}
+
+/// First wait before reopening a dropped device, doubled per attempt
+const RECONNECT_INITIAL_BACKOFF: Duration = Duration::from_millis(100);
+/// Longest wait between reconnect attempts
+const RECONNECT_MAX_BACKOFF: Duration = Duration::from_secs(5);
+/// Reconnect attempts before giving up, about 16 s of backoff in all
+const MAX_RECONNECT_ATTEMPTS: u32 = 8;
+
+/// Reopen a device after `error` broke the connection, retrying with
+/// exponential backoff and logging each attempt. Gives up after
+/// `MAX_RECONNECT_ATTEMPTS`, or at once when the device is being
+/// disconnected.
+async fn reconnect(
+    device: &mut Box<dyn Device>,
+    device_id: &str,
+    error: &Error,
+    disconnecting: &Cancellation,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<(), String> {
+    if let Err(error) = device.disconnect().await {
+        tracing::debug!(device_id, %error, "Closing the broken connection failed");
+    }
+    let mut backoff = RECONNECT_INITIAL_BACKOFF;
+    let mut last_error = error.to_string();
+    for attempt in 1..=MAX_RECONNECT_ATTEMPTS {
+        disconnecting.sleep(backoff).await?;
+        tracing::warn!(
+            device_id,
+            attempt,
+            error = %last_error,
+            "Reconnecting after connection failure"
+        );
+        state.lock().await.events.push(
+            device_id,
+            ConnectionEventKind::ReconnectAttempt,
+            Some(format!("attempt {}: {}", attempt, last_error)),
+        );
+        match device.connect().await {
+            Ok(()) => return Ok(()),
+            Err(reconnect_error) => last_error = reconnect_error.to_string(),
+        }
+        backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+    }
+
+    tracing::error!(
+        device_id,
+        attempts = MAX_RECONNECT_ATTEMPTS,
+        error = %last_error,
+        "Giving up reconnecting"
+    );
+    state.lock().await.events.push(
+        device_id,
+        ConnectionEventKind::ConnectionLost,
+        Some(last_error.clone()),
+    );
+    Err(format!(
+        "{}; reconnecting failed after {} attempts: {}",
+        error, MAX_RECONNECT_ATTEMPTS, last_error
+    ))
+}
+
+/// Query, record and publish one measurement. With `reconnect` and
+/// auto-reconnect enabled for the device, a connection failure reopens the
+/// device with backoff and the read is retried once, unless the device is
+/// being disconnected.
+/// Polled readings get their timestamp snapped to the poller's `grid`.
+/// A device whose reads keep failing is disconnected.
+async fn take_measurement(
+    device_id: &str,
+    settle: Option<Duration>,
+    reconnect: bool,
+    grid: Option<&mut TimestampGrid>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Measurement, String> {
+    let result = measure(device_id, settle, reconnect, grid, state).await;
+    if let Err(error) = &result {
+        disconnect_if_failing(device_id, error, state).await;
+    }
+    result
+}
+
+/// Disconnect a device whose measurements failed `max_consecutive_errors`
+/// times in a row, so a wedged meter isn't polled forever
+async fn disconnect_if_failing(device_id: &str, error: &str, state: &Arc<Mutex<AppState>>) {
+    let (managed_device, limit) = {
+        let state_guard = state.lock().await;
+        let Ok(managed_device) = state_guard.device(device_id) else {
+            return;
+        };
+        (managed_device, state_guard.config.max_consecutive_errors)
+    };
+    let errors = managed_device.stats.consecutive_errors();
+    if limit == 0 || errors < limit {
+        return;
+    }
+    drop(managed_device);
+
+    tracing::warn!(
+        device_id,
+        errors,
+        "Too many failed measurements, disconnecting"
+    );
+    state.lock().await.events.push(
+        device_id,
+        ConnectionEventKind::ErrorLimitReached,
+        Some(format!(
+            "{} consecutive failed measurements, last: {}",
+            errors, error
+        )),
+    );
+    // A concurrent request may have disconnected it already.
+    if let Err(error) = disconnect_device(device_id.to_string(), true, state).await {
+        tracing::debug!(device_id, %error, "Automatic disconnect failed");
+    }
+}
+
+/// Read and record one measurement for `take_measurement`, counting failed
+/// reads against the device
+async fn measure(
+    device_id: &str,
+    settle: Option<Duration>,
+    reconnect: bool,
+    grid: Option<&mut TimestampGrid>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Measurement, String> {
+    let (managed_device, global_preferences) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.device(device_id)?,
+            state_guard.config.display_preferences.clone(),
+        )
+    };
+    let reconnect = reconnect && managed_device.auto_reconnect;
+
+    // Record while still holding the device so sequence order matches the
+    // order the readings were taken in.
+    let mut device = managed_device.lock_device().await?;
+    let disconnecting = &managed_device.disconnecting;
+    if let Some(settle) = settle {
+        disconnecting.sleep(settle).await?;
+    }
+    let mut measurement = match managed_device
+        .timed("measurement", device.get_measurement())
+        .await
+    {
+        Err(error)
+            if reconnect && error.is_connection_failure() && !disconnecting.is_cancelled() =>
+        {
+            self::reconnect(&mut device, device_id, &error, disconnecting, state)
+                .await
+                .inspect_err(|_| managed_device.stats.record_error())
+                .map_err(|e| format!("Failed to get measurement: {}", e))?;
+            managed_device
+                .timed("measurement", device.get_measurement())
+                .await
+        }
+        result => result,
+    }
+    .inspect_err(|_| managed_device.stats.record_error())
+    .map_err(|e| format!("Failed to get measurement: {}", e))?;
+    let function_settle = managed_device
+        .session
+        .lock()
+        .await
+        .settle_after(&measurement.unit);
+    if let (None, Some(function_settle)) = (settle, function_settle) {
+        // The transient reading is discarded, not recorded.
+        disconnecting.sleep(function_settle).await?;
+        measurement = managed_device
+            .timed("measurement", device.get_measurement())
+            .await
+            .inspect_err(|_| managed_device.stats.record_error())
+            .map_err(|e| format!("Failed to get measurement: {}", e))?;
+    }
+    measurement.metadata.timestamp_source = Some(TimestampSource::Host);
+    if managed_device.timestamp_source == TimestampSource::Device {
+        match managed_device.timed("clock", device.get_clock()).await {
+            Ok(time) => {
+                measurement.timestamp = Some(time);
+                measurement.metadata.timestamp_source = Some(TimestampSource::Device);
+            }
+            Err(error) => {
+                tracing::warn!(
+                    device_id,
+                    %error,
+                    "Reading the device clock failed, keeping the host timestamp"
+                );
+            }
+        }
+    }
+    if let Some(grid) = grid {
+        measurement = grid.snap(measurement);
+    }
+    let measurement = managed_device
+        .record(device_id, measurement, global_preferences)
+        .await;
+    drop(device);
+
+    Ok(measurement)
+}
+
+/// Take one measurement from each device concurrently, in the given order.
+/// Devices only wait for their own lock.
+async fn read_concurrently(
+    device_ids: &[String],
+    state: &Arc<Mutex<AppState>>,
+) -> Vec<std::result::Result<Measurement, String>> {
+    let reads: Vec<_> = device_ids
+        .iter()
+        .map(|device_id| {
+            let device_id = device_id.clone();
+            let state = state.clone();
+            tokio::spawn(
+                async move { take_measurement(&device_id, None, true, None, &state).await },
+            )
+        })
+        .collect();
+
+    let mut results = Vec::with_capacity(reads.len());
+    for read in reads {
+        results.push(
+            read.await
+                .unwrap_or_else(|e| Err(format!("Measurement task failed: {}", e))),
+        );
+    }
+    results
+}
+
+/// Simultaneous readings of two devices and their difference
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Differential {
+    pub a: Measurement,
+    pub b: Measurement,
+    /// `a - b`
+    pub difference: f64,
+    pub unit: Unit,
+}
+
+/// Read two devices at once and return `a - b`. Both readings must be in
+/// range and in the same unit.
+pub async fn get_differential(
+    device_a: String,
+    device_b: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Differential, String> {
+    if device_a == device_b {
+        return Err("A differential needs two different devices".to_string());
+    }
+
+    let ids = [device_a, device_b];
+    let mut readings = Vec::with_capacity(2);
+    for (device_id, result) in ids.iter().zip(read_concurrently(&ids, state).await) {
+        let measurement = result.map_err(|e| {
+            if e.contains(device_id.as_str()) {
+                e
+            } else {
+                format!("Device {}: {}", device_id, e)
+            }
+        })?;
+        if measurement.state != MeasurementState::Normal {
+            return Err(format!(
+                "Device {}: reading is {:?}, no difference can be taken",
+                device_id, measurement.state
+            ));
+        }
+        readings.push(measurement);
+    }
+    let [a, b] = <[Measurement; 2]>::try_from(readings)
+        .map_err(|_| "Expected one reading per device".to_string())?;
+
+    if a.unit != b.unit {
+        return Err(format!(
+            "Units differ: {} reads {:?}, {} reads {:?}",
+            ids[0], a.unit, ids[1], b.unit
+        ));
+    }
+
+    Ok(Differential {
+        difference: a.value - b.value,
+        unit: a.unit.clone(),
+        a,
+        b,
+    })
+}
+
+/// One device's part of a bench snapshot
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct SnapshotEntry {
+    pub device_id: String,
+    pub device_type: DeviceType,
+    /// Model name reported by the device
+    pub label: String,
+    pub serial_number: String,
+    /// Reading taken for the snapshot, absent if the read failed
+    pub measurement: Option<Measurement>,
+    pub error: Option<String>,
+}
+
+/// Backend resource usage
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Metrics {
+    /// Number of connected devices
+    pub devices: usize,
+    /// Memory of the measurement buffers; `None` for a store on disk
+    pub buffer_memory: Option<MemoryUsage>,
+}
+
+/// Get the current resource usage of the backend
+pub async fn get_metrics(state: &Arc<Mutex<AppState>>) -> Metrics {
+    let (devices, store) = {
+        let state_guard = state.lock().await;
+        (state_guard.devices.len(), state_guard.store.clone())
+    };
+    let buffer_memory = store.lock().await.memory_usage();
+    Metrics {
+        devices,
+        buffer_memory,
+    }
+}
+
+/// A reading of every connected device, taken at once
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Snapshot {
+    /// Host time the reads were started
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    /// Entries in device id order
+    pub devices: Vec<SnapshotEntry>,
+}
+
+/// Read every connected device concurrently. A failing device reports its
+/// error in its entry instead of failing the snapshot.
+pub async fn get_snapshot(state: &Arc<Mutex<AppState>>) -> Snapshot {
+    let mut devices: Vec<_> = {
+        let state_guard = state.lock().await;
+        state_guard
+            .devices
+            .iter()
+            .map(|(id, managed_device)| {
+                (
+                    id.clone(),
+                    managed_device.device_type,
+                    managed_device.info.clone(),
+                )
+            })
+            .collect()
+    };
+    devices.sort_by(|a, b| a.0.cmp(&b.0));
+
+    let timestamp = chrono::Utc::now();
+    let ids: Vec<String> = devices.iter().map(|(id, _, _)| id.clone()).collect();
+    let results = read_concurrently(&ids, state).await;
+
+    let devices = devices
+        .into_iter()
+        .zip(results)
+        .map(|((device_id, device_type, info), result)| {
+            let (measurement, error) = match result {
+                Ok(measurement) => (Some(measurement), None),
+                Err(e) => (None, Some(e)),
+            };
+            SnapshotEntry {
+                device_id,
+                device_type,
+                label: info.model,
+                serial_number: info.serial_number,
+                measurement,
+                error,
+            }
+        })
+        .collect();
+
+    Snapshot { timestamp, devices }
+}
+
+/// Get the most recent measurement without querying the device.
+///
+/// Never older than a reading returned by a completed `get_measurement`.
+pub async fn get_last_measurement(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Measurement, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let session = managed_device.session.lock().await;
+    session
+        .last_measurement()
+        .cloned()
+        .ok_or_else(|| format!("No measurement recorded for device {} yet", device_id))
+}
+
+/// Get every reading on the device display, falling back to the primary
+/// measurement where the device has no multi-value query
+pub async fn get_all_measurements(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<DisplayReading>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+
+    let mut device = managed_device.lock_device().await?;
+    let mut readings = managed_device
+        .timed("display_download", device.get_all_measurements())
+        .await
+        .map_err(|e| format!("Failed to get measurements: {}", e))?;
+    // All readings of one display download share an acquisition.
+    let mut session = managed_device.session.lock().await;
+    let sequence = session.next_sequence();
+    for reading in &mut readings {
+        reading.measurement = session.correct(reading.measurement.clone());
+        reading.measurement.sequence = sequence;
+    }
+    drop(session);
+    managed_device.stats.record(readings.len());
+    drop(device);
+
+    Ok(readings)
+}
+
+/// Download a device's whole display, the primary reading and its
+/// sub-display readings
+pub async fn get_full_display(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DisplayData, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+
+    let mut device = managed_device.lock_device().await?;
+    let mut display = managed_device
+        .timed("display_download", device.get_full_display())
+        .await
+        .map_err(|e| format!("Failed to download display: {}", e))?;
+    // All readings of one display download share an acquisition.
+    let mut session = managed_device.session.lock().await;
+    let sequence = session.next_sequence();
+    let readings = std::iter::once(&mut display.primary)
+        .chain(display.auxiliary.iter_mut().map(|a| &mut a.measurement));
+    for measurement in readings {
+        *measurement = session.correct(measurement.clone());
+        measurement.sequence = sequence;
+    }
+    drop(session);
+    managed_device.stats.record(1 + display.auxiliary.len());
+    drop(device);
+
+    Ok(display)
+}
+
+/// Get the rate of change of a device's readings over the recent history
+pub async fn get_trend(
+    device_id: String,
+    window: Duration,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Trend, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let newest = managed_device
+        .session
+        .lock()
+        .await
+        .last_measurement()
+        .and_then(|m| m.timestamp);
+    let Some(newest) = newest else {
+        return Ok(history::trend(std::iter::empty(), window));
+    };
+
+    let from = chrono::Duration::from_std(window)
+        .ok()
+        .and_then(|window| newest.checked_sub_signed(window));
+    let samples = managed_device
+        .store
+        .lock()
+        .await
+        .range(&device_id, from, None)
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    Ok(history::trend(samples.iter(), window))
+}
+
+/// Get value statistics of a device's buffered readings since its unit
+/// last changed
+pub async fn get_statistics(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Statistics, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let samples = managed_device
+        .store
+        .lock()
+        .await
+        .range(&device_id, None, None)
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    Ok(history::statistics(samples.iter()))
+}
+
+/// Get the stored measurements of a device taken between `from` and `to`,
+/// oldest first; with `limit`, only the most recent `limit` of them
+pub async fn get_history(
+    device_id: String,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+    limit: Option<usize>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<Measurement>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let store = managed_device.store.lock().await;
+    let mut measurements = store
+        .range(&device_id, from, to)
+        .map_err(|e| format!("Failed to read history: {}", e))?;
+    if let Some(limit) = limit {
+        measurements.drain(..measurements.len().saturating_sub(limit));
+    }
+    Ok(measurements)
+}
+
+/// Get the effective display preferences of a device
+pub async fn get_display_preferences(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DisplayPreferences, String> {
+    let (managed_device, global_preferences) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.device(&device_id)?,
+            state_guard.config.display_preferences.clone(),
+        )
+    };
+    let session = managed_device.session.lock().await;
+    Ok(session.display_preferences.clone().or(global_preferences))
+}
+
+/// Replace a device's display preference overrides; unset fields fall back
+/// to the global preferences
+pub async fn set_display_preferences(
+    device_id: String,
+    preferences: DisplayPreferences,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DisplayPreferences, String> {
+    preferences.validate().map_err(|e| e.to_string())?;
+    let (managed_device, global_preferences) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.device(&device_id)?,
+            state_guard.config.display_preferences.clone(),
+        )
+    };
+    managed_device.session.lock().await.display_preferences = preferences.clone();
+    Ok(preferences.or(global_preferences))
+}
+
+/// Enable or, with `None`, disable smoothing of a device's live readings
+pub async fn set_smoothing(
+    device_id: String,
+    smoothing: Option<Smoothing>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    if let Some(smoothing) = &smoothing {
+        smoothing.validate().map_err(|e| e.to_string())?;
+    }
+    let managed_device = managed_device(&device_id, state).await?;
+    managed_device.session.lock().await.set_smoothing(smoothing);
+    Ok(match smoothing {
+        Some(smoothing) => format!("Smoothing set to {:?}", smoothing),
+        None => "Smoothing disabled".to_string(),
+    })
+}
+
+/// Get device status
+pub async fn get_device_status(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DeviceListItem, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    Ok(managed_device.list_item(device_id))
+}
+
+/// Reset device
+pub async fn reset_device(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    if managed_device.resetting.swap(true, Ordering::AcqRel) {
+        return Err(Error::Busy("device is resetting".to_string()).to_string());
+    }
+    let _resetting = ResetInProgress(&managed_device.resetting);
+    // Commands queued before the reset still run first.
+    let mut device = managed_device.queue_slot().await?;
+    managed_device
+        .timed("reset", device.reset())
+        .await
+        .map_err(|e| format!("Failed to reset device: {}", e))?;
+    managed_device.stats.reset();
+    managed_device.session.lock().await.restart_warm_up();
+    Ok("Device reset successfully".to_string())
+}
+
+/// Command type a raw command is timed under, e.g. `QM` for `qm`
+fn command_name(command: &str) -> String {
+    command
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_uppercase()
+}
+
+/// Send raw command to device. As for measurements, `reconnect` allows
+/// reconnecting and one retry if auto-reconnect is enabled.
+pub async fn send_raw_command(
+    device_id: String,
+    command: String,
+    reconnect: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let reconnect = reconnect && managed_device.auto_reconnect;
+    let mut device = managed_device.lock_device().await?;
+    let name = command_name(&command);
+    let response = match managed_device
+        .timed(&name, device.send_command(&command))
+        .await
+    {
+        Err(error)
+            if reconnect
+                && error.is_connection_failure()
+                && !managed_device.disconnecting.is_cancelled() =>
+        {
+            self::reconnect(
+                &mut device,
+                &device_id,
+                &error,
+                &managed_device.disconnecting,
+                state,
+            )
+            .await
+            .map_err(|e| format!("Failed to send command: {}", e))?;
+            managed_device
+                .timed(&name, device.send_command(&command))
+                .await
+        }
+        result => result,
+    }
+    .map_err(|e| format!("Failed to send command: {}", e))?;
+    Ok(response)
+}
+
+/// Change the simulation parameters of a connected mock device
+pub async fn configure_mock(
+    device_id: String,
+    config: MockConfig,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    device
+        .configure_mock(config)
+        .map_err(|e| format!("Failed to configure mock: {}", e))?;
+    Ok("Mock device reconfigured".to_string())
+}
+
+/// Read a device's configurable settings
+pub async fn get_device_settings(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DeviceSettings, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    let mut settings = managed_device
+        .timed("settings", device.get_settings())
+        .await
+        .map_err(|e| format!("Failed to read settings: {}", e))?;
+    let session = managed_device.session.lock().await;
+    settings.temperature_offset = Some(session.temperature_offset);
+    settings.invert_sign = Some(session.invert_sign.clone());
+    settings.db_reference = session.db_reference.clone();
+    Ok(settings)
+}
+
+/// Select the input filter for a device's frequency measurements
+pub async fn set_frequency_filter(
+    device_id: String,
+    filter: FrequencyFilter,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("set_frequency_filter", device.set_frequency_filter(filter))
+        .await
+        .map_err(|e| format!("Failed to set frequency filter: {}", e))?;
+    Ok(format!("Frequency filter set to {:?}", filter))
+}
+
+/// Choose the reading on a device's secondary display
+pub async fn set_secondary_function(
+    device_id: String,
+    function: SecondaryFunction,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed(
+            "set_secondary_function",
+            device.set_secondary_function(function),
+        )
+        .await
+        .map_err(|e| format!("Failed to set secondary function: {}", e))?;
+    Ok(format!("Secondary function set to {:?}", function))
+}
+
+/// Set the backlight brightness of a device's display
+pub async fn set_display_brightness(
+    device_id: String,
+    level: u8,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed(
+            "set_display_brightness",
+            device.set_display_brightness(level),
+        )
+        .await
+        .map_err(|e| format!("Failed to set display brightness: {}", e))?;
+    Ok(format!("Display brightness set to {}", level))
+}
+
+/// Set the contrast of a device's display
+pub async fn set_display_contrast(
+    device_id: String,
+    level: u8,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("set_display_contrast", device.set_display_contrast(level))
+        .await
+        .map_err(|e| format!("Failed to set display contrast: {}", e))?;
+    Ok(format!("Display contrast set to {}", level))
+}
+
+/// Follow the raw serial traffic of a device, if debug endpoints are enabled
+pub async fn subscribe_wire(
+    device_id: &str,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<broadcast::Receiver<WireChunk>, String> {
+    let state_guard = state.lock().await;
+    if !state_guard.config.debug_endpoints {
+        return Err(
+            "Debug endpoints are disabled; set \"debug_endpoints\": true in the configuration"
+                .to_string(),
+        );
+    }
+    Ok(state_guard.device(device_id)?.wire_tap.subscribe())
+}
+
+/// Set the integration time of a device's readings in power-line cycles
+pub async fn set_nplc(
+    device_id: String,
+    cycles: f64,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("set_nplc", device.set_nplc(cycles))
+        .await
+        .map_err(|e| format!("Failed to set NPLC: {}", e))?;
+    Ok(format!("Integration time set to {} NPLC", cycles))
+}
+
+/// Switch a device's input between high and normal impedance
+pub async fn set_high_input_impedance(
+    device_id: String,
+    enabled: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed(
+            "set_high_input_impedance",
+            device.set_high_input_impedance(enabled),
+        )
+        .await
+        .map_err(|e| format!("Failed to set input impedance: {}", e))?;
+    Ok(if enabled {
+        "High input impedance enabled".to_string()
+    } else {
+        "High input impedance disabled".to_string()
+    })
+}
+
+/// Turn a device's auto-save of stable readings on or off
+pub async fn set_auto_save(
+    device_id: String,
+    enabled: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("set_auto_save", device.set_auto_save(enabled))
+        .await
+        .map_err(|e| format!("Failed to set auto-save: {}", e))?;
+    Ok(if enabled {
+        "Auto-save enabled".to_string()
+    } else {
+        "Auto-save disabled".to_string()
+    })
+}
+
+/// Set the offset in °C added to a device's temperature readings
+pub async fn set_temperature_offset(
+    device_id: String,
+    offset_celsius: f64,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    validate_temperature_offset(offset_celsius).map_err(|e| e.to_string())?;
+    let managed_device = managed_device(&device_id, state).await?;
+    managed_device.session.lock().await.temperature_offset = offset_celsius;
+    Ok(format!("Temperature offset set to {} °C", offset_celsius))
+}
+
+/// Choose which of a device's readings have their sign flipped
+pub async fn set_invert_sign(
+    device_id: String,
+    invert_sign: SignInversion,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let message = format!("Sign inversion set to {:?}", invert_sign);
+    managed_device.session.lock().await.invert_sign = invert_sign;
+    Ok(message)
+}
+
+/// Pass/fail limits of a device and the verdicts counted against them
+#[derive(Debug, Clone, Serialize)]
+pub struct LimitsStatus {
+    pub limits: Option<Limits>,
+    pub counts: VerdictCounts,
+}
+
+/// Set or, with `None`, clear the limits readings are judged against.
+/// The pass/fail counts restart either way.
+pub async fn set_limits(
+    device_id: String,
+    limits: Option<Limits>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    if let Some(limits) = &limits {
+        limits.validate().map_err(|e| e.to_string())?;
+    }
+    let managed_device = managed_device(&device_id, state).await?;
+    managed_device
+        .session
+        .lock()
+        .await
+        .set_limits(limits.clone());
+    Ok(match limits {
+        Some(limits) => format!("Limits set to {:?}", limits),
+        None => "Limits cleared".to_string(),
+    })
+}
+
+pub async fn get_limits(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<LimitsStatus, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let session = managed_device.session.lock().await;
+    Ok(LimitsStatus {
+        limits: session.limits(),
+        counts: session.verdicts(),
+    })
+}
+
+/// Zero the pass/fail counts, keeping the limits
+pub async fn reset_limit_counts(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    managed_device.session.lock().await.reset_verdicts();
+    Ok("Pass/fail counts reset".to_string())
+}
+
+/// Commands known for raw use with a device
+pub async fn get_device_commands(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<CommandInfo>, String> {
+    let device_type = state.lock().await.device(&device_id)?.device_type;
+    Ok(device_commands(device_type))
+}
+
+/// Read a device's real-time clock
+pub async fn get_device_clock(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<chrono::DateTime<chrono::Utc>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("clock", device.get_clock())
+        .await
+        .map_err(|e| format!("Failed to read clock: {}", e))
+}
+
+/// Null a device's current reading, or with `zero: false` clear the null
+pub async fn set_zero(
+    device_id: String,
+    zero: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    if zero {
+        managed_device
+            .timed("zero_adjust", device.zero_adjust())
+            .await
+            .map_err(|e| format!("Failed to zero device: {}", e))?;
+        Ok("Zero set to the current reading".to_string())
+    } else {
+        managed_device
+            .timed("clear_zero", device.clear_zero())
+            .await
+            .map_err(|e| format!("Failed to clear zero: {}", e))?;
+        Ok("Zero cleared".to_string())
+    }
+}
+
+/// Capture a device's current dB reading as the 0 dB point of later
+/// readings in its unit, or with `capture` false clear it
+pub async fn set_db_reference(
+    device_id: String,
+    capture: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    if !capture {
+        managed_device.session.lock().await.db_reference = None;
+        return Ok("dB reference cleared".to_string());
+    }
+
+    let mut device = managed_device.lock_device().await?;
+    let reading = managed_device
+        .timed("measurement", device.get_measurement())
+        .await
+        .map_err(|e| format!("Failed to get measurement: {}", e))?;
+    // The reference is taken as readings will be compared against it:
+    // corrected, but not relative to an earlier reference.
+    let mut session = managed_device.session.lock().await;
+    session.db_reference = None;
+    let reference = DbReference::capture(&session.correct(reading)).map_err(|e| e.to_string())?;
+    session.db_reference = Some(reference.clone());
+    Ok(format!(
+        "dB reference set to {} {}",
+        reference.value,
+        reference.unit.symbol()
+    ))
+}
+
+/// Set a device's real-time clock
+pub async fn set_device_clock(
+    device_id: String,
+    time: chrono::DateTime<chrono::Utc>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("set_clock", device.set_clock(time))
+        .await
+        .map_err(|e| format!("Failed to set clock: {}", e))?;
+    Ok(format!("Clock set to {}", time.to_rfc3339()))
+}
+
+/// Functions a device can select without turning its dial
+pub async fn get_available_functions(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<MeasurementFunction>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("available_functions", device.available_functions())
+        .await
+        .map_err(|e| format!("Failed to query available functions: {}", e))
+}
+
+/// Run a device's built-in self-test
+pub async fn run_self_test(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<SelfTestResult, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("self_test", device.self_test())
+        .await
+        .map_err(|e| format!("Failed to run self-test: {}", e))
+}
+
+/// List the setup memory slots of a device
+pub async fn list_saved_setups(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<SavedSetupInfo>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("saved_setups", device.list_saved_setups())
+        .await
+        .map_err(|e| format!("Failed to list saved setups: {}", e))
+}
+
+/// Recall a stored setup on a device
+pub async fn recall_setup(
+    device_id: String,
+    index: usize,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("recall_setup", device.recall_setup(index))
+        .await
+        .map_err(|e| format!("Failed to recall setup: {}", e))?;
+    Ok(format!("Recalled setup {}", index))
+}
+
+/// Read the calibration record of a device
+pub async fn get_calibration_info(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<CalibrationInfo, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("calibration", device.get_calibration_info())
+        .await
+        .map_err(|e| format!("Failed to read calibration info: {}", e))
+}
+
+/// Freeze or release a device's display. While held, readings and the
+/// background poller repeat the frozen value, flagged `metadata.held`.
+pub async fn set_hold(
+    device_id: String,
+    enabled: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("hold", device.set_hold(enabled))
+        .await
+        .map_err(|e| format!("Failed to set hold: {}", e))?;
+    Ok(format!(
+        "Hold {} on device {}",
+        if enabled { "enabled" } else { "released" },
+        device_id
+    ))
+}
+
+/// Capture a reading on demand, recorded like any other; while the display
+/// is held the capture becomes the new held reading
+pub async fn trigger_capture(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Measurement, String> {
+    let (managed_device, global_preferences) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.device(&device_id)?,
+            state_guard.config.display_preferences.clone(),
+        )
+    };
+    let mut device = managed_device.lock_device().await?;
+    let mut measurement = managed_device
+        .timed("trigger", device.trigger_capture())
+        .await
+        .inspect_err(|_| managed_device.stats.record_error())
+        .map_err(|e| format!("Failed to trigger capture: {}", e))?;
+    measurement.metadata.timestamp_source = Some(TimestampSource::Host);
+    let measurement = managed_device
+        .record(&device_id, measurement, global_preferences)
+        .await;
+    drop(device);
+    Ok(measurement)
+}
+
+/// Start MIN MAX recording on a device
+pub async fn start_recording(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("start_recording", device.start_recording())
+        .await
+        .map_err(|e| format!("Failed to start recording: {}", e))?;
+    Ok(format!("Started MIN MAX recording on device {}", device_id))
+}
+
+/// Stop MIN MAX recording on a device
+pub async fn stop_recording(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("stop_recording", device.stop_recording())
+        .await
+        .map_err(|e| format!("Failed to stop recording: {}", e))?;
+    Ok(format!("Stopped MIN MAX recording on device {}", device_id))
+}
+
+/// Read the minimum, maximum and average a device recorded
+pub async fn get_recording_summary(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<RecordingSummary, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("recording_summary", device.get_recording_summary())
+        .await
+        .map_err(|e| format!("Failed to read recording: {}", e))
+}
+
+/// List the single measurements saved on a device
+pub async fn list_saved_measurements(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<SavedMeasurement>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("saved_measurements", device.list_saved_measurements())
+        .await
+        .map_err(|e| format!("Failed to list saved measurements: {}", e))
+}
+
+/// Read one saved measurement of a device, `None` if its slot is empty
+pub async fn get_saved_measurement(
+    device_id: String,
+    index: usize,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Option<SavedMeasurement>, String> {
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut device = managed_device.lock_device().await?;
+    managed_device
+        .timed("saved_measurement", device.get_saved_measurement(index))
+        .await
+        .map_err(|e| format!("Failed to read saved measurement: {}", e))
+}
+
+/// Get list of connected devices
+pub async fn get_connected_devices(
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Vec<DeviceListItem>, String> {
+    let state_guard = state.lock().await;
+
+    let device_list = state_guard
+        .devices
+        .iter()
+        .map(|(id, managed_device)| managed_device.list_item(id.clone()))
+        .collect();
+
+    Ok(device_list)
+}
+
+/// Read a device and store the reading under `label`
+pub async fn capture_measurement(
+    device_id: String,
+    label: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<Capture, String> {
+    if label.trim().is_empty() {
+        return Err("Capture label must not be empty".to_string());
+    }
+    let measurement = take_measurement(&device_id, None, true, None, state).await?;
+    state
+        .lock()
+        .await
+        .captures
+        .insert(&device_id, label, measurement)
+}
+
+/// List a device's captures in the order they were first taken
+pub async fn get_captures(device_id: String, state: &Arc<Mutex<AppState>>) -> Vec<Capture> {
+    state.lock().await.captures.list(&device_id)
+}
+
+/// Delete every capture of a device
+pub async fn clear_captures(device_id: String, state: &Arc<Mutex<AppState>>) -> String {
+    let removed = state.lock().await.captures.clear(&device_id);
+    format!("Removed {} captures of device {}", removed, device_id)
+}
+
+/// Latency statistics of a device's recent commands, by command type
+pub async fn get_command_timing(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<BTreeMap<String, CommandTiming>, String> {
+    Ok(managed_device(&device_id, state).await?.timings.profile())
+}
+
+/// Get the connection event log, oldest first, optionally for one device
+pub async fn get_connection_events(
+    device_id: Option<String>,
+    state: &Arc<Mutex<AppState>>,
+) -> Vec<ConnectionEvent> {
+    let state_guard = state.lock().await;
+    state_guard
+        .events
+        .iter()
+        .filter(|event| device_id.as_ref().is_none_or(|id| &event.device_id == id))
+        .cloned()
+        .collect()
+}
+
+/// Get available serial ports
+pub fn get_available_ports() -> std::result::Result<Vec<String>, String> {
+    let ports = serialport::available_ports()
+        .map_err(|e| format!("Failed to get available ports: {:?}", e))?
+        .into_iter()
+        .map(|p| p.port_name)
+        .collect();
+
+    Ok(ports)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conversion::DEFAULT_DBM_REFERENCE_OHMS;
+    use crate::device::mock::{LatencyDistribution, MockProfileKind};
+    use std::sync::atomic::AtomicU64;
+
+    pub(super) fn shared_state() -> Arc<Mutex<AppState>> {
+        Arc::new(Mutex::new(AppState::new()))
+    }
+
+    pub(super) async fn connect_mock(
+        port: &str,
+        state: &Arc<Mutex<AppState>>,
+    ) -> std::result::Result<ConnectDeviceResponse, String> {
+        connect_mock_with(port, DeviceOptions::default(), state).await
+    }
+
+    pub(super) async fn connect_mock_with(
+        port: &str,
+        options: DeviceOptions,
+        state: &Arc<Mutex<AppState>>,
+    ) -> std::result::Result<ConnectDeviceResponse, String> {
+        connect_device("Mock".to_string(), Some(port.to_string()), options, state).await
+    }
+
+    /// A reproducible mock voltage source answering without delay
+    pub(super) fn instant_mock() -> DeviceOptions {
+        DeviceOptions {
+            mock: MockConfig {
+                seed: Some(1),
+                connect_latency: Some(LatencyDistribution::Fixed { ms: 0 }),
+                measurement_latency: Some(LatencyDistribution::Fixed { ms: 0 }),
+                profile: Some(MockProfileKind::VoltageSine),
+                ..MockConfig::default()
+            },
+            ..DeviceOptions::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn concurrent_connects_to_one_port_admit_one_device() {
+        let state = shared_state();
+
+        let (first, second) = tokio::join!(
+            connect_mock("/dev/ttyTEST0", &state),
+            connect_mock("/dev/ttyTEST0", &state)
+        );
+
+        assert!(first.is_ok() != second.is_ok());
+        let error = first.and(second).unwrap_err();
+        assert!(error.contains("already in use"), "{}", error);
+        let state_guard = state.lock().await;
+        assert_eq!(state_guard.devices.len(), 1);
+        assert!(state_guard.connecting.is_empty());
+    }
+
+    #[tokio::test]
+    async fn connect_with_a_nonstandard_baud_rate_is_refused() {
+        let state = shared_state();
+        let options = DeviceOptions {
+            baud: Some(14_400),
+            ..DeviceOptions::default()
+        };
+
+        let error = connect_device("Mock".to_string(), None, options, &state)
+            .await
+            .unwrap_err();
+        assert!(error.contains("Unsupported baud rate 14400"), "{}", error);
+        assert!(state.lock().await.devices.is_empty());
+    }
+
+    #[tokio::test]
+    async fn disconnected_ids_are_told_apart_from_unknown_ones() {
+        let state = shared_state();
+        let device = connect_mock("/dev/ttyTEST0", &state).await.unwrap();
+        disconnect(device.id.clone(), true, false, &state)
+            .await
+            .unwrap();
+
+        let state_guard = state.lock().await;
+        let gone = state_guard.unavailable(&device.id);
+        assert!(gone.contains("disconnected"), "{}", gone);
+        for unknown in ["device_0002", "device_1", "device_x", "mock"] {
+            let error = state_guard.unavailable(unknown);
+            assert_eq!(error, format!("Device {} not found", unknown));
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn last_never_goes_back_behind_a_completed_read() {
+        let state = shared_state();
+        let device = connect_mock_with("/dev/ttyTEST0", instant_mock(), &state)
+            .await
+            .unwrap();
+        let completed = Arc::new(AtomicU64::new(0));
+
+        let readers: Vec<_> = (0..4)
+            .map(|_| {
+                let (state, device_id, completed) =
+                    (state.clone(), device.id.clone(), completed.clone());
+                tokio::spawn(async move {
+                    for _ in 0..25 {
+                        let measurement = measure(&device_id, None, false, None, &state)
+                            .await
+                            .unwrap();
+                        completed.fetch_max(measurement.sequence, Ordering::SeqCst);
+                    }
+                })
+            })
+            .collect();
+        let watchers: Vec<_> = (0..4)
+            .map(|_| {
+                let (state, device_id, completed) =
+                    (state.clone(), device.id.clone(), completed.clone());
+                tokio::spawn(async move {
+                    let mut previous = 0;
+                    for _ in 0..200 {
+                        let floor = completed.load(Ordering::SeqCst);
+                        let Ok(last) = get_last_measurement(device_id.clone(), &state).await else {
+                            continue;
+                        };
+                        assert!(last.sequence >= floor, "{} < {}", last.sequence, floor);
+                        assert!(
+                            last.sequence >= previous,
+                            "{} < {}",
+                            last.sequence,
+                            previous
+                        );
+                        previous = last.sequence;
+                        tokio::task::yield_now().await;
+                    }
+                })
+            })
+            .collect();
+
+        for task in readers.into_iter().chain(watchers) {
+            task.await.unwrap();
+        }
+        let last = get_last_measurement(device.id, &state).await.unwrap();
+        assert_eq!(last.sequence, 100);
+    }
+
+    #[tokio::test]
+    async fn read_count_goes_up_on_each_successful_read() {
+        let state = shared_state();
+        let id = connect_mock_with("COM_COUNT", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+
+        for expected in 1..=3 {
+            measure(&id, None, false, None, &state).await.unwrap();
+            let status = get_device_status(id.clone(), &state).await.unwrap();
+            assert_eq!(status.measurement_count, expected);
+            assert_eq!(status.total_samples, expected);
+        }
+
+        let failing = DeviceOptions {
+            mock: MockConfig {
+                fault_rate: Some(1.0),
+                ..instant_mock().mock
+            },
+            ..DeviceOptions::default()
+        };
+        let id = connect_mock_with("COM_COUNT_FAILING", failing, &state)
+            .await
+            .unwrap()
+            .id;
+        measure(&id, None, false, None, &state).await.unwrap_err();
+        let status = get_device_status(id, &state).await.unwrap();
+        assert_eq!(status.measurement_count, 0);
+    }
+
+    #[tokio::test]
+    async fn connect_disconnect_cycle_is_logged() {
+        let state = shared_state();
+        let first = connect_mock_with("COM_EVENTS", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        disconnect_device(first.clone(), false, &state)
+            .await
+            .unwrap();
+        let second = connect_mock_with("COM_EVENTS", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+
+        let events = get_connection_events(None, &state).await;
+        let log: Vec<_> = events
+            .iter()
+            .map(|event| (event.device_id.as_str(), event.kind))
+            .collect();
+        assert_eq!(
+            log,
+            [
+                (first.as_str(), ConnectionEventKind::Connected),
+                (first.as_str(), ConnectionEventKind::Disconnected),
+                (second.as_str(), ConnectionEventKind::Connected),
+            ]
+        );
+        assert_eq!(events[0].detail.as_deref(), Some("Mock on COM_EVENTS"));
+
+        let events = get_connection_events(Some(first), &state).await;
+        assert_eq!(events.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn configure_mock_switches_the_waveform_mid_session() {
+        let state = shared_state();
+        let id = connect_mock_with("COM_RECONFIGURE", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let before = measure(&id, None, false, None, &state).await.unwrap();
+        assert_eq!(before.unit, Unit::VoltDc);
+
+        let config = MockConfig {
+            profile: Some(MockProfileKind::ResistanceSweep),
+            ..instant_mock().mock
+        };
+        configure_mock(id.clone(), config, &state).await.unwrap();
+
+        let after = measure(&id, None, false, None, &state).await.unwrap();
+        assert_eq!(after.unit, Unit::Ohm);
+        assert!(after.sequence > before.sequence);
+        let status = get_device_status(id, &state).await.unwrap();
+        assert!(status.connected);
+    }
+
+    #[tokio::test]
+    async fn settle_ms_returns_the_settled_value() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.mock.profile = Some(MockProfileKind::TemperatureDrift);
+        options.settle_ms = Some(500);
+        let id = connect_mock_with("COM_SETTLE", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        let started = std::time::Instant::now();
+        let settled = measure(&id, None, false, None, &state).await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(500));
+        // The mock ramps up with a 100 ms time constant, so a reading taken
+        // without the delay would be well short of the signal.
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        let later = measure(&id, None, false, None, &state).await.unwrap();
+
+        assert!(
+            (settled.value - later.value).abs() < later.value.abs() * 0.03,
+            "settled {} vs later {}",
+            settled.value,
+            later.value
+        );
+        // The transient reading is discarded, not recorded
+        assert_eq!(settled.sequence, 1);
+        assert_eq!(later.sequence, 2);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn queued_commands_run_in_arrival_order() {
+        let state = shared_state();
+        let id = connect_mock_with("COM_ORDER", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let managed_device = state.lock().await.device(&id).unwrap();
+
+        // Hold the device so every command queues behind it
+        let held = managed_device.device.lock().await;
+        let mut tasks = Vec::new();
+        for _ in 0..5 {
+            let (id, state) = (id.clone(), state.clone());
+            tasks.push(tokio::spawn(async move {
+                measure(&id, None, false, None, &state)
+                    .await
+                    .unwrap()
+                    .sequence
+            }));
+            // Let the command reach the queue before the next one is sent
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        drop(held);
+
+        let mut sequences = Vec::new();
+        for task in tasks {
+            sequences.push(task.await.unwrap());
+        }
+        assert_eq!(sequences, [1, 2, 3, 4, 5]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn measurement_and_reset_serialize_or_refuse() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.mock.measurement_latency = Some(LatencyDistribution::Fixed { ms: 50 });
+        let id = connect_mock_with("COM_RESET_RACE", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        // A reset already queued refuses the measurement outright
+        let reset = {
+            let (id, state) = (id.clone(), state.clone());
+            tokio::spawn(async move { reset_device(id, &state).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let error = measure(&id, None, false, None, &state).await.unwrap_err();
+        assert!(error.starts_with("Device busy"), "{}", error);
+        reset.await.unwrap().unwrap();
+
+        // A measurement already running completes before the reset
+        let reading = {
+            let (id, state) = (id.clone(), state.clone());
+            tokio::spawn(async move { measure(&id, None, false, None, &state).await })
+        };
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        reset_device(id.clone(), &state).await.unwrap();
+        let measurement = reading.await.unwrap().unwrap();
+        assert_eq!(measurement.unit, Unit::VoltDc);
+        assert_eq!(measurement.state, MeasurementState::Normal);
+        assert!(measurement.value.is_finite());
+
+        // Started together, either order is fine but nothing is corrupted
+        for _ in 0..5 {
+            let (reading, reset) = tokio::join!(
+                measure(&id, None, false, None, &state),
+                reset_device(id.clone(), &state)
+            );
+            reset.unwrap();
+            match reading {
+                Ok(measurement) => {
+                    assert_eq!(measurement.unit, Unit::VoltDc);
+                    assert!(measurement.value.is_finite());
+                }
+                Err(error) => assert!(error.starts_with("Device busy"), "{}", error),
+            }
+        }
+        let status = get_device_status(id, &state).await.unwrap();
+        assert!(status.connected);
+    }
+
+    #[tokio::test]
+    async fn recording_state_follows_start_and_stop() {
+        let state = shared_state();
+        let id = connect_mock_with("COM_RECORDING", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let error = get_recording_summary(id.clone(), &state).await.unwrap_err();
+        assert!(error.contains("No MIN MAX recording"), "{}", error);
+
+        start_recording(id.clone(), &state).await.unwrap();
+        measure(&id, None, false, None, &state).await.unwrap();
+        assert!(
+            get_recording_summary(id.clone(), &state)
+                .await
+                .unwrap()
+                .active
+        );
+
+        stop_recording(id.clone(), &state).await.unwrap();
+        let stopped = get_recording_summary(id.clone(), &state).await.unwrap();
+        assert!(!stopped.active);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let later = get_recording_summary(id.clone(), &state).await.unwrap();
+        assert_eq!(later.duration_secs, stopped.duration_secs);
+        assert!(stop_recording(id.clone(), &state).await.is_err());
+
+        start_recording(id.clone(), &state).await.unwrap();
+        let restarted = get_recording_summary(id, &state).await.unwrap();
+        assert!(restarted.active);
+        assert!(restarted.started_at >= stopped.started_at);
+    }
+
+    #[tokio::test]
+    async fn labeled_capture_is_stored_and_retrieved() {
+        let state = shared_state();
+        let id = connect_mock_with("COM_CAPTURE", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+
+        let capture = capture_measurement(id.clone(), "baseline".to_string(), &state)
+            .await
+            .unwrap();
+        measure(&id, None, false, None, &state).await.unwrap();
+
+        let captures = get_captures(id.clone(), &state).await;
+        assert_eq!(captures.len(), 1);
+        assert_eq!(captures[0].label, "baseline");
+        assert_eq!(
+            captures[0].measurement.sequence,
+            capture.measurement.sequence
+        );
+        assert_eq!(captures[0].measurement.value, capture.measurement.value);
+        // Later readings don't touch the capture
+        assert_eq!(captures[0].measurement.sequence, 1);
+
+        let error = capture_measurement(id.clone(), " ".to_string(), &state)
+            .await
+            .unwrap_err();
+        assert!(error.contains("label"), "{}", error);
+        clear_captures(id.clone(), &state).await;
+        assert!(get_captures(id, &state).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn mismatched_unit_is_converted_or_refused() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.mock.profile = Some(MockProfileKind::TemperatureDrift);
+        let id = connect_mock_with("COM_UNIT", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        // Celsius converts to the other temperature scale
+        let reading = get_measurement(
+            id.clone(),
+            None,
+            Some(Unit::Fahrenheit),
+            DEFAULT_DBM_REFERENCE_OHMS,
+            false,
+            &state,
+        )
+        .await
+        .unwrap();
+        let reading: Measurement = serde_json::from_value(reading).unwrap();
+        assert_eq!(reading.unit, Unit::Fahrenheit);
+        let celsius = get_last_measurement(id.clone(), &state).await.unwrap();
+        assert_eq!(celsius.unit, Unit::Celsius);
+        assert!((reading.value - (celsius.value * 9.0 / 5.0 + 32.0)).abs() < 1e-9);
+
+        // but not into volts
+        let error = get_measurement(
+            id,
+            None,
+            Some(Unit::VoltDc),
+            DEFAULT_DBM_REFERENCE_OHMS,
+            false,
+            &state,
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            error,
+            Error::UnexpectedUnit {
+                expected: Unit::VoltDc,
+                actual: Unit::Celsius,
+            }
+            .to_string()
+        );
+    }
+
+    #[tokio::test]
+    async fn scan_reports_only_the_ports_that_answered() {
+        let ports = ["mock0", "mock1", "mock2"].map(String::from).to_vec();
+        let started = std::time::Instant::now();
+
+        // The mock takes 150 ms to connect and identify, so the short probe
+        // of mock1 times out and mock2 fails outright.
+        let results = scan(ports, |port| async move {
+            match port.as_str() {
+                "mock0" => probe_port(&port, DeviceType::Mock, Duration::from_secs(1)).await,
+                "mock1" => probe_port(&port, DeviceType::Mock, Duration::from_millis(10)).await,
+                _ => Err("No such port".to_string()),
+            }
+        })
+        .await
+        .unwrap();
+
+        assert!(started.elapsed() < Duration::from_secs(1));
+        let ports: Vec<_> = results.iter().map(|r| r.port.as_str()).collect();
+        assert_eq!(ports, ["mock0", "mock1", "mock2"]);
+        assert_eq!(
+            results[0].info.as_ref().unwrap().serial_number,
+            "MOCK123456"
+        );
+        assert!(results[1].info.is_none());
+        assert!(results[2].info.is_none());
+    }
+
+    #[tokio::test]
+    async fn probe_gives_up_after_its_timeout() {
+        let error = probe_port("mock0", DeviceType::Mock, Duration::from_millis(10))
+            .await
+            .unwrap_err();
+        assert_eq!(error, "Probe of mock0 timed out");
+
+        // A probe that waits long enough identifies the mock
+        probe_port("mock0", DeviceType::Mock, Duration::from_secs(1))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn commands_are_timed_by_type() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.mock.measurement_latency = Some(LatencyDistribution::Fixed { ms: 10 });
+        let id = connect_mock_with("COM_TIMING", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        for _ in 0..3 {
+            measure(&id, None, false, None, &state).await.unwrap();
+        }
+        send_raw_command(id.clone(), "qm".to_string(), false, &state)
+            .await
+            .unwrap();
+        send_raw_command(id.clone(), "ID".to_string(), false, &state)
+            .await
+            .unwrap();
+
+        let timings = get_command_timing(id, &state).await.unwrap();
+        assert_eq!(
+            timings.keys().collect::<Vec<_>>(),
+            ["ID", "QM", "identify", "measurement"]
+        );
+        let measurement = &timings["measurement"];
+        assert_eq!((measurement.count, measurement.window), (3, 3));
+        // 10 ms latency plus one power line cycle of integration
+        assert!(measurement.min_ms >= 30.0, "{:?}", measurement);
+        assert!(measurement.min_ms <= measurement.avg_ms);
+        assert!(measurement.avg_ms <= measurement.max_ms);
+        assert_eq!(timings["QM"].count, 1);
+        assert_eq!(timings["identify"].count, 1);
+        assert!(timings["ID"].min_ms >= 30.0, "{:?}", timings["ID"]);
+    }
+
+    /// Drop the mock's link behind the backend's back, as a pulled cable would
+    async fn pull_cable(id: &str, state: &Arc<Mutex<AppState>>) {
+        let managed_device = state.lock().await.device(id).unwrap();
+        managed_device
+            .device
+            .lock()
+            .await
+            .disconnect()
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn lost_connection_is_reopened_and_the_command_retried() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.auto_reconnect = Some(true);
+        let id = connect_mock_with("COM_RECONNECT", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        pull_cable(&id, &state).await;
+        let measurement = measure(&id, None, true, None, &state).await.unwrap();
+        assert_eq!(measurement.unit, Unit::VoltDc);
+
+        pull_cable(&id, &state).await;
+        let reply = send_raw_command(id.clone(), "ID".to_string(), true, &state)
+            .await
+            .unwrap();
+        assert!(reply.contains("MOCK123456"), "{}", reply);
+
+        let attempts = get_connection_events(Some(id.clone()), &state)
+            .await
+            .into_iter()
+            .filter(|event| event.kind == ConnectionEventKind::ReconnectAttempt)
+            .count();
+        assert_eq!(attempts, 2);
+
+        // The per-request opt-out surfaces the error instead
+        pull_cable(&id, &state).await;
+        let error = measure(&id, None, false, None, &state).await.unwrap_err();
+        assert!(error.contains("Not connected"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn timeouts_are_not_retried() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.auto_reconnect = Some(true);
+        options.mock.fault_rate = Some(1.0);
+        let id = connect_mock_with("COM_TIMEOUT", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        let error = measure(&id, None, true, None, &state).await.unwrap_err();
+
+        assert!(error.contains("Timeout"), "{}", error);
+        let events = get_connection_events(Some(id), &state).await;
+        assert!(events
+            .iter()
+            .all(|event| event.kind != ConnectionEventKind::ReconnectAttempt));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn disconnect_during_a_measurement_tears_down_cleanly() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.mock.measurement_latency = Some(LatencyDistribution::Fixed { ms: 100 });
+        let id = connect_mock_with("COM_TEARDOWN", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        let spawn_measure = |settle: Option<Duration>| {
+            let (id, state) = (id.clone(), state.clone());
+            tokio::spawn(async move { measure(&id, settle, false, None, &state).await })
+        };
+        let in_flight = spawn_measure(None);
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        let queued = spawn_measure(None);
+        let settling = spawn_measure(Some(Duration::from_secs(10)));
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        let started = std::time::Instant::now();
+        disconnect_device(id.clone(), true, &state).await.unwrap();
+
+        // The command talking to the device finishes; the others give up
+        // rather than run against the closing port.
+        let measurement = in_flight.await.unwrap().unwrap();
+        assert_eq!(measurement.unit, Unit::VoltDc);
+        let error = queued.await.unwrap().unwrap_err();
+        assert_eq!(error, disconnecting_error());
+        settling.await.unwrap().unwrap_err();
+        assert!(started.elapsed() < Duration::from_secs(2));
+
+        let error = measure(&id, None, false, None, &state).await.unwrap_err();
+        assert!(error.contains("disconnected"), "{}", error);
+        assert!(get_connected_devices(&state).await.unwrap().is_empty());
+        connect_mock_with("COM_TEARDOWN", instant_mock(), &state)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn async_connect_returns_before_the_device_is_ready() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.mock.connect_latency = Some(LatencyDistribution::Fixed { ms: 300 });
+
+        let started = std::time::Instant::now();
+        let pending = connect_device_async(
+            "Mock".to_string(),
+            Some("COM_ASYNC".to_string()),
+            options,
+            &state,
+        )
+        .await
+        .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(100));
+
+        let connecting = get_connecting_devices(&state).await;
+        assert_eq!(connecting.len(), 1);
+        assert_eq!(connecting[0].id, pending.id);
+        let error = measure(&pending.id, None, false, None, &state)
+            .await
+            .unwrap_err();
+        assert!(error.contains("is connecting"), "{}", error);
+
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while get_device_status(pending.id.clone(), &state).await.is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("device never connected");
+        assert!(get_connecting_devices(&state).await.is_empty());
+        measure(&pending.id, None, false, None, &state)
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn quick_reconnect_takes_over_the_open_port() {
+        let state = shared_state();
+        state.lock().await.config.port_linger_ms = 200;
+        let first = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let handle = state.lock().await.device(&first).unwrap().device;
+        disconnect_device(first, false, &state).await.unwrap();
+
+        let second = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let reused = state.lock().await.device(&second).unwrap().device;
+        assert!(Arc::ptr_eq(&handle, &reused));
+        assert!(reused.lock().await.is_connected());
+
+        // Once the linger has passed the port is closed and a new one opened
+        disconnect_device(second, false, &state).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        assert!(!handle.lock().await.is_connected());
+        let third = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let reopened = state.lock().await.device(&third).unwrap().device;
+        assert!(!Arc::ptr_eq(&handle, &reopened));
+    }
+
+    #[tokio::test]
+    async fn readings_name_the_clock_that_stamped_them() {
+        let state = shared_state();
+        let host = connect_mock_with("/dev/ttyTEST0", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let options = DeviceOptions {
+            timestamp_source: TimestampSource::Device,
+            ..instant_mock()
+        };
+        let device = connect_mock_with("/dev/ttyTEST1", options, &state)
+            .await
+            .unwrap()
+            .id;
+        let meter_time = chrono::Utc::now() + chrono::Duration::days(1);
+        set_device_clock(device.clone(), meter_time, &state)
+            .await
+            .unwrap();
+
+        let reading = take_measurement(&host, None, false, None, &state)
+            .await
+            .unwrap();
+        assert_eq!(
+            reading.metadata.timestamp_source,
+            Some(TimestampSource::Host)
+        );
+
+        let reading = take_measurement(&device, None, false, None, &state)
+            .await
+            .unwrap();
+        assert_eq!(
+            reading.metadata.timestamp_source,
+            Some(TimestampSource::Device)
+        );
+        let drift = reading.timestamp.unwrap() - meter_time;
+        assert!(drift.num_seconds().abs() < 5, "{}", drift);
+    }
+
+    #[tokio::test]
+    async fn device_failing_every_read_is_disconnected_at_the_limit() {
+        let state = shared_state();
+        state.lock().await.config.max_consecutive_errors = 3;
+        let mut options = instant_mock();
+        options.mock.fault_rate = Some(1.0);
+        let id = connect_mock_with("COM_FAULTY", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        for _ in 0..2 {
+            take_measurement(&id, None, false, None, &state)
+                .await
+                .unwrap_err();
+            assert!(state.lock().await.device(&id).is_ok());
+        }
+        take_measurement(&id, None, false, None, &state)
+            .await
+            .unwrap_err();
+
+        assert!(state.lock().await.device(&id).is_err());
+        let kinds: Vec<_> = get_connection_events(Some(id), &state)
+            .await
+            .into_iter()
+            .map(|event| event.kind)
+            .collect();
+        assert_eq!(
+            kinds[kinds.len() - 2..],
+            [
+                ConnectionEventKind::ErrorLimitReached,
+                ConnectionEventKind::Disconnected
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn disconnect_summarizes_the_buffered_readings() {
+        let state = shared_state();
+        let id = connect_mock_with("/dev/ttyTEST0", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let mut values = Vec::new();
+        for _ in 0..3 {
+            let reading = take_measurement(&id, None, false, None, &state)
+                .await
+                .unwrap();
+            values.push(reading.value);
+        }
+
+        let summary = disconnect_device(id.clone(), false, &state)
+            .await
+            .unwrap()
+            .summary;
+
+        assert_eq!(summary.device_id, id);
+        assert_eq!(summary.measurement_count, 3);
+        assert_eq!(summary.sample_count, 3);
+        assert_eq!(summary.dropped_samples, 0);
+        assert_eq!(summary.error_count, 0);
+        assert_eq!(summary.units.len(), 1);
+        let volts = &summary.units[0];
+        assert_eq!(volts.unit, Unit::VoltDc);
+        assert_eq!(volts.count, 3);
+        assert_eq!(
+            volts.min,
+            values.iter().copied().fold(f64::INFINITY, f64::min)
+        );
+        assert_eq!(
+            volts.max,
+            values.iter().copied().fold(f64::NEG_INFINITY, f64::max)
+        );
+        assert!((volts.mean - values.iter().sum::<f64>() / 3.0).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn disconnect_is_refused_while_subscribed_unless_forced() {
+        let state = shared_state();
+        let first = connect_mock_with("/dev/ttyTEST0", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let second = connect_mock_with("/dev/ttyTEST1", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let interval = Duration::from_secs(60);
+
+        let updates = polling::subscribe(&first, interval, &state).await.unwrap();
+        let error = disconnect_device(first.clone(), false, &state)
+            .await
+            .unwrap_err();
+        assert!(error.contains("1 stream subscribers"), "{}", error);
+        assert!(state.lock().await.device(&first).is_ok());
+        drop(updates);
+        disconnect_device(first, false, &state).await.unwrap();
+
+        let mut updates = polling::subscribe(&second, interval, &state).await.unwrap();
+        disconnect_device(second.clone(), true, &state)
+            .await
+            .unwrap();
+        assert!(state.lock().await.device(&second).is_err());
+        loop {
+            match updates.recv().await {
+                Err(broadcast::error::RecvError::Closed) => break,
+                Err(broadcast::error::RecvError::Lagged(_)) | Ok(_) => {}
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn failed_connect_ids_are_not_reported_as_disconnected() {
+        let state = shared_state();
+        // Checked by the mock on connect, not when the request is made
+        let mut broken = instant_mock();
+        broken.mock.fault_rate = Some(2.0);
+
+        connect_mock_with("COM_BROKEN", broken.clone(), &state)
+            .await
+            .unwrap_err();
+        let pending = connect_device_async(
+            "Mock".to_string(),
+            Some("COM_BROKEN".to_string()),
+            broken,
+            &state,
+        )
+        .await
+        .unwrap();
+        tokio::time::timeout(Duration::from_secs(2), async {
+            while !get_connecting_devices(&state).await.is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("connect never finished");
+
+        let state_guard = state.lock().await;
+        for device_id in ["device_0001", pending.id.as_str()] {
+            let error = state_guard.unavailable(device_id);
+            assert!(error.contains("connect failed"), "{}", error);
+            assert!(!error.contains("disconnected"), "{}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn polled_reading_is_not_served_once_polling_fails() {
+        let state = shared_state();
+        state.lock().await.config.max_consecutive_errors = 0;
+        let id = connect_mock_with("COM_POLLED", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let read = || {
+            get_measurement(
+                id.clone(),
+                None,
+                None,
+                DEFAULT_DBM_REFERENCE_OHMS,
+                false,
+                &state,
+            )
+        };
+        let subscribers = state.lock().await.device(&id).unwrap().subscribers;
+        let until = |failing: bool| {
+            let subscribers = subscribers.clone();
+            tokio::time::timeout(Duration::from_secs(2), async move {
+                while subscribers.poll_error().is_some() != failing {
+                    tokio::time::sleep(Duration::from_millis(5)).await;
+                }
+            })
+        };
+
+        let mut updates = polling::subscribe(&id, Duration::from_millis(20), &state)
+            .await
+            .unwrap();
+        updates.recv().await.unwrap();
+        read().await.unwrap();
+
+        let failing = MockConfig {
+            fault_rate: Some(1.0),
+            ..instant_mock().mock
+        };
+        configure_mock(id.clone(), failing, &state).await.unwrap();
+        until(true).await.expect("poller never failed");
+        let error = read().await.unwrap_err();
+        assert!(error.contains("Timeout"), "{}", error);
+
+        configure_mock(id.clone(), instant_mock().mock, &state)
+            .await
+            .unwrap();
+        until(false).await.expect("poller never recovered");
+        read().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn history_limit_is_clamped_to_the_buffer() {
+        let state = Arc::new(Mutex::new(AppState::with_store(
+            Config::default(),
+            Box::new(InMemoryStore::new(4, None)),
+        )));
+        let id = connect_mock_with("COM_HISTORY", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        for _ in 0..6 {
+            take_measurement(&id, None, false, None, &state)
+                .await
+                .unwrap();
+        }
+        let sequences = |limit| {
+            let id = id.clone();
+            let state = state.clone();
+            async move {
+                get_history(id, None, None, limit, &state)
+                    .await
+                    .unwrap()
+                    .iter()
+                    .map(|m| m.sequence)
+                    .collect::<Vec<_>>()
+            }
+        };
+
+        assert_eq!(sequences(None).await, [3, 4, 5, 6]);
+        assert_eq!(sequences(Some(2)).await, [5, 6]);
+        assert_eq!(sequences(Some(100)).await, [3, 4, 5, 6]);
+        assert!(sequences(Some(0)).await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn scripted_drop_is_recovered_by_reconnecting() {
+        let state = shared_state();
+        let mut options = instant_mock();
+        options.auto_reconnect = Some(true);
+        options.mock.disconnect_after = Some(2);
+        let id = connect_mock_with("COM_DROP", options, &state)
+            .await
+            .unwrap()
+            .id;
+
+        for _ in 0..5 {
+            measure(&id, None, true, None, &state).await.unwrap();
+        }
+
+        let kinds: Vec<_> = get_connection_events(Some(id.clone()), &state)
+            .await
+            .into_iter()
+            .map(|event| event.kind)
+            .filter(|kind| *kind != ConnectionEventKind::Connected)
+            .collect();
+        assert_eq!(kinds, [ConnectionEventKind::ReconnectAttempt]);
+        let status = get_device_status(id, &state).await.unwrap();
+        assert!(status.connected);
+        assert_eq!(status.measurement_count, 5);
+    }
+}
@@ -0,0 +1,684 @@
+//! Measurement fan-out and background polling for live subscribers
+//!
+//! Every measurement recorded for a device is broadcast to its subscribers,
+//! whichever request took it. A device's channel is created with its first
+//! subscriber, together with the single poller task that keeps readings
+//! flowing, and both are torn down once the last subscriber is gone, so a
+//! device nobody listens to is never polled.
+//!
+//! Polling can be paused without disconnecting, e.g. while probes are
+//! moved. The poller keeps running but skips its reads, and subscribers are
+//! told where the gap starts and ends.
+//!
+//! A reading the meter auto-saved is followed by an auto-save event
+//! carrying it.
+//!
+//! A consumer slower than the poller reads through `LatestUpdates`, which
+//! skips stale readings so it always gets the newest one.
+//!
+//! Polling can also be started explicitly, without subscribers, to keep the
+//! history buffer filling; it then runs until stopped or the device is
+//! disconnected, and measurement requests are answered from its latest
+//! reading instead of querying the device again.
+//!
+//! With a snap tolerance, the poller keeps to a fixed grid and moves the
+//! timestamp of each polled reading onto its grid point, so scheduler
+//! jitter doesn't make the series uneven.
+
+use super::{managed_device, take_measurement, AppState};
+use crate::config::StreamConfig;
+use crate::device::Measurement;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::{RecvError, TryRecvError};
+use tokio::sync::{broadcast, Mutex};
+
+/// Readings buffered per device before a slow subscriber starts skipping
+const UPDATE_CHANNEL_CAPACITY: usize = 64;
+
+/// Interval of explicitly started polling unless one is given
+pub const DEFAULT_POLL_INTERVAL_MS: u64 = 500;
+
+/// Shortest interval accepted for explicitly started polling
+pub const MIN_POLL_INTERVAL_MS: u64 = 10;
+
+/// What subscribers of a device receive
+#[derive(Debug, Clone)]
+pub enum Update {
+    Measurement(Measurement),
+    /// Polling was paused or resumed; the gap in readings starts or ends here
+    Acquisition {
+        paused: bool,
+        at: DateTime<Utc>,
+    },
+    /// The meter saved this stable reading to its memory on its own
+    AutoSave(Measurement),
+}
+
+impl Update {
+    /// The update as stream clients receive it: a measurement as is, the
+    /// others as an `event` with its timestamp
+    pub fn to_json(&self) -> serde_json::Result<serde_json::Value> {
+        Ok(match self {
+            Update::Measurement(measurement) => serde_json::to_value(measurement)?,
+            Update::Acquisition { paused, at } => {
+                let event = if *paused { "paused" } else { "resumed" };
+                serde_json::json!({ "event": event, "timestamp": at })
+            }
+            Update::AutoSave(measurement) => serde_json::json!({
+                "event": "auto_saved",
+                "timestamp": measurement.timestamp,
+                "measurement": measurement,
+            }),
+        })
+    }
+}
+
+/// Subscription that delivers only the newest measurement to a consumer
+/// that fell behind. Readings queued behind a newer one are dropped and
+/// counted; other updates are always delivered, in order.
+pub struct LatestUpdates {
+    receiver: broadcast::Receiver<Update>,
+    /// Update received while looking ahead past a measurement
+    pending: Option<Update>,
+    dropped: u64,
+}
+
+impl LatestUpdates {
+    pub fn new(receiver: broadcast::Receiver<Update>) -> Self {
+        Self {
+            receiver,
+            pending: None,
+            dropped: 0,
+        }
+    }
+
+    /// Updates skipped so far, including any the channel overwrote before
+    /// they were read
+    pub fn dropped(&self) -> u64 {
+        self.dropped
+    }
+
+    /// Wait for the next update; `None` once the channel is closed.
+    ///
+    /// Cancel safe: the only await is on the channel itself.
+    pub async fn recv(&mut self) -> Option<Update> {
+        let mut update = match self.pending.take() {
+            Some(update) => update,
+            None => loop {
+                match self.receiver.recv().await {
+                    Ok(update) => break update,
+                    Err(RecvError::Lagged(skipped)) => self.dropped += skipped,
+                    Err(RecvError::Closed) => return None,
+                }
+            },
+        };
+        while matches!(update, Update::Measurement(_)) {
+            match self.receiver.try_recv() {
+                Ok(newer @ Update::Measurement(_)) => {
+                    self.dropped += 1;
+                    update = newer;
+                }
+                Ok(other) => {
+                    self.pending = Some(other);
+                    break;
+                }
+                Err(TryRecvError::Lagged(skipped)) => self.dropped += skipped,
+                Err(TryRecvError::Empty | TryRecvError::Closed) => break,
+            }
+        }
+        Some(update)
+    }
+}
+
+/// The nominal times of a poller's readings: `origin + k * interval`. The
+/// first reading sets the origin, so a constant read latency doesn't
+/// count as drift.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct TimestampGrid {
+    origin: Option<DateTime<Utc>>,
+    interval: chrono::Duration,
+    tolerance: chrono::Duration,
+}
+
+impl TimestampGrid {
+    fn new(interval: Duration, tolerance: Duration) -> Option<Self> {
+        Some(Self {
+            origin: None,
+            interval: chrono::Duration::from_std(interval).ok()?,
+            tolerance: chrono::Duration::from_std(tolerance).ok()?,
+        })
+    }
+
+    /// Move the timestamp of `measurement` to the nearest grid point,
+    /// keeping the arrival time in the metadata and flagging a drift
+    /// beyond the tolerance
+    pub(crate) fn snap(&mut self, mut measurement: Measurement) -> Measurement {
+        let (Some(timestamp), Some(interval_ns)) = (
+            measurement.timestamp,
+            self.interval.num_nanoseconds().filter(|&ns| ns > 0),
+        ) else {
+            return measurement;
+        };
+        let origin = *self.origin.get_or_insert(timestamp);
+        let Some(offset_ns) = (timestamp - origin).num_nanoseconds() else {
+            return measurement;
+        };
+        let step = (offset_ns as f64 / interval_ns as f64).round() as i32;
+        let snapped = origin + self.interval * step;
+        measurement.timestamp = Some(snapped);
+        measurement.metadata.raw_timestamp = Some(timestamp);
+        measurement.metadata.timestamp_drifted = (timestamp - snapped).abs() > self.tolerance;
+        measurement
+    }
+}
+
+/// Broadcast channel of a device with live subscribers
+struct Channel {
+    sender: broadcast::Sender<Update>,
+    /// Interval of the poller started with the channel
+    interval: Duration,
+    /// Polling was started explicitly and continues without subscribers
+    held: bool,
+}
+
+/// Subscribers of one device.
+///
+/// The channel only exists while someone listens. Subscribing and the
+/// poller's idle check both happen under the lock, so a subscriber never
+/// joins a channel that is being torn down.
+#[derive(Default)]
+pub(crate) struct Subscribers {
+    channel: std::sync::Mutex<Option<Channel>>,
+    /// Set while polling is paused; survives the channel coming and going
+    paused: AtomicBool,
+    /// Error of the poller's last read, cleared by a successful one
+    poll_error: std::sync::Mutex<Option<String>>,
+}
+
+impl Subscribers {
+    fn channel(&self) -> std::sync::MutexGuard<'_, Option<Channel>> {
+        self.channel.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Join the channel, creating it if needed. Returns `true` as the second
+    /// value when the channel is new and the caller must start a poller
+    /// with `interval`.
+    fn subscribe(&self, interval: Duration) -> (broadcast::Receiver<Update>, bool) {
+        let mut channel = self.channel();
+        match channel.as_ref() {
+            Some(channel) => (channel.sender.subscribe(), false),
+            None => {
+                let (sender, receiver) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
+                *channel = Some(Channel {
+                    sender,
+                    interval,
+                    held: false,
+                });
+                (receiver, true)
+            }
+        }
+    }
+
+    /// Keep the channel and its poller alive without subscribers, creating
+    /// it if needed. Returns the interval of the poller and whether the
+    /// caller must start it.
+    fn hold(&self, interval: Duration) -> (Duration, bool) {
+        let mut channel = self.channel();
+        match channel.as_mut() {
+            Some(channel) => {
+                channel.held = true;
+                (channel.interval, false)
+            }
+            None => {
+                let (sender, _) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
+                *channel = Some(Channel {
+                    sender,
+                    interval,
+                    held: true,
+                });
+                (interval, true)
+            }
+        }
+    }
+
+    /// Let the poller stop once nobody is subscribed; returns `false` if
+    /// polling wasn't started explicitly
+    fn release(&self) -> bool {
+        match self.channel().as_mut() {
+            Some(channel) if channel.held => {
+                channel.held = false;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Drop the channel if nobody is subscribed and polling wasn't started
+    /// explicitly; returns whether it did
+    fn release_if_idle(&self) -> bool {
+        let mut channel = self.channel();
+        match channel.as_ref() {
+            Some(active) if active.held || active.sender.receiver_count() > 0 => false,
+            _ => {
+                *channel = None;
+                true
+            }
+        }
+    }
+
+    /// Send a measurement to the current subscribers, if any, followed by
+    /// an auto-save event if the meter saved it
+    pub(crate) fn publish(&self, measurement: &Measurement) {
+        self.send(Update::Measurement(measurement.clone()));
+        if measurement.metadata.auto_saved {
+            self.send(Update::AutoSave(measurement.clone()));
+        }
+    }
+
+    fn send(&self, update: Update) {
+        if let Some(channel) = self.channel().as_ref() {
+            // Fails only when every receiver dropped since the last poll.
+            let _ = channel.sender.send(update);
+        }
+    }
+
+    pub(crate) fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Acquire)
+    }
+
+    /// A poller is running and not paused, so the latest recorded reading
+    /// is at most one interval old
+    pub(crate) fn is_polling(&self) -> bool {
+        self.channel().is_some() && !self.is_paused()
+    }
+
+    /// Error of the poller's latest read, if it failed. The last recorded
+    /// reading is stale until a read succeeds again.
+    pub(crate) fn poll_error(&self) -> Option<String> {
+        self.poll_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn set_poll_error(&self, error: Option<String>) {
+        *self.poll_error.lock().unwrap_or_else(|e| e.into_inner()) = error;
+    }
+
+    /// Pause or resume polling; returns `false` if it already was
+    fn set_paused(&self, paused: bool) -> bool {
+        if self.paused.swap(paused, Ordering::AcqRel) == paused {
+            return false;
+        }
+        self.send(Update::Acquisition {
+            paused,
+            at: Utc::now(),
+        });
+        true
+    }
+
+    /// Close the channel; subscribers see `Closed` once drained
+    pub(crate) fn close(&self) {
+        self.channel().take();
+    }
+
+    /// Number of live subscribers
+    pub(crate) fn count(&self) -> usize {
+        self.channel()
+            .as_ref()
+            .map_or(0, |channel| channel.sender.receiver_count())
+    }
+
+    fn status(&self) -> PollingStatus {
+        let channel = self.channel();
+        PollingStatus {
+            active: channel.is_some(),
+            interval_ms: channel
+                .as_ref()
+                .map(|channel| channel.interval.as_millis() as u64),
+            subscribers: channel
+                .as_ref()
+                .map_or(0, |channel| channel.sender.receiver_count()),
+            started: channel.as_ref().is_some_and(|channel| channel.held),
+            paused: self.is_paused(),
+        }
+    }
+}
+
+/// Whether a device is being polled for live subscribers
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct PollingStatus {
+    /// A poller is running. It stops at its next tick once `subscribers`
+    /// drops to zero.
+    pub active: bool,
+    /// Poll interval, while active
+    pub interval_ms: Option<u64>,
+    pub subscribers: usize,
+    /// Polling was started explicitly and runs without subscribers
+    pub started: bool,
+    /// Polling is paused; the poller, if any, skips its reads
+    pub paused: bool,
+}
+
+/// Get the polling state of a device
+pub async fn status(
+    device_id: &str,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<PollingStatus, String> {
+    Ok(managed_device(device_id, state).await?.subscribers.status())
+}
+
+/// Pause or resume the background polling of a device. The device stays
+/// connected and its buffer is kept; direct measurement requests still run.
+pub async fn set_paused(
+    device_id: &str,
+    paused: bool,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(device_id, state).await?;
+    let changed = managed_device.subscribers.set_paused(paused);
+    Ok(match (paused, changed) {
+        (true, true) => format!("Paused polling of device {}", device_id),
+        (true, false) => format!("Polling of device {} is already paused", device_id),
+        (false, true) => format!("Resumed polling of device {}", device_id),
+        (false, false) => format!("Polling of device {} is not paused", device_id),
+    })
+}
+
+/// Validate a poll interval in milliseconds
+pub fn poll_interval(interval_ms: u64) -> std::result::Result<Duration, String> {
+    if interval_ms < MIN_POLL_INTERVAL_MS {
+        return Err(format!(
+            "Poll interval must be at least {} ms",
+            MIN_POLL_INTERVAL_MS
+        ));
+    }
+    Ok(Duration::from_millis(interval_ms))
+}
+
+/// Start polling a device every `interval` without a subscriber, keeping
+/// its history buffer filling until `stop` or a disconnect. A device
+/// already polled for subscribers keeps that poller and its interval.
+pub async fn start(
+    device_id: &str,
+    interval: Duration,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(device_id, state).await?;
+    let (interval, start_poller) = managed_device.subscribers.hold(interval);
+    if !start_poller {
+        return Ok(format!(
+            "Device {} is already polled every {} ms",
+            device_id,
+            interval.as_millis()
+        ));
+    }
+    tokio::spawn(poll(device_id.to_string(), interval, state.clone()));
+    Ok(format!(
+        "Polling device {} every {} ms",
+        device_id,
+        interval.as_millis()
+    ))
+}
+
+/// Stop polling started with `start`. The poller keeps running while
+/// subscribers remain.
+pub async fn stop(
+    device_id: &str,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    let managed_device = managed_device(device_id, state).await?;
+    if managed_device.subscribers.release() {
+        Ok(format!("Stopped polling device {}", device_id))
+    } else {
+        Err(format!("Polling of device {} was not started", device_id))
+    }
+}
+
+/// Subscribe to a device's measurements. The first subscriber starts a
+/// poller querying the device every `interval`.
+///
+/// The receiver reports `Closed` once the device is disconnected; dropping
+/// it unsubscribes.
+pub async fn subscribe(
+    device_id: &str,
+    interval: Duration,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<broadcast::Receiver<Update>, String> {
+    let managed_device = managed_device(device_id, state).await?;
+    let (updates, start_poller) = managed_device.subscribers.subscribe(interval);
+    if start_poller {
+        tokio::spawn(poll(device_id.to_string(), interval, state.clone()));
+    }
+    Ok(updates)
+}
+
+/// Poll a device every `interval` while it has subscribers or polling was
+/// started. Every poller snaps to the configured grid, however it was
+/// started.
+async fn poll(device_id: String, interval: Duration, state: Arc<Mutex<AppState>>) {
+    tracing::debug!(device_id, "Starting measurement poller");
+    let snap_tolerance = state
+        .lock()
+        .await
+        .config
+        .stream
+        .as_ref()
+        .and_then(StreamConfig::snap_tolerance);
+    let mut ticker = tokio::time::interval(interval);
+    // On a grid, a late tick must not shift every later one. The tolerance
+    // was checked against the configured interval; a poller running at
+    // another one keeps it below half its own.
+    let mut grid = snap_tolerance
+        .and_then(|tolerance| TimestampGrid::new(interval, tolerance.min(interval / 2)));
+    ticker.set_missed_tick_behavior(match grid {
+        Some(_) => tokio::time::MissedTickBehavior::Skip,
+        None => tokio::time::MissedTickBehavior::Delay,
+    });
+
+    loop {
+        ticker.tick().await;
+
+        // Gone from the state means disconnected, which closes the channel.
+        let Ok(managed_device) = managed_device(&device_id, &state).await else {
+            break;
+        };
+        if managed_device.subscribers.release_if_idle() {
+            break;
+        }
+        let subscribers = managed_device.subscribers.clone();
+        drop(managed_device);
+        if subscribers.is_paused() {
+            continue;
+        }
+
+        match take_measurement(&device_id, None, true, grid.as_mut(), &state).await {
+            Ok(_) => subscribers.set_poll_error(None),
+            Err(error) => {
+                tracing::warn!(device_id, %error, "Polled measurement failed");
+                subscribers.set_poll_error(Some(error));
+            }
+        }
+    }
+
+    tracing::debug!(device_id, "Measurement poller stopped");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{MeasurementAttribute, MeasurementState, Unit};
+
+    fn reading_at(millis: i64) -> Measurement {
+        let mut measurement = Measurement::new(
+            1.0,
+            Unit::VoltDc,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        );
+        measurement.timestamp = DateTime::from_timestamp_millis(1_700_000_000_000 + millis);
+        measurement
+    }
+
+    #[test]
+    fn grid_snaps_jittered_readings_and_flags_drift() {
+        let mut grid =
+            TimestampGrid::new(Duration::from_millis(100), Duration::from_millis(20)).unwrap();
+
+        let first = grid.snap(reading_at(0));
+        let jittered = grid.snap(reading_at(112));
+        let drifted = grid.snap(reading_at(235));
+
+        assert_eq!(first.timestamp, reading_at(0).timestamp);
+        assert_eq!(jittered.timestamp, reading_at(100).timestamp);
+        assert_eq!(jittered.metadata.raw_timestamp, reading_at(112).timestamp);
+        assert!(!jittered.metadata.timestamp_drifted);
+        assert_eq!(drifted.timestamp, reading_at(200).timestamp);
+        assert!(drifted.metadata.timestamp_drifted);
+    }
+
+    #[tokio::test]
+    async fn poller_stops_once_every_subscriber_is_gone() {
+        let state = crate::communication::tests::shared_state();
+        let device = crate::communication::tests::connect_mock("/dev/ttyTEST0", &state)
+            .await
+            .unwrap();
+
+        let interval = Duration::from_millis(MIN_POLL_INTERVAL_MS);
+        let first = subscribe(&device.id, interval, &state).await.unwrap();
+        let second = subscribe(&device.id, interval, &state).await.unwrap();
+        let polling = status(&device.id, &state).await.unwrap();
+        assert!(polling.active);
+        assert_eq!(polling.subscribers, 2);
+
+        drop(first);
+        tokio::time::sleep(interval * 5).await;
+        assert!(status(&device.id, &state).await.unwrap().active);
+
+        drop(second);
+        // The poller notices at its next tick, after any read in progress.
+        tokio::time::timeout(Duration::from_secs(1), async {
+            while status(&device.id, &state).await.unwrap().active {
+                tokio::time::sleep(interval).await;
+            }
+        })
+        .await
+        .expect("poller still running");
+    }
+
+    #[tokio::test]
+    async fn slow_consumer_gets_the_newest_reading() {
+        let (sender, receiver) = broadcast::channel(UPDATE_CHANNEL_CAPACITY);
+        let mut updates = LatestUpdates::new(receiver);
+        let value = |update: Option<Update>| match update {
+            Some(Update::Measurement(measurement)) => measurement.value,
+            other => panic!("expected a measurement, got {:?}", other),
+        };
+
+        for millis in [0, 100, 200] {
+            let mut measurement = reading_at(millis);
+            measurement.value = millis as f64;
+            sender.send(Update::Measurement(measurement)).unwrap();
+        }
+        let at = Utc::now();
+        sender
+            .send(Update::Acquisition { paused: true, at })
+            .unwrap();
+        sender.send(Update::Measurement(reading_at(300))).unwrap();
+
+        assert_eq!(value(updates.recv().await), 200.0);
+        assert_eq!(updates.dropped(), 2);
+        assert!(matches!(
+            updates.recv().await,
+            Some(Update::Acquisition { paused: true, .. })
+        ));
+        assert_eq!(value(updates.recv().await), 1.0);
+        assert_eq!(updates.dropped(), 2);
+
+        drop(sender);
+        assert!(updates.recv().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn readings_the_channel_overwrote_count_as_dropped() {
+        let (sender, receiver) = broadcast::channel(2);
+        let mut updates = LatestUpdates::new(receiver);
+        for millis in 0..5 {
+            sender
+                .send(Update::Measurement(reading_at(millis)))
+                .unwrap();
+        }
+
+        assert!(updates.recv().await.is_some());
+        assert_eq!(updates.dropped(), 4);
+    }
+
+    async fn next_update(updates: &mut broadcast::Receiver<Update>) -> Update {
+        tokio::time::timeout(Duration::from_secs(1), updates.recv())
+            .await
+            .expect("no update")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn nothing_is_acquired_while_paused() {
+        use crate::communication::get_device_status;
+        use crate::communication::tests::{connect_mock_with, instant_mock, shared_state};
+
+        let state = shared_state();
+        let id = connect_mock_with("COM_PAUSE", instant_mock(), &state)
+            .await
+            .unwrap()
+            .id;
+        let interval = Duration::from_millis(MIN_POLL_INTERVAL_MS);
+        let mut updates = subscribe(&id, interval, &state).await.unwrap();
+        assert!(matches!(
+            next_update(&mut updates).await,
+            Update::Measurement(_)
+        ));
+
+        set_paused(&id, true, &state).await.unwrap();
+        // Let a read already in progress finish, then watch for more
+        tokio::time::sleep(interval * 5).await;
+        let count = get_device_status(id.clone(), &state)
+            .await
+            .unwrap()
+            .measurement_count;
+        tokio::time::sleep(interval * 10).await;
+        assert_eq!(
+            get_device_status(id.clone(), &state)
+                .await
+                .unwrap()
+                .measurement_count,
+            count
+        );
+        assert!(status(&id, &state).await.unwrap().paused);
+        let mut saw_pause = false;
+        while let Ok(update) = updates.try_recv() {
+            saw_pause |= matches!(update, Update::Acquisition { paused: true, .. });
+        }
+        assert!(saw_pause);
+
+        set_paused(&id, false, &state).await.unwrap();
+        assert!(matches!(
+            next_update(&mut updates).await,
+            Update::Acquisition { paused: false, .. }
+        ));
+        assert!(matches!(
+            next_update(&mut updates).await,
+            Update::Measurement(_)
+        ));
+        assert!(
+            get_device_status(id, &state)
+                .await
+                .unwrap()
+                .measurement_count
+                > count
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//! Shareable device profiles
+//!
+//! A profile is a versioned JSON document holding everything that makes up
+//! a bench setup: the settings of the meter itself and the host-side
+//! corrections, display units, smoothing and limits. Importing it into
+//! another device reproduces the setup; fields a newer or different build
+//! doesn't know are skipped with a warning rather than rejected.
+
+use super::{managed_device, settle_duration, AppState};
+use crate::config::{DisplayPreferences, Limits, SignInversion, Smoothing};
+use crate::conversion::validate_temperature_offset;
+use crate::device::{DeviceType, FrequencyFilter, SecondaryFunction};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Profile format written by this build; older versions import unchanged
+pub const PROFILE_VERSION: u32 = 1;
+
+/// Portable configuration of a device
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DeviceProfile {
+    pub version: u32,
+    /// Device the profile was exported from
+    pub device_type: DeviceType,
+    #[serde(default)]
+    pub frequency_filter: Option<FrequencyFilter>,
+    #[serde(default)]
+    pub secondary_function: Option<SecondaryFunction>,
+    #[serde(default)]
+    pub display_brightness: Option<u8>,
+    #[serde(default)]
+    pub display_contrast: Option<u8>,
+    #[serde(default)]
+    pub nplc: Option<f64>,
+    #[serde(default)]
+    pub high_input_impedance: Option<bool>,
+    #[serde(default)]
+    pub auto_save: Option<bool>,
+    #[serde(default)]
+    pub temperature_offset: Option<f64>,
+    #[serde(default)]
+    pub invert_sign: Option<SignInversion>,
+    #[serde(default)]
+    pub display_preferences: Option<DisplayPreferences>,
+    #[serde(default)]
+    pub settle_ms: Option<u64>,
+    /// Smoothing and limits are off when absent
+    #[serde(default)]
+    pub smoothing: Option<Smoothing>,
+    #[serde(default)]
+    pub limits: Option<Limits>,
+    /// Fields this build doesn't know
+    #[serde(flatten, skip_serializing)]
+    pub unknown: BTreeMap<String, serde_json::Value>,
+}
+
+/// Outcome of importing a profile
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ProfileImport {
+    /// Settings set on the device
+    pub applied: Vec<String>,
+    /// Settings skipped, and why
+    pub warnings: Vec<String>,
+}
+
+/// Export the full configuration of a device. Settings the meter can't
+/// report are left out.
+pub async fn export_profile(
+    device_id: String,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<DeviceProfile, String> {
+    let (managed_device, global_preferences) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.device(&device_id)?,
+            state_guard.config.display_preferences.clone(),
+        )
+    };
+    let mut device = managed_device.lock_device().await?;
+    let settings = match managed_device
+        .timed("settings", device.get_settings())
+        .await
+    {
+        Ok(settings) => settings,
+        Err(error) => {
+            tracing::warn!(device_id, %error, "Exporting profile without device settings");
+            Default::default()
+        }
+    };
+    drop(device);
+
+    let session = managed_device.session.lock().await;
+    Ok(DeviceProfile {
+        version: PROFILE_VERSION,
+        device_type: managed_device.device_type,
+        frequency_filter: settings.frequency_filter,
+        secondary_function: settings.secondary_function,
+        display_brightness: settings.display_brightness,
+        display_contrast: settings.display_contrast,
+        nplc: settings.nplc,
+        high_input_impedance: settings.high_input_impedance,
+        auto_save: settings.auto_save,
+        temperature_offset: Some(session.temperature_offset),
+        invert_sign: Some(session.invert_sign.clone()),
+        display_preferences: Some(session.display_preferences.clone().or(global_preferences)),
+        settle_ms: Some(session.settle.as_millis() as u64),
+        smoothing: session.smoothing(),
+        limits: session.limits(),
+        unknown: BTreeMap::new(),
+    })
+}
+
+/// Check a profile can be imported without changing anything
+fn validate(profile: &DeviceProfile) -> std::result::Result<(), String> {
+    if profile.version > PROFILE_VERSION {
+        return Err(format!(
+            "Profile version {} is newer than the supported version {}",
+            profile.version, PROFILE_VERSION
+        ));
+    }
+    if let Some(offset) = profile.temperature_offset {
+        validate_temperature_offset(offset).map_err(|e| e.to_string())?;
+    }
+    if let Some(preferences) = &profile.display_preferences {
+        preferences.validate().map_err(|e| e.to_string())?;
+    }
+    if let Some(settle_ms) = profile.settle_ms {
+        settle_duration(settle_ms)?;
+    }
+    if let Some(smoothing) = &profile.smoothing {
+        smoothing.validate().map_err(|e| e.to_string())?;
+    }
+    if let Some(limits) = &profile.limits {
+        limits.validate().map_err(|e| e.to_string())?;
+    }
+    Ok(())
+}
+
+/// Apply a profile to a device once all of it validates.
+///
+/// The meter's own settings go first, the secondary function before the
+/// input filter, as later settings can depend on them; a setting the meter
+/// refuses is skipped with a warning. Host-side settings follow in one step,
+/// limits last since they are judged in display units.
+pub async fn import_profile(
+    device_id: String,
+    profile: DeviceProfile,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<ProfileImport, String> {
+    validate(&profile)?;
+    let managed_device = managed_device(&device_id, state).await?;
+    let mut outcome = ProfileImport::default();
+    for field in profile.unknown.keys() {
+        outcome
+            .warnings
+            .push(format!("Skipped unknown field '{}'", field));
+    }
+    if profile.device_type != managed_device.device_type {
+        outcome.warnings.push(format!(
+            "Profile was exported from a {:?}, applying to a {:?}",
+            profile.device_type, managed_device.device_type
+        ));
+    }
+
+    let mut device = managed_device.lock_device().await?;
+    if let Some(function) = profile.secondary_function {
+        match managed_device
+            .timed(
+                "set_secondary_function",
+                device.set_secondary_function(function),
+            )
+            .await
+        {
+            Ok(()) => outcome.applied.push("secondary_function".to_string()),
+            Err(e) => outcome
+                .warnings
+                .push(format!("Skipped secondary_function: {}", e)),
+        }
+    }
+    if let Some(filter) = profile.frequency_filter {
+        match managed_device
+            .timed("set_frequency_filter", device.set_frequency_filter(filter))
+            .await
+        {
+            Ok(()) => outcome.applied.push("frequency_filter".to_string()),
+            Err(e) => outcome
+                .warnings
+                .push(format!("Skipped frequency_filter: {}", e)),
+        }
+    }
+    if let Some(cycles) = profile.nplc {
+        match managed_device
+            .timed("set_nplc", device.set_nplc(cycles))
+            .await
+        {
+            Ok(()) => outcome.applied.push("nplc".to_string()),
+            Err(e) => outcome.warnings.push(format!("Skipped nplc: {}", e)),
+        }
+    }
+    if let Some(enabled) = profile.high_input_impedance {
+        match managed_device
+            .timed(
+                "set_high_input_impedance",
+                device.set_high_input_impedance(enabled),
+            )
+            .await
+        {
+            Ok(()) => outcome.applied.push("high_input_impedance".to_string()),
+            Err(e) => outcome
+                .warnings
+                .push(format!("Skipped high_input_impedance: {}", e)),
+        }
+    }
+    if let Some(enabled) = profile.auto_save {
+        match managed_device
+            .timed("set_auto_save", device.set_auto_save(enabled))
+            .await
+        {
+            Ok(()) => outcome.applied.push("auto_save".to_string()),
+            Err(e) => outcome.warnings.push(format!("Skipped auto_save: {}", e)),
+        }
+    }
+    if let Some(level) = profile.display_brightness {
+        match managed_device
+            .timed(
+                "set_display_brightness",
+                device.set_display_brightness(level),
+            )
+            .await
+        {
+            Ok(()) => outcome.applied.push("display_brightness".to_string()),
+            Err(e) => outcome
+                .warnings
+                .push(format!("Skipped display_brightness: {}", e)),
+        }
+    }
+    if let Some(level) = profile.display_contrast {
+        match managed_device
+            .timed("set_display_contrast", device.set_display_contrast(level))
+            .await
+        {
+            Ok(()) => outcome.applied.push("display_contrast".to_string()),
+            Err(e) => outcome
+                .warnings
+                .push(format!("Skipped display_contrast: {}", e)),
+        }
+    }
+    drop(device);
+
+    let mut session = managed_device.session.lock().await;
+    if let Some(offset) = profile.temperature_offset {
+        session.temperature_offset = offset;
+        outcome.applied.push("temperature_offset".to_string());
+    }
+    if let Some(invert_sign) = profile.invert_sign {
+        session.invert_sign = invert_sign;
+        outcome.applied.push("invert_sign".to_string());
+    }
+    if let Some(preferences) = profile.display_preferences {
+        session.display_preferences = preferences;
+        outcome.applied.push("display_preferences".to_string());
+    }
+    if let Some(settle_ms) = profile.settle_ms {
+        session.settle = settle_duration(settle_ms)?;
+        outcome.applied.push("settle_ms".to_string());
+    }
+    session.set_smoothing(profile.smoothing);
+    outcome.applied.push("smoothing".to_string());
+    session.set_limits(profile.limits);
+    outcome.applied.push("limits".to_string());
+    Ok(outcome)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::tests::{connect_mock, shared_state};
+    use crate::device::Unit;
+
+    #[tokio::test]
+    async fn an_exported_profile_imports_unchanged() {
+        let state = shared_state();
+        let source = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let target = connect_mock("/dev/ttyTEST1", &state).await.unwrap().id;
+
+        let mut setup = export_profile(source.clone(), &state).await.unwrap();
+        setup.display_brightness = Some(1);
+        setup.display_contrast = Some(3);
+        setup.nplc = Some(10.0);
+        setup.temperature_offset = Some(-1.5);
+        setup.invert_sign = Some(SignInversion::All(true));
+        setup.settle_ms = Some(250);
+        setup.smoothing = Some(Smoothing::Sma { window: 8 });
+        setup.limits = Some(Limits {
+            nominal: 5.0,
+            tolerance: Some(0.1),
+            lower_tolerance: None,
+            upper_tolerance: None,
+            unit: Unit::VoltDc,
+        });
+        import_profile(source.clone(), setup, &state).await.unwrap();
+
+        let exported = export_profile(source, &state).await.unwrap();
+        let document = serde_json::to_string(&exported).unwrap();
+        let profile: DeviceProfile = serde_json::from_str(&document).unwrap();
+        let outcome = import_profile(target.clone(), profile, &state)
+            .await
+            .unwrap();
+        for field in ["nplc", "display_contrast", "temperature_offset", "limits"] {
+            assert!(outcome.applied.iter().any(|f| f == field), "{}", field);
+        }
+
+        let imported = export_profile(target, &state).await.unwrap();
+        assert_eq!(
+            serde_json::to_value(&imported).unwrap(),
+            serde_json::to_value(&exported).unwrap()
+        );
+        assert_eq!(imported.nplc, Some(10.0));
+        assert_eq!(imported.temperature_offset, Some(-1.5));
+        assert_eq!(imported.settle_ms, Some(250));
+        assert_eq!(imported.smoothing, Some(Smoothing::Sma { window: 8 }));
+        assert_eq!(imported.limits.map(|limits| limits.nominal), Some(5.0));
+    }
+}
@@ -0,0 +1,107 @@
+//! Replay of recorded measurements at their original pace
+//!
+//! A replay emits stored readings as if they were arriving live, spaced by
+//! the intervals between their timestamps divided by a speed factor, so
+//! clients can chart a past session with their live code path.
+
+use crate::device::Measurement;
+use futures_util::stream::{self, Stream};
+use std::time::Duration;
+
+/// Fastest accepted replay speed, relative to the recording
+pub const MAX_REPLAY_SPEED: f64 = 1000.0;
+
+/// Check a replay speed; `0` replays without pausing
+pub fn validate_speed(speed: f64) -> std::result::Result<f64, String> {
+    if !(0.0..=MAX_REPLAY_SPEED).contains(&speed) {
+        return Err(format!(
+            "Replay speed must be between 0 and {}, got {}",
+            MAX_REPLAY_SPEED, speed
+        ));
+    }
+    Ok(speed)
+}
+
+/// Emit `measurements` in order, pausing before each for its distance from
+/// the previous stamped one divided by `speed`. Unstamped readings and
+/// timestamps going backwards don't pause.
+pub fn paced(measurements: Vec<Measurement>, speed: f64) -> impl Stream<Item = Measurement> {
+    stream::unfold(
+        (measurements.into_iter(), None),
+        move |(mut measurements, previous)| async move {
+            let measurement = measurements.next()?;
+            let timestamp = measurement.timestamp.or(previous);
+            if speed > 0.0 {
+                let gap = previous
+                    .zip(measurement.timestamp)
+                    .and_then(|(previous, timestamp)| (timestamp - previous).to_std().ok());
+                if let Some(gap) = gap {
+                    tokio::time::sleep(Duration::from_secs_f64(gap.as_secs_f64() / speed)).await;
+                }
+            }
+            Some((measurement, (measurements, timestamp)))
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{MeasurementAttribute, MeasurementState, Unit};
+    use futures_util::StreamExt;
+    use tokio::time::Instant;
+
+    fn reading(value: f64, offset_ms: i64) -> Measurement {
+        let mut measurement = Measurement::new(
+            value,
+            Unit::VoltDc,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        );
+        measurement.timestamp =
+            Some(chrono::DateTime::UNIX_EPOCH + chrono::Duration::milliseconds(offset_ms));
+        measurement
+    }
+
+    /// Values and the time after `started` each was emitted
+    async fn replay(measurements: Vec<Measurement>, speed: f64) -> Vec<(f64, Duration)> {
+        let started = Instant::now();
+        let mut replay = Box::pin(paced(measurements, speed));
+        let mut emitted = Vec::new();
+        while let Some(measurement) = replay.next().await {
+            emitted.push((measurement.value, started.elapsed()));
+        }
+        emitted
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn readings_are_spaced_by_their_gaps_over_the_speed() {
+        let recording = vec![reading(1.0, 0), reading(2.0, 1000), reading(3.0, 3000)];
+
+        let emitted = replay(recording, 2.0).await;
+
+        assert_eq!(
+            emitted,
+            [
+                (1.0, Duration::ZERO),
+                (2.0, Duration::from_millis(500)),
+                (3.0, Duration::from_millis(1500)),
+            ]
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn speed_zero_and_backward_timestamps_do_not_pause() {
+        let recording = vec![reading(1.0, 0), reading(2.0, 1000)];
+        let emitted = replay(recording, 0.0).await;
+        assert!(emitted.iter().all(|(_, at)| at.is_zero()), "{:?}", emitted);
+
+        let recording = vec![reading(1.0, 1000), reading(2.0, 0), reading(3.0, 500)];
+        let emitted = replay(recording, 1.0).await;
+        let at: Vec<_> = emitted.iter().map(|(_, at)| *at).collect();
+        assert_eq!(
+            at,
+            [Duration::ZERO, Duration::ZERO, Duration::from_millis(500)]
+        );
+    }
+}
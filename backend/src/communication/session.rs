@@ -0,0 +1,326 @@
+//! Host-side state kept for each registered device
+
+use super::smoothing::Smoother;
+use crate::config::{DisplayPreferences, Limits, SignInversion, Smoothing};
+use crate::device::{DbReference, Measurement, Unit, Verdict};
+use serde::Serialize;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Readings and settings of one device that live on the host
+pub struct DeviceSession {
+    /// Sequence number handed to the next successful measurement
+    next_sequence: u64,
+    /// Most recent recorded measurement, always the highest sequence so far
+    last_measurement: Option<Measurement>,
+    /// Per-device display units, layered over the global preferences
+    pub display_preferences: DisplayPreferences,
+    /// Settling time after a function change, zero to disable
+    pub settle: Duration,
+    /// Readings are flagged as warming up for this long after
+    /// `warm_up_from`, zero to disable
+    pub warm_up: Duration,
+    /// Connect or the last reset
+    warm_up_from: Instant,
+    /// Unit of the previous reading as the device reported it
+    last_unit: Option<Unit>,
+    /// User correction in °C for temperature readings. Neither supported
+    /// meter accepts an offset remotely, so it is always applied here.
+    pub temperature_offset: f64,
+    /// Readings whose sign is flipped before display conversion
+    pub invert_sign: SignInversion,
+    /// 0 dB point that readings in its unit are made relative to. Neither
+    /// supported meter offers relative dB remotely, so it is applied here.
+    pub db_reference: Option<DbReference>,
+    /// Moving average over the recorded readings, if enabled
+    smoother: Option<Smoother>,
+    /// Pass/fail window the recorded readings are checked against
+    limits: Option<Limits>,
+    verdicts: VerdictCounts,
+}
+
+/// Recorded readings that passed or failed the limits
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct VerdictCounts {
+    pub pass: u64,
+    pub fail: u64,
+}
+
+impl DeviceSession {
+    pub fn new(
+        display_preferences: DisplayPreferences,
+        settle: Duration,
+        smoothing: Option<Smoothing>,
+    ) -> Self {
+        Self {
+            next_sequence: 1,
+            last_measurement: None,
+            display_preferences,
+            settle,
+            warm_up: Duration::ZERO,
+            warm_up_from: Instant::now(),
+            last_unit: None,
+            temperature_offset: 0.0,
+            invert_sign: SignInversion::default(),
+            db_reference: None,
+            smoother: smoothing.map(Smoother::new),
+            limits: None,
+            verdicts: VerdictCounts::default(),
+        }
+    }
+
+    pub fn smoothing(&self) -> Option<Smoothing> {
+        self.smoother.as_ref().map(Smoother::smoothing)
+    }
+
+    pub fn limits(&self) -> Option<Limits> {
+        self.limits.clone()
+    }
+
+    /// Set or, with `None`, clear the limits. The counts start over.
+    pub fn set_limits(&mut self, limits: Option<Limits>) {
+        self.limits = limits;
+        self.verdicts = VerdictCounts::default();
+    }
+
+    pub fn verdicts(&self) -> VerdictCounts {
+        self.verdicts
+    }
+
+    pub fn reset_verdicts(&mut self) {
+        self.verdicts = VerdictCounts::default();
+    }
+
+    /// Enable, replace or, with `None`, disable smoothing. The filter
+    /// starts afresh either way.
+    pub fn set_smoothing(&mut self, smoothing: Option<Smoothing>) {
+        self.smoother = smoothing.map(Smoother::new);
+    }
+
+    /// Start the warm-up period over, e.g. after the device was reset
+    pub fn restart_warm_up(&mut self) {
+        self.warm_up_from = Instant::now();
+    }
+
+    pub fn is_warming_up(&self) -> bool {
+        self.warm_up_from.elapsed() < self.warm_up
+    }
+
+    /// Note the unit of a fresh reading. Returns the settling time to wait
+    /// when it differs from the previous one, i.e. the function changed.
+    pub fn settle_after(&mut self, unit: &Unit) -> Option<Duration> {
+        let changed = self.last_unit.as_ref() != Some(unit);
+        self.last_unit = Some(unit.clone());
+        (changed && !self.settle.is_zero()).then_some(self.settle)
+    }
+
+    /// Reserve the next sequence number
+    pub fn next_sequence(&mut self) -> u64 {
+        let sequence = self.next_sequence;
+        self.next_sequence += 1;
+        sequence
+    }
+
+    /// Apply the user temperature offset, sign inversion and dB reference
+    /// to a reading in the device's units, and flag it during the warm-up
+    /// period
+    pub fn correct(&self, measurement: Measurement) -> Measurement {
+        let mut measurement = measurement.with_temperature_offset(self.temperature_offset);
+        if self.invert_sign.applies_to(&measurement.unit) {
+            measurement = measurement.with_sign_inverted();
+        }
+        if let Some(reference) = self.db_reference.clone() {
+            measurement = measurement.relative_to(reference);
+        }
+        measurement.metadata.warming_up = self.is_warming_up();
+        measurement
+    }
+
+    /// Correct a fresh reading, convert it into display units, smooth it,
+    /// judge it against the limits, stamp it and publish it
+    /// as the latest sample. The sequence number and the cached value change
+    /// together so readers never observe one without the other.
+    pub fn record(
+        &mut self,
+        measurement: Measurement,
+        global_preferences: DisplayPreferences,
+    ) -> Measurement {
+        let measurement = self.correct(measurement);
+        let mut measurement = self
+            .display_preferences
+            .clone()
+            .or(global_preferences)
+            .apply(measurement);
+        if let Some(smoother) = &mut self.smoother {
+            measurement = smoother.apply(measurement);
+        }
+        if let Some(limits) = &self.limits {
+            let verdict = limits.verdict(&measurement);
+            match verdict {
+                Verdict::Pass => self.verdicts.pass += 1,
+                Verdict::Fail => self.verdicts.fail += 1,
+            }
+            measurement.metadata.verdict = Some(verdict);
+        }
+        measurement.sequence = self.next_sequence();
+        self.last_measurement = Some(measurement.clone());
+        measurement
+    }
+
+    pub fn last_measurement(&self) -> Option<&Measurement> {
+        self.last_measurement.as_ref()
+    }
+}
+
+/// Successful-read counters, readable without waiting for the session lock
+#[derive(Debug, Default)]
+pub struct MeasurementStats {
+    /// Successful measurement requests
+    measurements: AtomicU64,
+    /// Individual readings returned, counting each reading of a display download
+    samples: AtomicU64,
+    /// Failed measurement requests
+    errors: AtomicU64,
+    /// Failed measurement requests since the last successful one
+    consecutive_errors: AtomicU32,
+}
+
+impl MeasurementStats {
+    /// Count one successful request that returned `samples` readings
+    pub fn record(&self, samples: usize) {
+        self.measurements.fetch_add(1, Ordering::Relaxed);
+        self.samples.fetch_add(samples as u64, Ordering::Relaxed);
+        self.consecutive_errors.store(0, Ordering::Relaxed);
+    }
+
+    /// Count one failed request
+    pub fn record_error(&self) {
+        self.errors.fetch_add(1, Ordering::Relaxed);
+        self.consecutive_errors.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn consecutive_errors(&self) -> u32 {
+        self.consecutive_errors.load(Ordering::Relaxed)
+    }
+
+    pub fn reset(&self) {
+        self.measurements.store(0, Ordering::Relaxed);
+        self.samples.store(0, Ordering::Relaxed);
+        self.errors.store(0, Ordering::Relaxed);
+    }
+
+    pub fn measurement_count(&self) -> u64 {
+        self.measurements.load(Ordering::Relaxed)
+    }
+
+    pub fn total_samples(&self) -> u64 {
+        self.samples.load(Ordering::Relaxed)
+    }
+
+    pub fn error_count(&self) -> u64 {
+        self.errors.load(Ordering::Relaxed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{MeasurementAttribute, MeasurementState, UnitCategory};
+
+    fn session() -> DeviceSession {
+        DeviceSession::new(DisplayPreferences::default(), Duration::ZERO, None)
+    }
+
+    fn reading(value: f64, unit: Unit) -> Measurement {
+        Measurement::new(
+            value,
+            unit,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        )
+    }
+
+    #[test]
+    fn sequences_increase_when_timestamps_collide() {
+        let mut session = session();
+        let first = reading(1.0, Unit::VoltDc);
+        let mut second = reading(2.0, Unit::VoltDc);
+        second.timestamp = first.timestamp;
+
+        let first = session.record(first, DisplayPreferences::default());
+        let second = session.record(second, DisplayPreferences::default());
+
+        assert_eq!(first.timestamp, second.timestamp);
+        assert!(second.sequence > first.sequence);
+        assert_eq!(first.sequence, 1);
+        assert_eq!(session.last_measurement().unwrap().sequence, 2);
+    }
+
+    #[test]
+    fn global_temperature_unit_applies_unless_the_device_overrides_it() {
+        let fahrenheit = DisplayPreferences {
+            temperature_unit: Some(Unit::Fahrenheit),
+            ..DisplayPreferences::default()
+        };
+
+        let mut plain = session();
+        let converted = plain.record(reading(100.0, Unit::Celsius), fahrenheit.clone());
+        assert_eq!(converted.unit, Unit::Fahrenheit);
+        assert!((converted.value - 212.0).abs() < 1e-9);
+
+        let mut kelvin = session();
+        kelvin.display_preferences.temperature_unit = Some(Unit::Kelvin);
+        let converted = kelvin.record(reading(100.0, Unit::Celsius), fahrenheit.clone());
+        assert_eq!(converted.unit, Unit::Kelvin);
+        assert!((converted.value - 373.15).abs() < 1e-9);
+
+        let volts = plain.record(reading(1.0, Unit::VoltDc), fahrenheit);
+        assert_eq!(volts.unit, Unit::VoltDc);
+    }
+
+    #[test]
+    fn inverted_current_readings_are_negated_but_overloads_are_not() {
+        let mut session = session();
+        session.invert_sign = SignInversion::Categories(vec![UnitCategory::Current]);
+
+        let current = session.correct(reading(1.25, Unit::AmpDc));
+        assert_eq!(current.value, -1.25);
+        assert!(current.metadata.sign_inverted);
+
+        let mut overload = reading(9.99e37, Unit::AmpDc);
+        overload.state = MeasurementState::Overload;
+        let overload = session.correct(overload);
+        assert_eq!(overload.value, 9.99e37);
+        assert!(!overload.metadata.sign_inverted);
+
+        let voltage = session.correct(reading(1.25, Unit::VoltDc));
+        assert_eq!(voltage.value, 1.25);
+        assert!(!voltage.metadata.sign_inverted);
+
+        let zero = session.correct(reading(0.0, Unit::AmpAc));
+        assert!(zero.value.is_sign_positive());
+    }
+
+    #[test]
+    fn readings_are_flagged_only_while_warming_up() {
+        let mut session = session();
+        let flagged = |session: &mut DeviceSession| {
+            session
+                .record(reading(1.0, Unit::VoltDc), DisplayPreferences::default())
+                .metadata
+                .warming_up
+        };
+        assert!(!flagged(&mut session));
+
+        session.warm_up = Duration::from_millis(50);
+        session.restart_warm_up();
+        assert!(flagged(&mut session));
+
+        std::thread::sleep(Duration::from_millis(60));
+        assert!(!flagged(&mut session));
+
+        // A reset starts the period over
+        session.restart_warm_up();
+        assert!(flagged(&mut session));
+    }
+}
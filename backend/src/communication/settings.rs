@@ -0,0 +1,199 @@
+//! Partial settings updates
+//!
+//! A patch names only the settings to change, e.g. `{"nplc": 10}`. Each
+//! field is handed to the setter of its own endpoint and succeeds or fails
+//! on its own, so one refused setting doesn't undo the others.
+
+use super::{
+    managed_device, set_auto_save, set_display_brightness, set_display_contrast,
+    set_frequency_filter, set_high_input_impedance, set_invert_sign, set_nplc,
+    set_secondary_function, set_temperature_offset, AppState,
+};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Settings a patch may change, in the order they are applied: the
+/// secondary function first since the filter depends on the function
+pub const SETTING_KEYS: &[&str] = &[
+    "secondary_function",
+    "frequency_filter",
+    "nplc",
+    "high_input_impedance",
+    "auto_save",
+    "display_brightness",
+    "display_contrast",
+    "temperature_offset",
+    "invert_sign",
+];
+
+/// Result of applying one field of a patch
+#[derive(Debug, Clone, Serialize)]
+pub struct FieldOutcome {
+    pub success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl From<std::result::Result<String, String>> for FieldOutcome {
+    fn from(result: std::result::Result<String, String>) -> Self {
+        match result {
+            Ok(message) => Self {
+                success: true,
+                message: Some(message),
+                error: None,
+            },
+            Err(error) => Self {
+                success: false,
+                message: None,
+                error: Some(error),
+            },
+        }
+    }
+}
+
+/// Apply the fields of `patch` to a device. A patch with a field outside
+/// `SETTING_KEYS` is refused before anything changes.
+pub async fn patch_settings(
+    device_id: String,
+    mut patch: serde_json::Map<String, serde_json::Value>,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<BTreeMap<String, FieldOutcome>, String> {
+    managed_device(&device_id, state).await?;
+    let mut unknown: Vec<_> = patch
+        .keys()
+        .filter(|key| !SETTING_KEYS.contains(&key.as_str()))
+        .cloned()
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort();
+        return Err(format!(
+            "Unknown settings {}, expected any of {}",
+            unknown.join(", "),
+            SETTING_KEYS.join(", ")
+        ));
+    }
+
+    let mut outcomes = BTreeMap::new();
+    for key in SETTING_KEYS {
+        if let Some(value) = patch.remove(*key) {
+            let result = apply(device_id.clone(), key, value, state).await;
+            outcomes.insert(key.to_string(), result.into());
+        }
+    }
+    Ok(outcomes)
+}
+
+/// Route one field to its setter
+async fn apply(
+    device_id: String,
+    key: &str,
+    value: serde_json::Value,
+    state: &Arc<Mutex<AppState>>,
+) -> std::result::Result<String, String> {
+    match key {
+        "secondary_function" => set_secondary_function(device_id, parse(key, value)?, state).await,
+        "frequency_filter" => set_frequency_filter(device_id, parse(key, value)?, state).await,
+        "nplc" => set_nplc(device_id, parse(key, value)?, state).await,
+        "high_input_impedance" => {
+            set_high_input_impedance(device_id, parse(key, value)?, state).await
+        }
+        "auto_save" => set_auto_save(device_id, parse(key, value)?, state).await,
+        "display_brightness" => set_display_brightness(device_id, parse(key, value)?, state).await,
+        "display_contrast" => set_display_contrast(device_id, parse(key, value)?, state).await,
+        "temperature_offset" => set_temperature_offset(device_id, parse(key, value)?, state).await,
+        "invert_sign" => set_invert_sign(device_id, parse(key, value)?, state).await,
+        _ => Err(format!("Unknown setting {}", key)),
+    }
+}
+
+fn parse<T: DeserializeOwned>(
+    key: &str,
+    value: serde_json::Value,
+) -> std::result::Result<T, String> {
+    serde_json::from_value(value).map_err(|e| format!("Invalid {}: {}", key, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::get_device_settings;
+    use crate::communication::tests::{connect_mock, shared_state};
+    use crate::device::DeviceSettings;
+    use serde_json::json;
+
+    async fn settings(device_id: &str, state: &Arc<Mutex<AppState>>) -> DeviceSettings {
+        get_device_settings(device_id.to_string(), state)
+            .await
+            .unwrap()
+    }
+
+    fn json_of(settings: &DeviceSettings) -> serde_json::Value {
+        serde_json::to_value(settings).unwrap()
+    }
+
+    fn patch(value: serde_json::Value) -> serde_json::Map<String, serde_json::Value> {
+        value.as_object().unwrap().clone()
+    }
+
+    #[tokio::test]
+    async fn one_field_changes_and_the_rest_stay() {
+        let state = shared_state();
+        let id = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let before = settings(&id, &state).await;
+
+        let outcomes = patch_settings(id.clone(), patch(json!({"nplc": 10.0})), &state)
+            .await
+            .unwrap();
+
+        assert_eq!(outcomes.keys().collect::<Vec<_>>(), ["nplc"]);
+        assert!(outcomes["nplc"].success);
+        let mut after = settings(&id, &state).await;
+        assert_eq!(after.nplc, Some(10.0));
+        after.nplc = before.nplc;
+        assert_eq!(json_of(&after), json_of(&before));
+    }
+
+    #[tokio::test]
+    async fn refused_field_does_not_undo_the_others() {
+        let state = shared_state();
+        let id = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let before = settings(&id, &state).await;
+
+        let outcomes = patch_settings(
+            id.clone(),
+            patch(json!({"display_contrast": 3, "display_brightness": 99})),
+            &state,
+        )
+        .await
+        .unwrap();
+
+        assert!(outcomes["display_contrast"].success);
+        assert!(!outcomes["display_brightness"].success);
+        let after = settings(&id, &state).await;
+        assert_eq!(after.display_contrast, Some(3));
+        assert_eq!(after.display_brightness, before.display_brightness);
+    }
+
+    #[tokio::test]
+    async fn unknown_field_refuses_the_whole_patch() {
+        let state = shared_state();
+        let id = connect_mock("/dev/ttyTEST0", &state).await.unwrap().id;
+        let before = settings(&id, &state).await;
+
+        let error = patch_settings(
+            id.clone(),
+            patch(json!({"display_contrast": 3, "volume": 11})),
+            &state,
+        )
+        .await
+        .unwrap_err();
+
+        assert!(error.contains("volume"), "{}", error);
+        assert_eq!(json_of(&settings(&id, &state).await), json_of(&before));
+    }
+}
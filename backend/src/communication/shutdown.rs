@@ -0,0 +1,111 @@
+//! Orderly shutdown of the backend
+//!
+//! Every device is disconnected with its port closed at once, lingering
+//! ports are closed, and what happened is collected into a report that is
+//! printed on exit and optionally written to a file.
+
+use super::summary::SessionSummary;
+use super::{close_parked, disconnect, AppState};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+
+/// Outcome of disconnecting one device during shutdown
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DeviceShutdown {
+    pub device_id: String,
+    pub disconnected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub summary: Option<SessionSummary>,
+}
+
+/// What the shutdown sequence did
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct ShutdownReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    /// Devices that were connected, in id order
+    pub devices: Vec<DeviceShutdown>,
+    /// Ports of recently disconnected devices that were still open
+    pub parked_ports_closed: usize,
+    /// Background connects still running, which are abandoned
+    pub connects_abandoned: Vec<String>,
+}
+
+/// Disconnect every device and close every port
+pub async fn shutdown(state: &Arc<Mutex<AppState>>) -> ShutdownReport {
+    let started_at = Utc::now();
+    let (mut device_ids, mut connects_abandoned) = {
+        let state_guard = state.lock().await;
+        (
+            state_guard.devices.keys().cloned().collect::<Vec<_>>(),
+            state_guard.connecting.keys().cloned().collect::<Vec<_>>(),
+        )
+    };
+    device_ids.sort();
+    connects_abandoned.sort();
+
+    let mut devices = Vec::with_capacity(device_ids.len());
+    for device_id in device_ids {
+        let result = disconnect(device_id.clone(), true, false, state).await;
+        if let Err(error) = &result {
+            tracing::warn!(device_id, %error, "Disconnect during shutdown failed");
+        }
+        let (summary, error) = match result {
+            Ok(response) => (Some(response.summary), None),
+            Err(error) => (None, Some(error)),
+        };
+        devices.push(DeviceShutdown {
+            device_id,
+            disconnected: error.is_none(),
+            error,
+            summary,
+        });
+    }
+
+    let parked: Vec<_> = state.lock().await.parked.drain().collect();
+    let parked_ports_closed = parked.len();
+    for (_, parked) in parked {
+        close_parked(parked.device).await;
+    }
+
+    ShutdownReport {
+        started_at,
+        finished_at: Utc::now(),
+        devices,
+        parked_ports_closed,
+        connects_abandoned,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::communication::tests::{connect_mock, shared_state};
+
+    #[tokio::test]
+    async fn report_lists_every_disconnected_mock() {
+        let state = shared_state();
+        let mut ids = Vec::new();
+        for port in ["/dev/ttyTEST0", "/dev/ttyTEST1", "/dev/ttyTEST2"] {
+            ids.push(connect_mock(port, &state).await.unwrap().id);
+        }
+
+        let report = shutdown(&state).await;
+
+        let reported: Vec<_> = report.devices.iter().map(|d| d.device_id.clone()).collect();
+        assert_eq!(reported, ids);
+        for device in &report.devices {
+            assert!(device.disconnected, "{:?}", device.error);
+            assert!(device.summary.is_some());
+        }
+        assert!(report.connects_abandoned.is_empty());
+        assert!(report.finished_at >= report.started_at);
+        assert!(state.lock().await.devices.is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+//! Moving-average smoothing of live readings
+//!
+//! Only consecutive `Normal` readings in the same unit are averaged; any
+//! other reading passes through and restarts the filter.
+
+use crate::config::Smoothing;
+use crate::device::{Measurement, MeasurementState, Unit};
+use std::collections::VecDeque;
+
+/// Filter state for one device
+pub struct Smoother {
+    smoothing: Smoothing,
+    /// Unit of the readings currently averaged
+    unit: Option<Unit>,
+    ema: Option<f64>,
+    window: VecDeque<f64>,
+}
+
+impl Smoother {
+    pub fn new(smoothing: Smoothing) -> Self {
+        Self {
+            smoothing,
+            unit: None,
+            ema: None,
+            window: VecDeque::new(),
+        }
+    }
+
+    pub fn smoothing(&self) -> Smoothing {
+        self.smoothing
+    }
+
+    fn restart(&mut self, unit: Option<Unit>) {
+        self.unit = unit;
+        self.ema = None;
+        self.window.clear();
+    }
+
+    /// Replace the value of a reading by the smoothed value, keeping the
+    /// reading itself in the metadata
+    pub fn apply(&mut self, mut measurement: Measurement) -> Measurement {
+        if measurement.state != MeasurementState::Normal {
+            self.restart(None);
+            return measurement;
+        }
+        if self.unit.as_ref() != Some(&measurement.unit) {
+            self.restart(Some(measurement.unit.clone()));
+        }
+
+        let raw = measurement.value;
+        let smoothed = match self.smoothing {
+            Smoothing::Ema { alpha } => {
+                let ema = self.ema.map_or(raw, |ema| ema + alpha * (raw - ema));
+                self.ema = Some(ema);
+                ema
+            }
+            Smoothing::Sma { window } => {
+                self.window.push_back(raw);
+                while self.window.len() > window {
+                    self.window.pop_front();
+                }
+                self.window.iter().sum::<f64>() / self.window.len() as f64
+            }
+        };
+        measurement.value = smoothed;
+        measurement.metadata.raw_value = Some(raw);
+        measurement
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::MeasurementAttribute;
+
+    fn reading(value: f64) -> Measurement {
+        Measurement::new(
+            value,
+            Unit::VoltDc,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        )
+    }
+
+    #[test]
+    fn ema_converges_on_a_step_at_its_rate() {
+        let alpha = 0.25;
+        let mut smoother = Smoother::new(Smoothing::Ema { alpha });
+        assert_eq!(smoother.apply(reading(0.0)).value, 0.0);
+
+        for step in 1..=20 {
+            let smoothed = smoother.apply(reading(1.0));
+            let expected = 1.0 - (1.0 - alpha).powi(step);
+            assert!((smoothed.value - expected).abs() < 1e-12, "step {}", step);
+            assert_eq!(smoothed.metadata.raw_value, Some(1.0));
+        }
+    }
+
+    #[test]
+    fn sma_reaches_a_step_after_its_window() {
+        let mut smoother = Smoother::new(Smoothing::Sma { window: 4 });
+        smoother.apply(reading(0.0));
+
+        let values: Vec<f64> = (0..4).map(|_| smoother.apply(reading(1.0)).value).collect();
+        assert_eq!(values, [0.5, 2.0 / 3.0, 0.75, 1.0]);
+    }
+
+    #[test]
+    fn a_unit_change_restarts_the_filter() {
+        let mut smoother = Smoother::new(Smoothing::Ema { alpha: 0.1 });
+        smoother.apply(reading(10.0));
+
+        let mut ohms = reading(500.0);
+        ohms.unit = Unit::Ohm;
+        assert_eq!(smoother.apply(ohms).value, 500.0);
+    }
+}
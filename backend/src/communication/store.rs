@@ -0,0 +1,378 @@
+//! Storage of recorded measurements
+//!
+//! Every recorded measurement goes to the configured `MeasurementStore`,
+//! and history queries such as trends read from it. The in-memory store
+//! keeps a bounded buffer per device that is dropped on disconnect; the
+//! SQLite store (feature `sqlite`) keeps everything across restarts.
+
+use super::history::MeasurementHistory;
+use crate::config::StoreConfig;
+use crate::device::Measurement;
+use crate::error::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Backend holding the measurements of all devices
+pub trait MeasurementStore: Send {
+    /// Append a recorded measurement of `device_id`
+    fn insert(&mut self, device_id: &str, measurement: &Measurement) -> Result<()>;
+
+    /// Measurements of `device_id` taken within `from..=to`, oldest first.
+    /// An open bound is unlimited; unstamped measurements only match when
+    /// both bounds are open.
+    fn range(
+        &self,
+        device_id: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Measurement>>;
+
+    /// Called once a device is disconnected
+    fn device_removed(&mut self, _device_id: &str) {}
+
+    /// Memory held by buffered measurements; `None` for stores on disk
+    fn memory_usage(&self) -> Option<MemoryUsage> {
+        None
+    }
+}
+
+/// Memory taken by the buffers of an in-memory store
+#[derive(Debug, Clone, Copy, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct MemoryUsage {
+    pub bytes: usize,
+    pub budget_bytes: Option<usize>,
+    /// Measurements each device may currently keep
+    pub per_device_capacity: usize,
+}
+
+/// Open the store selected by the configuration
+pub fn open(config: &StoreConfig) -> Result<Box<dyn MeasurementStore>> {
+    match config {
+        StoreConfig::Memory {
+            capacity,
+            memory_budget_bytes,
+        } => Ok(Box::new(InMemoryStore::new(
+            *capacity,
+            *memory_budget_bytes,
+        ))),
+        #[cfg(feature = "sqlite")]
+        StoreConfig::Sqlite { path } => Ok(Box::new(sqlite::SqliteStore::open(path)?)),
+        #[cfg(not(feature = "sqlite"))]
+        StoreConfig::Sqlite { .. } => Err(crate::error::Error::Config(
+            "the SQLite store requires building with the `sqlite` feature".to_string(),
+        )),
+    }
+}
+
+fn in_range(
+    measurement: &Measurement,
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+) -> bool {
+    match measurement.timestamp {
+        Some(timestamp) => {
+            from.is_none_or(|from| timestamp >= from) && to.is_none_or(|to| timestamp <= to)
+        }
+        None => from.is_none() && to.is_none(),
+    }
+}
+
+/// Estimated memory of one buffered measurement; only the token of an
+/// unrecognised unit lives on the heap, and is left out
+const SAMPLE_BYTES: usize = std::mem::size_of::<Measurement>();
+
+/// The most recent `capacity` measurements of each connected device.
+///
+/// With a memory budget, every device gets an equal share of it once the
+/// full capacity of all devices would exceed it, so buffers shrink
+/// proportionally as devices are added and grow back as they leave.
+pub struct InMemoryStore {
+    capacity: usize,
+    memory_budget: Option<usize>,
+    devices: HashMap<String, MeasurementHistory>,
+}
+
+impl InMemoryStore {
+    pub fn new(capacity: usize, memory_budget: Option<usize>) -> Self {
+        Self {
+            capacity,
+            memory_budget,
+            devices: HashMap::new(),
+        }
+    }
+
+    /// Capacity of each buffer when `devices` share the budget
+    fn per_device_capacity(&self, devices: usize) -> usize {
+        match self.memory_budget {
+            Some(budget) if devices > 0 => self.capacity.min(budget / SAMPLE_BYTES / devices),
+            _ => self.capacity,
+        }
+    }
+
+    fn rebalance(&mut self) {
+        let capacity = self.per_device_capacity(self.devices.len());
+        for history in self.devices.values_mut() {
+            if history.capacity() != capacity {
+                history.set_capacity(capacity);
+            }
+        }
+    }
+}
+
+impl MeasurementStore for InMemoryStore {
+    fn insert(&mut self, device_id: &str, measurement: &Measurement) -> Result<()> {
+        if !self.devices.contains_key(device_id) {
+            let capacity = self.per_device_capacity(self.devices.len() + 1);
+            self.devices
+                .insert(device_id.to_string(), MeasurementHistory::new(capacity));
+            self.rebalance();
+        }
+        if let Some(history) = self.devices.get_mut(device_id) {
+            history.push(measurement.clone());
+        }
+        Ok(())
+    }
+
+    fn range(
+        &self,
+        device_id: &str,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+    ) -> Result<Vec<Measurement>> {
+        Ok(self
+            .devices
+            .get(device_id)
+            .map(|history| {
+                history
+                    .iter()
+                    .filter(|m| in_range(m, from, to))
+                    .cloned()
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    fn device_removed(&mut self, device_id: &str) {
+        if self.devices.remove(device_id).is_some() {
+            self.rebalance();
+        }
+    }
+
+    fn memory_usage(&self) -> Option<MemoryUsage> {
+        Some(MemoryUsage {
+            bytes: self.devices.values().map(|h| h.len() * SAMPLE_BYTES).sum(),
+            budget_bytes: self.memory_budget,
+            per_device_capacity: self.per_device_capacity(self.devices.len()),
+        })
+    }
+}
+
+#[cfg(feature = "sqlite")]
+mod sqlite {
+    use super::MeasurementStore;
+    use crate::device::Measurement;
+    use crate::error::{Error, Result};
+    use chrono::{DateTime, Utc};
+    use rusqlite::{params, Connection};
+    use std::path::Path;
+
+    const SCHEMA: &str = "
+        CREATE TABLE IF NOT EXISTS measurements (
+            id INTEGER PRIMARY KEY,
+            device_id TEXT NOT NULL,
+            timestamp_us INTEGER,
+            sequence INTEGER NOT NULL,
+            value REAL NOT NULL,
+            data TEXT NOT NULL
+        );
+        CREATE INDEX IF NOT EXISTS measurements_device_time
+            ON measurements (device_id, timestamp_us);
+        CREATE INDEX IF NOT EXISTS measurements_time ON measurements (timestamp_us);
+    ";
+
+    fn storage_error(error: rusqlite::Error) -> Error {
+        Error::Storage(error.to_string())
+    }
+
+    /// Measurements persisted in a SQLite database, one row per reading.
+    ///
+    /// `data` holds the full measurement as JSON; the other columns exist
+    /// for indexing and ad-hoc SQL.
+    pub struct SqliteStore {
+        connection: Connection,
+    }
+
+    impl SqliteStore {
+        pub fn open(path: &Path) -> Result<Self> {
+            let connection = Connection::open(path).map_err(storage_error)?;
+            connection.execute_batch(SCHEMA).map_err(storage_error)?;
+            tracing::info!("Storing measurements in {}", path.display());
+            Ok(Self { connection })
+        }
+    }
+
+    impl MeasurementStore for SqliteStore {
+        fn insert(&mut self, device_id: &str, measurement: &Measurement) -> Result<()> {
+            self.connection
+                .execute(
+                    "INSERT INTO measurements (device_id, timestamp_us, sequence, value, data)
+                     VALUES (?1, ?2, ?3, ?4, ?5)",
+                    params![
+                        device_id,
+                        measurement.timestamp.map(|t| t.timestamp_micros()),
+                        measurement.sequence as i64,
+                        measurement.value,
+                        serde_json::to_string(measurement)?,
+                    ],
+                )
+                .map_err(storage_error)?;
+            Ok(())
+        }
+
+        fn range(
+            &self,
+            device_id: &str,
+            from: Option<DateTime<Utc>>,
+            to: Option<DateTime<Utc>>,
+        ) -> Result<Vec<Measurement>> {
+            // Unstamped rows have a NULL timestamp and fail either comparison.
+            let mut statement = self
+                .connection
+                .prepare_cached(
+                    "SELECT data FROM measurements
+                     WHERE device_id = ?1
+                       AND (?2 IS NULL OR timestamp_us >= ?2)
+                       AND (?3 IS NULL OR timestamp_us <= ?3)
+                     ORDER BY timestamp_us, id",
+                )
+                .map_err(storage_error)?;
+            let rows = statement
+                .query_map(
+                    params![
+                        device_id,
+                        from.map(|t| t.timestamp_micros()),
+                        to.map(|t| t.timestamp_micros()),
+                    ],
+                    |row| row.get::<_, String>(0),
+                )
+                .map_err(storage_error)?;
+
+            rows.map(|data| Ok(serde_json::from_str(&data.map_err(storage_error)?)?))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{MeasurementAttribute, MeasurementState, Unit};
+    use chrono::TimeZone;
+
+    fn reading_at(second: u32) -> Measurement {
+        let mut measurement = Measurement::new(
+            f64::from(second),
+            Unit::VoltDc,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        );
+        measurement.timestamp = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, second).single();
+        measurement
+    }
+
+    fn at(second: u32) -> Option<DateTime<Utc>> {
+        reading_at(second).timestamp
+    }
+
+    /// Insert readings at 0..5 s and check ranges over them
+    fn check_range(store: &mut dyn MeasurementStore) {
+        for second in 0..5 {
+            store.insert("device_0001", &reading_at(second)).unwrap();
+        }
+        store.insert("device_0002", &reading_at(2)).unwrap();
+
+        let values = |from, to| -> Vec<f64> {
+            let range = store.range("device_0001", from, to).unwrap();
+            range.iter().map(|m| m.value).collect()
+        };
+        assert_eq!(values(None, None), [0.0, 1.0, 2.0, 3.0, 4.0]);
+        assert_eq!(values(at(1), at(3)), [1.0, 2.0, 3.0]);
+        assert_eq!(values(at(3), None), [3.0, 4.0]);
+        assert_eq!(values(None, at(0)), [0.0]);
+        assert!(store.range("device_0003", None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn in_memory_range_is_inclusive() {
+        check_range(&mut InMemoryStore::new(100, None));
+    }
+
+    #[test]
+    fn in_memory_store_forgets_removed_devices() {
+        let mut store = InMemoryStore::new(100, None);
+        store.insert("device_0001", &reading_at(0)).unwrap();
+        store.device_removed("device_0001");
+        assert!(store.range("device_0001", None, None).unwrap().is_empty());
+    }
+
+    #[test]
+    fn memory_budget_is_shared_as_devices_are_added() {
+        // Room for 12 readings in all, at most 10 per device
+        let mut store = InMemoryStore::new(10, Some(12 * SAMPLE_BYTES));
+        let capacity = |store: &InMemoryStore| store.memory_usage().unwrap().per_device_capacity;
+        let kept = |store: &InMemoryStore, id| store.range(id, None, None).unwrap().len();
+
+        for second in 0..10 {
+            store.insert("device_0001", &reading_at(second)).unwrap();
+        }
+        assert_eq!(capacity(&store), 10);
+        assert_eq!(kept(&store, "device_0001"), 10);
+
+        store.insert("device_0002", &reading_at(0)).unwrap();
+        assert_eq!(capacity(&store), 6);
+        let values: Vec<f64> = store
+            .range("device_0001", None, None)
+            .unwrap()
+            .iter()
+            .map(|m| m.value)
+            .collect();
+        // The oldest readings go first
+        assert_eq!(values, [4.0, 5.0, 6.0, 7.0, 8.0, 9.0]);
+
+        store.insert("device_0003", &reading_at(0)).unwrap();
+        assert_eq!(capacity(&store), 4);
+        assert_eq!(kept(&store, "device_0001"), 4);
+        let usage = store.memory_usage().unwrap();
+        assert!(usage.bytes <= usage.budget_bytes.unwrap());
+
+        // and the buffers grow back as devices leave
+        store.device_removed("device_0002");
+        store.device_removed("device_0003");
+        assert_eq!(capacity(&store), 10);
+    }
+
+    #[test]
+    fn full_buffer_evicts_the_oldest_reading() {
+        let mut store = InMemoryStore::new(3, None);
+        for second in 0..5 {
+            store.insert("device_0001", &reading_at(second)).unwrap();
+        }
+        let values: Vec<f64> = store
+            .range("device_0001", None, None)
+            .unwrap()
+            .iter()
+            .map(|m| m.value)
+            .collect();
+        assert_eq!(values, [2.0, 3.0, 4.0]);
+        assert_eq!(store.memory_usage().unwrap().bytes, 3 * SAMPLE_BYTES);
+    }
+
+    #[cfg(feature = "sqlite")]
+    #[test]
+    fn sqlite_range_is_inclusive() {
+        let path = std::path::Path::new(":memory:");
+        check_range(&mut sqlite::SqliteStore::open(path).unwrap());
+    }
+}
@@ -0,0 +1,95 @@
+//! Newline-delimited JSON measurement stream over raw TCP
+//!
+//! For consumers that can't speak HTTP, such as SCADA systems. A client
+//! sends the id of a connected device as its first line and then receives
+//! one JSON measurement per line until it disconnects or the device does.
+//! Pausing and resuming polling shows up as an `{"event": "paused"}` or
+//! `{"event": "resumed"}` line with its timestamp. A reading the meter
+//! auto-saved is followed by an `{"event": "auto_saved"}` line holding it
+//! as `measurement`.
+//!
+//! A client that reads slower than the device is polled gets the newest
+//! reading each time it catches up, skipping the ones in between.
+
+use super::polling::{self, LatestUpdates};
+use super::AppState;
+use crate::config::StreamConfig;
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+
+/// Accept stream clients until the listener fails
+pub async fn serve(config: StreamConfig, state: Arc<Mutex<AppState>>) -> std::io::Result<()> {
+    let listener = TcpListener::bind(("127.0.0.1", config.port)).await?;
+    tracing::info!("Streaming measurements on tcp://127.0.0.1:{}", config.port);
+
+    loop {
+        let (socket, peer) = listener.accept().await?;
+        let state = state.clone();
+        tokio::spawn(async move {
+            if let Err(error) = handle_client(socket, config, &state).await {
+                tracing::debug!(%peer, %error, "Stream client dropped");
+            }
+        });
+    }
+}
+
+async fn handle_client(
+    socket: TcpStream,
+    config: StreamConfig,
+    state: &Arc<Mutex<AppState>>,
+) -> std::io::Result<()> {
+    let (reader, mut writer) = socket.into_split();
+    let mut reader = BufReader::new(reader);
+
+    let mut line = String::new();
+    if reader.read_line(&mut line).await? == 0 {
+        return Ok(());
+    }
+    let device_id = line.trim();
+
+    let updates = match polling::subscribe(device_id, config.poll_interval(), state).await {
+        Ok(updates) => updates,
+        Err(error) => {
+            return write_line(&mut writer, &serde_json::json!({ "error": error })).await;
+        }
+    };
+    let mut updates = LatestUpdates::new(updates);
+    tracing::info!(device_id, "Stream client subscribed");
+
+    // Anything the client sends after the first line is ignored; reading
+    // only serves to notice when it hangs up.
+    let mut discard = [0u8; 256];
+    loop {
+        tokio::select! {
+            update = updates.recv() => match update {
+                Some(update) => write_line(&mut writer, &update.to_json()?).await?,
+                None => break,
+            },
+            read = reader.read(&mut discard) => {
+                if read? == 0 {
+                    break;
+                }
+            }
+        }
+    }
+
+    tracing::info!(
+        device_id,
+        dropped = updates.dropped(),
+        "Stream client unsubscribed"
+    );
+    Ok(())
+}
+
+async fn write_line<W, T>(writer: &mut W, value: &T) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(value)?;
+    line.push(b'\n');
+    writer.write_all(&line).await
+}
@@ -0,0 +1,161 @@
+//! End-of-session summary of a device
+//!
+//! Collected when a device disconnects, from its counters and the readings
+//! still buffered, so a logging session ends with a report the client
+//! doesn't have to recompute.
+
+use crate::device::{Measurement, MeasurementState, Unit};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::path::Path;
+
+/// Statistics of the in-range readings in one unit
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct UnitStats {
+    pub unit: Unit,
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+}
+
+/// What happened between a device's connect and disconnect
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct SessionSummary {
+    pub device_id: String,
+    pub connected_at: DateTime<Utc>,
+    pub disconnected_at: DateTime<Utc>,
+    pub duration_secs: f64,
+    /// Successful measurement requests
+    pub measurement_count: u64,
+    /// Readings recorded, counting each reading of a display download
+    pub sample_count: u64,
+    /// Recorded readings no longer buffered, which `units` doesn't cover
+    pub dropped_samples: u64,
+    /// Failed measurement requests
+    pub error_count: u64,
+    /// Buffered in-range readings per unit, in the order the units first
+    /// appeared
+    pub units: Vec<UnitStats>,
+}
+
+impl SessionSummary {
+    /// Summarize a session from its counters and the `buffered` readings
+    /// taken since `connected_at`, oldest first
+    pub fn new(
+        device_id: String,
+        connected_at: DateTime<Utc>,
+        measurement_count: u64,
+        sample_count: u64,
+        error_count: u64,
+        buffered: &[Measurement],
+    ) -> Self {
+        let disconnected_at = Utc::now();
+        let mut units: Vec<UnitStats> = Vec::new();
+        let mut sums: Vec<f64> = Vec::new();
+        for measurement in buffered
+            .iter()
+            .filter(|m| m.state == MeasurementState::Normal)
+        {
+            let index = match units
+                .iter()
+                .position(|stats| stats.unit == measurement.unit)
+            {
+                Some(index) => index,
+                None => {
+                    units.push(UnitStats {
+                        unit: measurement.unit.clone(),
+                        count: 0,
+                        min: f64::INFINITY,
+                        max: f64::NEG_INFINITY,
+                        mean: 0.0,
+                    });
+                    sums.push(0.0);
+                    units.len() - 1
+                }
+            };
+            let stats = &mut units[index];
+            stats.count += 1;
+            stats.min = stats.min.min(measurement.value);
+            stats.max = stats.max.max(measurement.value);
+            sums[index] += measurement.value;
+        }
+        for (stats, sum) in units.iter_mut().zip(sums) {
+            stats.mean = sum / stats.count as f64;
+        }
+
+        Self {
+            device_id,
+            connected_at,
+            disconnected_at,
+            duration_secs: (disconnected_at - connected_at)
+                .to_std()
+                .map_or(0.0, |duration| duration.as_secs_f64()),
+            measurement_count,
+            sample_count,
+            dropped_samples: sample_count.saturating_sub(buffered.len() as u64),
+            error_count,
+            units,
+        }
+    }
+
+    /// Write the summary as pretty JSON into `dir`, named after the device
+    /// and its connect time
+    pub fn write_to(&self, dir: &Path) -> std::io::Result<()> {
+        let path = dir.join(format!(
+            "{}-{}.json",
+            self.device_id,
+            self.connected_at.format("%Y%m%dT%H%M%SZ")
+        ));
+        let json = serde_json::to_string_pretty(self).map_err(std::io::Error::other)?;
+        std::fs::write(path, json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::MeasurementAttribute;
+
+    fn reading(value: f64, unit: Unit, state: MeasurementState) -> Measurement {
+        Measurement::new(value, unit, state, MeasurementAttribute::None)
+    }
+
+    #[test]
+    fn units_are_summarized_apart_and_overloads_left_out() {
+        let buffered = [
+            reading(1.0, Unit::VoltDc, MeasurementState::Normal),
+            reading(100.0, Unit::Ohm, MeasurementState::Normal),
+            reading(0.0, Unit::Ohm, MeasurementState::Overload),
+            reading(3.0, Unit::VoltDc, MeasurementState::Normal),
+            reading(300.0, Unit::Ohm, MeasurementState::Normal),
+        ];
+
+        let summary = SessionSummary::new("dev".into(), Utc::now(), 6, 7, 1, &buffered);
+
+        assert_eq!(summary.dropped_samples, 2);
+        assert_eq!(summary.error_count, 1);
+        let units: Vec<_> = summary
+            .units
+            .iter()
+            .map(|stats| {
+                (
+                    stats.unit.clone(),
+                    stats.count,
+                    stats.min,
+                    stats.max,
+                    stats.mean,
+                )
+            })
+            .collect();
+        assert_eq!(
+            units,
+            [
+                (Unit::VoltDc, 2, 1.0, 3.0, 2.0),
+                (Unit::Ohm, 2, 100.0, 300.0, 200.0)
+            ]
+        );
+    }
+}
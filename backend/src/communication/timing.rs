@@ -0,0 +1,85 @@
+//! Per-command latency statistics for diagnosing slow devices
+
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Latencies kept per command type for the statistics
+pub const TIMING_WINDOW: usize = 100;
+
+/// Latency of the recent calls of one command type
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct CommandTiming {
+    /// Calls since connect, including those no longer in the window
+    pub count: u64,
+    /// Calls the latencies below are computed over
+    pub window: usize,
+    pub min_ms: f64,
+    pub max_ms: f64,
+    pub avg_ms: f64,
+}
+
+#[derive(Default)]
+struct Samples {
+    count: u64,
+    recent: VecDeque<Duration>,
+}
+
+/// Round-trip times of one device's commands, grouped by command type
+#[derive(Default)]
+pub(crate) struct CommandTimings {
+    commands: Mutex<HashMap<String, Samples>>,
+}
+
+impl CommandTimings {
+    pub(crate) fn record(&self, command: &str, elapsed: Duration) {
+        let mut commands = self.commands.lock().unwrap_or_else(|e| e.into_inner());
+        let samples = commands.entry(command.to_string()).or_default();
+        samples.count += 1;
+        if samples.recent.len() >= TIMING_WINDOW {
+            samples.recent.pop_front();
+        }
+        samples.recent.push_back(elapsed);
+    }
+
+    /// Statistics of every command type seen, by name
+    pub(crate) fn profile(&self) -> BTreeMap<String, CommandTiming> {
+        let millis = |d: &Duration| d.as_secs_f64() * 1000.0;
+        let commands = self.commands.lock().unwrap_or_else(|e| e.into_inner());
+        commands
+            .iter()
+            .map(|(command, samples)| {
+                let recent = samples.recent.iter().map(millis);
+                let timing = CommandTiming {
+                    count: samples.count,
+                    window: samples.recent.len(),
+                    min_ms: recent.clone().fold(f64::INFINITY, f64::min),
+                    max_ms: recent.clone().fold(0.0, f64::max),
+                    avg_ms: recent.sum::<f64>() / samples.recent.len() as f64,
+                };
+                (command.clone(), timing)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statistics_cover_the_recent_window_only() {
+        let timings = CommandTimings::default();
+        timings.record("QM", Duration::from_millis(500));
+        for _ in 0..TIMING_WINDOW {
+            timings.record("QM", Duration::from_millis(10));
+        }
+
+        let qm = &timings.profile()["QM"];
+        assert_eq!(qm.count, TIMING_WINDOW as u64 + 1);
+        assert_eq!(qm.window, TIMING_WINDOW);
+        assert_eq!((qm.min_ms, qm.max_ms, qm.avg_ms), (10.0, 10.0, 10.0));
+    }
+}
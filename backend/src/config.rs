@@ -0,0 +1,476 @@
+//! Backend configuration
+//!
+//! Settings are read from the JSON file named by the `TSMULTIMETER_CONFIG`
+//! environment variable. Every field is optional; without a file the defaults
+//! apply.
+
+use crate::communication::history::DEFAULT_HISTORY_CAPACITY;
+use crate::device::{Measurement, MeasurementState, Unit, UnitCategory, Verdict};
+use crate::error::{Error, Result};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Environment variable naming the configuration file
+pub const CONFIG_ENV_VAR: &str = "TSMULTIMETER_CONFIG";
+
+/// Largest window accepted for a simple moving average
+pub const MAX_SMOOTHING_WINDOW: usize = 1000;
+
+/// Default bound of each device's command queue
+pub const DEFAULT_MAX_QUEUED_COMMANDS: usize = 32;
+
+/// Default limit on the size of JSON request bodies
+pub const DEFAULT_MAX_BODY_BYTES: u64 = 64 * 1024;
+
+/// Default number of named captures kept per device
+pub const DEFAULT_MAX_CAPTURES: usize = 100;
+
+/// Default time a disconnected device's port stays open for a reconnect
+pub const DEFAULT_PORT_LINGER_MS: u64 = 1000;
+
+/// Longest accepted port linger
+pub const MAX_PORT_LINGER_MS: u64 = 60_000;
+
+/// Default number of failed reads in a row after which a device is
+/// disconnected
+pub const DEFAULT_MAX_CONSECUTIVE_ERRORS: u32 = 20;
+
+/// Top-level backend configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// Display units applied to every device unless overridden per device
+    pub display_preferences: DisplayPreferences,
+    /// Raw TCP measurement stream; disabled when absent
+    pub stream: Option<StreamConfig>,
+    /// Where recorded measurements are kept
+    pub store: StoreConfig,
+    /// Commands that may wait for one device before further requests are
+    /// refused as busy
+    pub max_queued_commands: usize,
+    /// Named captures kept per device
+    pub max_captures: usize,
+    /// Largest JSON request body accepted, in bytes
+    pub max_body_bytes: u64,
+    /// Reopen a device with backoff and retry once when a measurement or
+    /// raw command fails with a connection error; connect requests may
+    /// override it per device
+    pub auto_reconnect: bool,
+    /// Keep a disconnected device's port open this long, so a reconnect to
+    /// it reuses the open handle; zero closes ports at once
+    pub port_linger_ms: u64,
+    /// Serve debugging endpoints such as the raw serial stream, which
+    /// expose everything sent to and from the meters
+    pub debug_endpoints: bool,
+    /// Disconnect a device once this many measurements in a row have
+    /// failed; zero keeps retrying forever
+    pub max_consecutive_errors: u32,
+    /// File the shutdown report is written to on exit, besides stdout
+    pub shutdown_report_path: Option<PathBuf>,
+    /// Directory each device's session summary is written to when it
+    /// disconnects; not written when absent
+    pub session_summary_dir: Option<PathBuf>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            display_preferences: DisplayPreferences::default(),
+            stream: None,
+            store: StoreConfig::default(),
+            max_queued_commands: DEFAULT_MAX_QUEUED_COMMANDS,
+            max_captures: DEFAULT_MAX_CAPTURES,
+            max_body_bytes: DEFAULT_MAX_BODY_BYTES,
+            auto_reconnect: false,
+            port_linger_ms: DEFAULT_PORT_LINGER_MS,
+            debug_endpoints: false,
+            max_consecutive_errors: DEFAULT_MAX_CONSECUTIVE_ERRORS,
+            shutdown_report_path: None,
+            session_summary_dir: None,
+        }
+    }
+}
+
+impl Config {
+    /// How long disconnected ports are kept open; `None` when disabled
+    pub fn port_linger(&self) -> Option<Duration> {
+        (self.port_linger_ms > 0).then(|| Duration::from_millis(self.port_linger_ms))
+    }
+
+    /// Load the configuration file named by `TSMULTIMETER_CONFIG`, if set
+    pub fn load() -> Result<Self> {
+        let Ok(path) = std::env::var(CONFIG_ENV_VAR) else {
+            return Ok(Self::default());
+        };
+
+        let contents = std::fs::read_to_string(&path)
+            .map_err(|e| Error::Config(format!("Failed to read {}: {}", path, e)))?;
+        let config: Self = serde_json::from_str(&contents)
+            .map_err(|e| Error::Config(format!("Invalid config file {}: {}", path, e)))?;
+        config.validate()?;
+
+        tracing::info!("Loaded configuration from {}", path);
+        Ok(config)
+    }
+
+    fn validate(&self) -> Result<()> {
+        self.display_preferences.validate()?;
+        if self.max_queued_commands == 0 {
+            return Err(Error::Config(
+                "max_queued_commands must be at least 1".to_string(),
+            ));
+        }
+        if self.max_body_bytes == 0 {
+            return Err(Error::Config(
+                "max_body_bytes must be at least 1".to_string(),
+            ));
+        }
+        if self.port_linger_ms > MAX_PORT_LINGER_MS {
+            return Err(Error::Config(format!(
+                "port_linger_ms must be at most {}",
+                MAX_PORT_LINGER_MS
+            )));
+        }
+        if let StoreConfig::Memory {
+            memory_budget_bytes: Some(0),
+            ..
+        } = self.store
+        {
+            return Err(Error::Config(
+                "store.memory_budget_bytes must be non-zero".to_string(),
+            ));
+        }
+        if let Some(stream) = &self.stream {
+            stream.validate()?;
+        }
+        Ok(())
+    }
+}
+
+/// Measurement store backend
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum StoreConfig {
+    /// Keep the most recent `capacity` readings of each connected device
+    Memory {
+        #[serde(default = "StoreConfig::default_capacity")]
+        capacity: usize,
+        /// Bound on the memory of all buffers together; each device's
+        /// share shrinks as devices are added
+        #[serde(default)]
+        memory_budget_bytes: Option<usize>,
+    },
+    /// Persist every reading to a SQLite database (feature `sqlite`)
+    Sqlite { path: PathBuf },
+}
+
+impl StoreConfig {
+    fn default_capacity() -> usize {
+        DEFAULT_HISTORY_CAPACITY
+    }
+}
+
+impl Default for StoreConfig {
+    fn default() -> Self {
+        Self::Memory {
+            capacity: DEFAULT_HISTORY_CAPACITY,
+            memory_budget_bytes: None,
+        }
+    }
+}
+
+/// TCP server streaming newline-delimited JSON measurements.
+///
+/// A client sends a device id as its first line and then receives every
+/// reading of that device until either side disconnects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct StreamConfig {
+    /// Port to listen on (localhost only)
+    pub port: u16,
+    /// How often subscribed devices are polled
+    #[serde(default = "StreamConfig::default_poll_interval_ms")]
+    pub poll_interval_ms: u64,
+    /// Snap the timestamps of polled readings to the poll grid, flagging
+    /// readings that arrived further than this from their grid point
+    #[serde(default)]
+    pub snap_tolerance_ms: Option<u64>,
+}
+
+impl StreamConfig {
+    fn default_poll_interval_ms() -> u64 {
+        500
+    }
+
+    /// Interval at which subscribed devices are polled
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_millis(self.poll_interval_ms)
+    }
+
+    pub fn snap_tolerance(&self) -> Option<Duration> {
+        self.snap_tolerance_ms.map(Duration::from_millis)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.port == 0 {
+            return Err(Error::Config("stream.port must be non-zero".to_string()));
+        }
+        if self.poll_interval_ms == 0 {
+            return Err(Error::Config(
+                "stream.poll_interval_ms must be non-zero".to_string(),
+            ));
+        }
+        // Every reading is within half an interval of some grid point.
+        if let Some(tolerance) = self.snap_tolerance_ms {
+            if tolerance * 2 > self.poll_interval_ms {
+                return Err(Error::Config(format!(
+                    "stream.snap_tolerance_ms must be at most half of poll_interval_ms, got {}",
+                    tolerance
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Moving-average filter applied to a device's live readings
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Smoothing {
+    /// Exponential moving average; each reading moves the smoothed value
+    /// `alpha` of the way towards it
+    Ema { alpha: f64 },
+    /// Mean of the last `window` readings
+    Sma { window: usize },
+}
+
+impl Smoothing {
+    pub fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Ema { alpha } if !(alpha > 0.0 && alpha <= 1.0) => Err(Error::Config(format!(
+                "EMA alpha must be in (0, 1], got {}",
+                alpha
+            ))),
+            Self::Sma { window } if window == 0 || window > MAX_SMOOTHING_WINDOW => {
+                Err(Error::Config(format!(
+                    "SMA window must be between 1 and {}, got {}",
+                    MAX_SMOOTHING_WINDOW, window
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Readings whose sign is flipped, e.g. for a current clamp fitted the
+/// other way round. Either `true`/`false` for every reading or a list of
+/// quantities such as `["current"]`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SignInversion {
+    All(bool),
+    Categories(Vec<UnitCategory>),
+}
+
+impl Default for SignInversion {
+    fn default() -> Self {
+        Self::All(false)
+    }
+}
+
+impl SignInversion {
+    pub fn applies_to(&self, unit: &Unit) -> bool {
+        match self {
+            Self::All(invert) => *invert,
+            Self::Categories(categories) => categories.contains(&unit.category()),
+        }
+    }
+}
+
+/// Acceptance window for go/no-go testing: readings within
+/// `nominal - lower_tolerance ..= nominal + upper_tolerance` pass. Either
+/// side falls back to `tolerance`, so a symmetric window needs only that.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Limits {
+    pub nominal: f64,
+    #[serde(default)]
+    pub tolerance: Option<f64>,
+    #[serde(default)]
+    pub lower_tolerance: Option<f64>,
+    #[serde(default)]
+    pub upper_tolerance: Option<f64>,
+    pub unit: Unit,
+}
+
+impl Limits {
+    /// Lowest and highest passing value; `None` for a side with no tolerance
+    fn bounds(&self) -> Option<(f64, f64)> {
+        let lower = self.lower_tolerance.or(self.tolerance)?;
+        let upper = self.upper_tolerance.or(self.tolerance)?;
+        Some((self.nominal - lower, self.nominal + upper))
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        let tolerances = [self.tolerance, self.lower_tolerance, self.upper_tolerance];
+        if !self.nominal.is_finite()
+            || tolerances
+                .iter()
+                .flatten()
+                .any(|tolerance| !(tolerance.is_finite() && *tolerance >= 0.0))
+        {
+            return Err(Error::Config(
+                "limits must be finite and tolerances non-negative".to_string(),
+            ));
+        }
+        if self.bounds().is_none() {
+            return Err(Error::Config(
+                "limits need a tolerance, or both a lower and an upper tolerance".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Pass a `Normal` reading in, or convertible to, the limit unit that
+    /// lies within the bounds; fail anything else
+    pub fn verdict(&self, measurement: &Measurement) -> Verdict {
+        let Some((lower, upper)) = self.bounds() else {
+            return Verdict::Fail;
+        };
+        match measurement.clone().expect_unit(self.unit.clone()) {
+            Ok(reading)
+                if reading.state == MeasurementState::Normal
+                    && (lower..=upper).contains(&reading.value) =>
+            {
+                Verdict::Pass
+            }
+            _ => Verdict::Fail,
+        }
+    }
+}
+
+/// Units measurements are presented in.
+///
+/// Unset fields leave readings in the unit the device reports.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+#[serde(default)]
+pub struct DisplayPreferences {
+    /// Temperature unit readings are converted to
+    pub temperature_unit: Option<Unit>,
+    /// Show conductance readings as resistance
+    pub conductance_as_resistance: Option<bool>,
+}
+
+impl DisplayPreferences {
+    /// Check that the preferences name convertible units
+    pub fn validate(&self) -> Result<()> {
+        match &self.temperature_unit {
+            None | Some(Unit::Celsius) | Some(Unit::Fahrenheit) | Some(Unit::Kelvin) => Ok(()),
+            Some(unit) => Err(Error::Config(format!(
+                "{:?} is not a temperature unit",
+                unit
+            ))),
+        }
+    }
+
+    /// Use these preferences where set and `fallback` otherwise
+    pub fn or(self, fallback: Self) -> Self {
+        Self {
+            temperature_unit: self.temperature_unit.or(fallback.temperature_unit),
+            conductance_as_resistance: self
+                .conductance_as_resistance
+                .or(fallback.conductance_as_resistance),
+        }
+    }
+
+    /// Convert a measurement into the preferred units
+    pub fn apply(&self, measurement: Measurement) -> Measurement {
+        let measurement = match &self.temperature_unit {
+            Some(target) => measurement
+                .convert_temperature(target.clone())
+                .unwrap_or(measurement),
+            None => measurement,
+        };
+        match self.conductance_as_resistance {
+            Some(true) => measurement
+                .conductance_to_resistance()
+                .unwrap_or(measurement),
+            _ => measurement,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::{MeasurementAttribute, MeasurementState};
+
+    fn reading(value: f64, unit: Unit) -> Measurement {
+        Measurement::new(
+            value,
+            unit,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        )
+    }
+
+    /// 5 V, 0.1 V below and 0.2 V above
+    fn limits() -> Limits {
+        Limits {
+            nominal: 5.0,
+            tolerance: Some(0.1),
+            lower_tolerance: None,
+            upper_tolerance: Some(0.2),
+            unit: Unit::VoltDc,
+        }
+    }
+
+    #[test]
+    fn readings_inside_the_window_pass() {
+        for value in [4.9, 5.0, 5.15, 5.2] {
+            assert_eq!(
+                limits().verdict(&reading(value, Unit::VoltDc)),
+                Verdict::Pass,
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn readings_outside_the_window_fail() {
+        for value in [4.85, 5.25, -5.0] {
+            assert_eq!(
+                limits().verdict(&reading(value, Unit::VoltDc)),
+                Verdict::Fail,
+                "{}",
+                value
+            );
+        }
+        let mut overload = reading(5.0, Unit::VoltDc);
+        overload.state = MeasurementState::Overload;
+        assert_eq!(limits().verdict(&overload), Verdict::Fail);
+        assert_eq!(limits().verdict(&reading(5.0, Unit::VoltAc)), Verdict::Fail);
+    }
+
+    #[test]
+    fn convertible_readings_are_judged_in_the_limit_unit() {
+        let limits = Limits {
+            nominal: 25.0,
+            tolerance: Some(0.5),
+            lower_tolerance: None,
+            upper_tolerance: None,
+            unit: Unit::Celsius,
+        };
+        assert_eq!(
+            limits.verdict(&reading(77.0, Unit::Fahrenheit)),
+            Verdict::Pass
+        );
+        assert_eq!(
+            limits.verdict(&reading(79.0, Unit::Fahrenheit)),
+            Verdict::Fail
+        );
+    }
+}
@@ -0,0 +1,360 @@
+//! Unit conversions for measurements
+//!
+//! Conversions only rescale `Normal` readings; overload, blank and other
+//! states keep their sentinel value and only change unit.
+
+use crate::device::{DbReference, Measurement, MeasurementState, Unit};
+use crate::error::{Error, Result};
+
+/// Value the meter reports for overload readings
+pub const OVERLOAD_VALUE: f64 = 9.99999999e37;
+
+/// Absolute zero in °C, the lowest temperature any scale can express
+pub const ABSOLUTE_ZERO_C: f64 = -273.15;
+
+/// Margin for rounding when comparing against absolute zero
+const ABSOLUTE_ZERO_TOLERANCE: f64 = 1e-9;
+
+/// Reference impedance for dBm when none is given, the audio standard
+pub const DEFAULT_DBM_REFERENCE_OHMS: f64 = 600.0;
+
+/// Power in watts that 0 dBm stands for
+const DBM_REFERENCE_WATTS: f64 = 1e-3;
+
+/// Largest accepted temperature offset, in either direction, in °C
+pub const MAX_TEMPERATURE_OFFSET_C: f64 = 50.0;
+
+/// Check a user temperature offset lies within `±MAX_TEMPERATURE_OFFSET_C`
+pub fn validate_temperature_offset(offset_celsius: f64) -> Result<()> {
+    if !(-MAX_TEMPERATURE_OFFSET_C..=MAX_TEMPERATURE_OFFSET_C).contains(&offset_celsius) {
+        return Err(Error::Config(format!(
+            "temperature offset must be between -{0} and {0} °C, got {1}",
+            MAX_TEMPERATURE_OFFSET_C, offset_celsius
+        )));
+    }
+    Ok(())
+}
+
+impl Measurement {
+    /// Copy of this reading in the base unit of its quantity, undoing any
+    /// SI prefix recorded in `metadata.unit_multiplier`, e.g. 12.5 in mA
+    /// becomes 0.0125 A. Readings already in base units are returned as
+    /// they are.
+    pub fn to_base_units(&self) -> Measurement {
+        let Some(multiplier) = self.metadata.unit_multiplier else {
+            return self.clone();
+        };
+        let scale = 10f64.powi(multiplier);
+        let mut base = self.rescaled(self.value * scale, self.unit.clone());
+        base.uncertainty = self.uncertainty.map(|u| u * scale);
+        base.metadata.unit_multiplier = None;
+        base
+    }
+
+    /// Convert a temperature reading between Celsius, Fahrenheit and Kelvin.
+    ///
+    /// Returns `None` if either unit is not a temperature. A reading within
+    /// rounding of absolute zero is clamped to it; one further below can
+    /// only come from a faulty sensor and is converted but marked `Invalid`.
+    pub fn convert_temperature(&self, target: Unit) -> Option<Measurement> {
+        let celsius = match self.unit {
+            Unit::Celsius => self.value,
+            Unit::Fahrenheit => (self.value - 32.0) * 5.0 / 9.0,
+            Unit::Kelvin => self.value + ABSOLUTE_ZERO_C,
+            _ => return None,
+        };
+        let below_absolute_zero = celsius < ABSOLUTE_ZERO_C - ABSOLUTE_ZERO_TOLERANCE;
+        let celsius = if below_absolute_zero {
+            celsius
+        } else {
+            celsius.max(ABSOLUTE_ZERO_C)
+        };
+        let value = match target {
+            Unit::Celsius => celsius,
+            Unit::Fahrenheit => celsius * 9.0 / 5.0 + 32.0,
+            Unit::Kelvin => celsius - ABSOLUTE_ZERO_C,
+            _ => return None,
+        };
+        // Kelvin and Celsius degrees are 9/5 of a Fahrenheit degree.
+        let degree = |unit: &Unit| {
+            if *unit == Unit::Fahrenheit {
+                5.0 / 9.0
+            } else {
+                1.0
+            }
+        };
+        let scale = degree(&self.unit) / degree(&target);
+        let mut converted = self.rescaled(value, target);
+        converted.uncertainty = self.uncertainty.map(|u| u * scale);
+        if below_absolute_zero && self.state == MeasurementState::Normal {
+            converted.state = MeasurementState::Invalid;
+        }
+        Some(converted)
+    }
+
+    /// Express a conductance reading as the equivalent resistance.
+    ///
+    /// Zero conductance is an open circuit and is reported as an overload.
+    pub fn conductance_to_resistance(&self) -> Option<Measurement> {
+        if self.unit != Unit::Siemens {
+            return None;
+        }
+        if self.state == MeasurementState::Normal && self.value == 0.0 {
+            let mut open = self.rescaled(OVERLOAD_VALUE, Unit::Ohm);
+            open.state = MeasurementState::Overload;
+            open.uncertainty = None;
+            return Some(open);
+        }
+        let mut resistance = self.rescaled(1.0 / self.value, Unit::Ohm);
+        resistance.uncertainty = self.uncertainty.map(|u| u / (self.value * self.value));
+        Some(resistance)
+    }
+
+    /// Express an AC or DC voltage reading as the power in dBm it delivers
+    /// into `reference_ohms`.
+    ///
+    /// dBm is undefined for zero and negative voltages; those readings are
+    /// reported as a negative overload rather than minus infinity.
+    pub fn voltage_to_dbm(&self, reference_ohms: f64) -> Option<Measurement> {
+        if !matches!(self.unit, Unit::VoltAc | Unit::VoltDc) {
+            return None;
+        }
+        if self.state == MeasurementState::Normal && self.value <= 0.0 {
+            let mut undefined = self.rescaled(-OVERLOAD_VALUE, Unit::DecibelM);
+            undefined.state = MeasurementState::OverloadNegative;
+            undefined.uncertainty = None;
+            return Some(undefined);
+        }
+        let power = self.value * self.value / reference_ohms;
+        let mut dbm = self.rescaled(10.0 * (power / DBM_REFERENCE_WATTS).log10(), Unit::DecibelM);
+        dbm.uncertainty = self
+            .uncertainty
+            .map(|u| 20.0 / std::f64::consts::LN_10 * u / self.value);
+        Some(dbm)
+    }
+
+    /// Express a dBm reading as the AC voltage across `reference_ohms`
+    pub fn dbm_to_voltage(&self, reference_ohms: f64) -> Option<Measurement> {
+        if self.unit != Unit::DecibelM {
+            return None;
+        }
+        let power = DBM_REFERENCE_WATTS * 10f64.powf(self.value / 10.0);
+        let volts = (power * reference_ohms).sqrt();
+        let mut voltage = self.rescaled(volts, Unit::VoltAc);
+        voltage.uncertainty = self
+            .uncertainty
+            .map(|u| volts * std::f64::consts::LN_10 / 20.0 * u);
+        Some(voltage)
+    }
+
+    /// Check a reading is in `expected`, converting it when the units are
+    /// compatible (the other temperature scale, conductance for an expected
+    /// resistance, or voltage and dBm into `DEFAULT_DBM_REFERENCE_OHMS`)
+    pub fn expect_unit(self, expected: Unit) -> Result<Measurement> {
+        self.expect_unit_at(expected, DEFAULT_DBM_REFERENCE_OHMS)
+    }
+
+    /// Like `expect_unit`, converting between voltage and dBm into
+    /// `reference_ohms`
+    pub fn expect_unit_at(self, expected: Unit, reference_ohms: f64) -> Result<Measurement> {
+        if self.unit == expected {
+            return Ok(self);
+        }
+        let converted = match expected {
+            Unit::Celsius | Unit::Fahrenheit | Unit::Kelvin => {
+                self.convert_temperature(expected.clone())
+            }
+            Unit::Ohm => self.conductance_to_resistance(),
+            Unit::DecibelM => self.voltage_to_dbm(reference_ohms),
+            Unit::VoltAc => self.dbm_to_voltage(reference_ohms),
+            _ => None,
+        };
+        converted.ok_or(Error::UnexpectedUnit {
+            expected,
+            actual: self.unit,
+        })
+    }
+
+    /// Correct a temperature reading by `offset_celsius`, recording the
+    /// offset in the metadata. Other readings, and a zero offset, pass
+    /// through unchanged.
+    pub fn with_temperature_offset(self, offset_celsius: f64) -> Measurement {
+        let offset = match self.unit {
+            Unit::Celsius | Unit::Kelvin => offset_celsius,
+            Unit::Fahrenheit => offset_celsius * 9.0 / 5.0,
+            _ => return self,
+        };
+        if offset_celsius == 0.0 {
+            return self;
+        }
+        let mut corrected = self.rescaled(self.value + offset, self.unit.clone());
+        corrected.metadata.temperature_offset = Some(offset_celsius);
+        corrected
+    }
+
+    /// Negate a `Normal` reading, recording the inversion in the metadata.
+    /// Overload, blank and other states are left as they are.
+    pub fn with_sign_inverted(mut self) -> Measurement {
+        if self.state != MeasurementState::Normal {
+            return self;
+        }
+        // Negating zero would display as -0.
+        if self.value != 0.0 {
+            self.value = -self.value;
+        }
+        self.metadata.sign_inverted = true;
+        self
+    }
+
+    /// Express a reading in the unit of `reference` as relative dB,
+    /// recording the reference in the metadata. Other readings pass through
+    /// unchanged.
+    pub fn relative_to(self, reference: DbReference) -> Measurement {
+        if self.unit != reference.unit {
+            return self;
+        }
+        let mut relative = self.rescaled(self.value - reference.value, Unit::Decibel);
+        relative.metadata.db_reference = Some(reference);
+        relative
+    }
+
+    /// Copy of this measurement in `unit`, with `value` applied to `Normal` readings only
+    fn rescaled(&self, value: f64, unit: Unit) -> Measurement {
+        let mut converted = self.clone();
+        converted.unit = unit;
+        if self.state == MeasurementState::Normal {
+            converted.value = value;
+        }
+        converted
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::MeasurementAttribute;
+
+    fn reading(value: f64, unit: Unit) -> Measurement {
+        Measurement::new(
+            value,
+            unit,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        )
+    }
+
+    #[test]
+    fn celsius_to_fahrenheit() {
+        let warm = reading(25.0, Unit::Celsius).convert_temperature(Unit::Fahrenheit);
+        let warm = warm.unwrap();
+        assert!((warm.value - 77.0).abs() < 1e-9);
+        assert_eq!(warm.unit, Unit::Fahrenheit);
+        assert_eq!(warm.state, MeasurementState::Normal);
+        assert_eq!(warm.attribute, MeasurementAttribute::None);
+    }
+
+    #[test]
+    fn volts_do_not_convert_to_ohms() {
+        let volts = reading(1.0, Unit::VoltDc);
+        assert!(volts.convert_temperature(Unit::Ohm).is_none());
+        assert!(volts.clone().expect_unit(Unit::Ohm).is_err());
+    }
+
+    #[test]
+    fn scaled_reading_returns_to_base_units() {
+        let mut milliamps = reading(12.5, Unit::AmpDc);
+        milliamps.uncertainty = Some(0.1);
+        milliamps.metadata.unit_multiplier = Some(-3);
+
+        let amps = milliamps.to_base_units();
+        assert!((amps.value - 0.0125).abs() < 1e-12);
+        assert!((amps.uncertainty.unwrap() - 0.0001).abs() < 1e-12);
+        assert_eq!(amps.unit, Unit::AmpDc);
+        assert_eq!(amps.metadata.unit_multiplier, None);
+    }
+
+    #[test]
+    fn base_units_and_overloads_are_left_alone() {
+        let volts = reading(3.3, Unit::VoltDc).to_base_units();
+        assert_eq!(volts.value, 3.3);
+        assert_eq!(volts.unit, Unit::VoltDc);
+
+        let mut overload = reading(OVERLOAD_VALUE, Unit::Ohm);
+        overload.state = MeasurementState::Overload;
+        overload.metadata.unit_multiplier = Some(3);
+        assert_eq!(overload.to_base_units().value, OVERLOAD_VALUE);
+    }
+
+    #[test]
+    fn readings_in_the_reference_unit_become_relative_db() {
+        let reference = DbReference::capture(&reading(-10.0, Unit::DecibelM)).unwrap();
+
+        let relative = reading(-4.0, Unit::DecibelM).relative_to(reference.clone());
+        assert_eq!(relative.unit, Unit::Decibel);
+        assert_eq!(relative.value, 6.0);
+        assert_eq!(relative.metadata.db_reference, Some(reference.clone()));
+
+        let other = reading(1.0, Unit::VoltAc).relative_to(reference);
+        assert_eq!(other.unit, Unit::VoltAc);
+    }
+
+    #[test]
+    fn only_db_readings_can_be_a_reference() {
+        let error = DbReference::capture(&reading(1.0, Unit::VoltAc)).unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn volts_to_dbm_at_600_and_50_ohms() {
+        let audio = reading(0.7746, Unit::VoltAc).voltage_to_dbm(600.0).unwrap();
+        assert_eq!(audio.unit, Unit::DecibelM);
+        assert!(audio.value.abs() < 1e-3, "{}", audio.value);
+
+        let rf = reading(0.2236, Unit::VoltAc).voltage_to_dbm(50.0).unwrap();
+        assert!(rf.value.abs() < 1e-3, "{}", rf.value);
+
+        let back = rf.dbm_to_voltage(50.0).unwrap();
+        assert!((back.value - 0.2236).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_volts_is_not_minus_infinity_dbm() {
+        let zero = reading(0.0, Unit::VoltDc).voltage_to_dbm(600.0).unwrap();
+        assert_eq!(zero.state, MeasurementState::OverloadNegative);
+        assert_eq!(zero.value, -OVERLOAD_VALUE);
+    }
+
+    #[test]
+    fn absolute_zero_matches_across_scales() {
+        let kelvin = reading(ABSOLUTE_ZERO_C, Unit::Celsius)
+            .convert_temperature(Unit::Kelvin)
+            .unwrap();
+        assert_eq!(kelvin.value, 0.0);
+        assert_eq!(kelvin.state, MeasurementState::Normal);
+
+        let celsius = reading(0.0, Unit::Kelvin)
+            .convert_temperature(Unit::Celsius)
+            .unwrap();
+        assert_eq!(celsius.value, ABSOLUTE_ZERO_C);
+
+        let kelvin = reading(-459.67, Unit::Fahrenheit)
+            .convert_temperature(Unit::Kelvin)
+            .unwrap();
+        assert_eq!(kelvin.value, 0.0);
+        assert_eq!(kelvin.state, MeasurementState::Normal);
+
+        let fahrenheit = reading(0.0, Unit::Kelvin)
+            .convert_temperature(Unit::Fahrenheit)
+            .unwrap();
+        assert!((fahrenheit.value + 459.67).abs() < 1e-9);
+    }
+
+    #[test]
+    fn below_absolute_zero_is_invalid() {
+        let kelvin = reading(-1.0, Unit::Kelvin)
+            .convert_temperature(Unit::Celsius)
+            .unwrap();
+        assert_eq!(kelvin.state, MeasurementState::Invalid);
+        assert_eq!(kelvin.value, ABSOLUTE_ZERO_C - 1.0);
+    }
+}
@@ -0,0 +1,189 @@
+//! Accuracy specifications for attaching an uncertainty to readings
+//!
+//! Accuracy is specified as ±(percent of reading + counts), where a count
+//! is one step of the last displayed digit on the active range.
+
+use crate::device::{Measurement, MeasurementState, Unit};
+
+/// ±(`percent` of reading + `counts` of the last displayed digit)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Accuracy {
+    pub percent: f64,
+    pub counts: f64,
+}
+
+impl Accuracy {
+    pub const fn new(percent: f64, counts: f64) -> Self {
+        Self { percent, counts }
+    }
+
+    /// Uncertainty of `value` shown with a last digit worth `resolution`
+    pub fn uncertainty(self, value: f64, resolution: f64) -> f64 {
+        value.abs() * self.percent / 100.0 + self.counts * resolution
+    }
+}
+
+/// Accuracy on one range of a function
+struct RangeSpec {
+    unit: Unit,
+    /// Largest reading the range displays
+    full_scale: f64,
+    /// Value of the last displayed digit
+    resolution: f64,
+    accuracy: Accuracy,
+}
+
+const fn range(unit: Unit, full_scale: f64, resolution: f64, accuracy: Accuracy) -> RangeSpec {
+    RangeSpec {
+        unit,
+        full_scale,
+        resolution,
+        accuracy,
+    }
+}
+
+/// Published 289 specifications, which the 287 shares: one year, 18-28 °C,
+/// AC functions at 45 Hz-1 kHz and temperature without the probe. Ranges
+/// of each function are in ascending order.
+const FLUKE_289: &[RangeSpec] = &[
+    range(Unit::VoltDc, 0.05, 1e-6, Accuracy::new(0.1, 20.0)),
+    range(Unit::VoltDc, 0.5, 1e-5, Accuracy::new(0.025, 2.0)),
+    range(Unit::VoltDc, 5.0, 1e-4, Accuracy::new(0.025, 2.0)),
+    range(Unit::VoltDc, 50.0, 1e-3, Accuracy::new(0.025, 2.0)),
+    range(Unit::VoltDc, 500.0, 1e-2, Accuracy::new(0.025, 2.0)),
+    range(Unit::VoltDc, 1000.0, 1e-1, Accuracy::new(0.025, 2.0)),
+    range(Unit::VoltAc, 0.05, 1e-6, Accuracy::new(0.3, 25.0)),
+    range(Unit::VoltAc, 0.5, 1e-5, Accuracy::new(0.3, 25.0)),
+    range(Unit::VoltAc, 5.0, 1e-4, Accuracy::new(0.3, 25.0)),
+    range(Unit::VoltAc, 50.0, 1e-3, Accuracy::new(0.3, 25.0)),
+    range(Unit::VoltAc, 500.0, 1e-2, Accuracy::new(0.3, 25.0)),
+    range(Unit::VoltAc, 1000.0, 1e-1, Accuracy::new(0.3, 25.0)),
+    range(Unit::AmpDc, 500e-6, 1e-8, Accuracy::new(0.075, 20.0)),
+    range(Unit::AmpDc, 5e-3, 1e-7, Accuracy::new(0.075, 2.0)),
+    range(Unit::AmpDc, 50e-3, 1e-6, Accuracy::new(0.075, 20.0)),
+    range(Unit::AmpDc, 400e-3, 1e-5, Accuracy::new(0.075, 2.0)),
+    range(Unit::AmpDc, 5.0, 1e-4, Accuracy::new(0.3, 10.0)),
+    range(Unit::AmpDc, 10.0, 1e-3, Accuracy::new(0.3, 10.0)),
+    range(Unit::AmpAc, 500e-6, 1e-8, Accuracy::new(0.6, 20.0)),
+    range(Unit::AmpAc, 5e-3, 1e-7, Accuracy::new(0.6, 5.0)),
+    range(Unit::AmpAc, 50e-3, 1e-6, Accuracy::new(0.6, 20.0)),
+    range(Unit::AmpAc, 400e-3, 1e-5, Accuracy::new(0.6, 5.0)),
+    range(Unit::AmpAc, 5.0, 1e-4, Accuracy::new(0.8, 20.0)),
+    range(Unit::AmpAc, 10.0, 1e-3, Accuracy::new(0.8, 5.0)),
+    range(Unit::Ohm, 500.0, 1e-2, Accuracy::new(0.05, 20.0)),
+    range(Unit::Ohm, 5e3, 1e-1, Accuracy::new(0.05, 2.0)),
+    range(Unit::Ohm, 50e3, 1.0, Accuracy::new(0.05, 2.0)),
+    range(Unit::Ohm, 500e3, 10.0, Accuracy::new(0.05, 2.0)),
+    range(Unit::Ohm, 5e6, 100.0, Accuracy::new(0.15, 4.0)),
+    range(Unit::Ohm, 50e6, 1e3, Accuracy::new(1.5, 4.0)),
+    range(Unit::Ohm, 500e6, 1e4, Accuracy::new(3.0, 10.0)),
+    range(Unit::Farad, 10e-9, 1e-12, Accuracy::new(1.0, 5.0)),
+    range(Unit::Farad, 100e-9, 1e-11, Accuracy::new(1.0, 2.0)),
+    range(Unit::Farad, 1e-6, 1e-10, Accuracy::new(1.0, 2.0)),
+    range(Unit::Farad, 10e-6, 1e-9, Accuracy::new(1.0, 2.0)),
+    range(Unit::Farad, 100e-6, 1e-8, Accuracy::new(1.0, 2.0)),
+    range(Unit::Farad, 1e-3, 1e-7, Accuracy::new(1.0, 2.0)),
+    range(Unit::Farad, 10e-3, 1e-6, Accuracy::new(1.0, 2.0)),
+    range(Unit::Farad, 100e-3, 1e-5, Accuracy::new(2.0, 20.0)),
+    range(Unit::Hertz, 99.999, 1e-3, Accuracy::new(0.02, 1.0)),
+    range(Unit::Hertz, 999.99, 1e-2, Accuracy::new(0.005, 1.0)),
+    range(Unit::Hertz, 9999.9, 1e-1, Accuracy::new(0.005, 1.0)),
+    range(Unit::Hertz, 99.999e3, 1.0, Accuracy::new(0.005, 1.0)),
+    range(Unit::Hertz, 999.99e3, 10.0, Accuracy::new(0.005, 1.0)),
+    range(Unit::Celsius, 1350.0, 0.1, Accuracy::new(1.0, 10.0)),
+    range(Unit::Fahrenheit, 2462.0, 0.1, Accuracy::new(1.0, 18.0)),
+];
+
+/// Uncertainty of a Fluke 289/287 reading, or `None` for functions and
+/// states without a specification.
+///
+/// `resolution` is the last-digit value the meter reported (`QDDA`) and
+/// selects the active range. Without it the range is taken to be the
+/// smallest that holds the reading, as autoranging would select.
+pub fn fluke_uncertainty(measurement: &Measurement, resolution: Option<f64>) -> Option<f64> {
+    if measurement.state != MeasurementState::Normal {
+        return None;
+    }
+    let magnitude = measurement.value.abs();
+    let mut selected = None;
+    for spec in FLUKE_289
+        .iter()
+        .filter(|spec| spec.unit == measurement.unit)
+    {
+        selected = Some(spec);
+        let fits = match resolution {
+            // Allow for the reported resolution being a rounded power of ten.
+            Some(resolution) => spec.resolution >= resolution * (1.0 - 1e-6),
+            None => spec.full_scale >= magnitude,
+        };
+        if fits {
+            break;
+        }
+    }
+    let spec = selected?;
+    Some(
+        spec.accuracy
+            .uncertainty(measurement.value, resolution.unwrap_or(spec.resolution)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::MeasurementAttribute;
+
+    fn reading(value: f64, unit: Unit) -> Measurement {
+        Measurement::new(
+            value,
+            unit,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        )
+    }
+
+    fn assert_close(actual: Option<f64>, expected: f64) {
+        let actual = actual.expect("no uncertainty");
+        assert!(
+            (actual - expected).abs() < 1e-12,
+            "{} != {}",
+            actual,
+            expected
+        );
+    }
+
+    #[test]
+    fn autoranged_reading_uses_the_smallest_range_holding_it() {
+        // 5 V range: 0.025% of 1 V plus 2 counts of 0.1 mV
+        assert_close(
+            fluke_uncertainty(&reading(1.0, Unit::VoltDc), None),
+            0.00045,
+        );
+        // 500 Ω range: 0.05% of 100 Ω plus 20 counts of 10 mΩ
+        assert_close(fluke_uncertainty(&reading(100.0, Unit::Ohm), None), 0.25);
+    }
+
+    #[test]
+    fn reported_resolution_selects_the_range() {
+        // 50 V range: 0.025% of 1 V plus 2 counts of 1 mV
+        assert_close(
+            fluke_uncertainty(&reading(1.0, Unit::VoltDc), Some(1e-3)),
+            0.00225,
+        );
+    }
+
+    #[test]
+    fn readings_beyond_the_top_range_use_it() {
+        assert_close(
+            fluke_uncertainty(&reading(-1200.0, Unit::VoltDc), None),
+            1200.0 * 0.00025 + 0.2,
+        );
+    }
+
+    #[test]
+    fn unspecified_readings_have_no_uncertainty() {
+        let mut overload = reading(1.0, Unit::VoltDc);
+        overload.state = MeasurementState::Overload;
+        assert_eq!(fluke_uncertainty(&overload, None), None);
+        assert_eq!(fluke_uncertainty(&reading(50.0, Unit::Percent), None), None);
+    }
+}
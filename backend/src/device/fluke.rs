@@ -2,26 +2,266 @@
 //!
 //! Implements the serial communication protocol for Fluke 289 and 287 multimeters.
 
+use crate::device::function::{MeasurementFunction, SwitchPosition};
+use crate::device::wire::{self, Direction, WireTap};
 use crate::device::{
-    Device, DeviceInfo, DeviceType, Measurement, MeasurementAttribute, MeasurementState, Unit,
+    accuracy, map_open_error, require_capability, validate_raw_command, Capabilities, CommandInfo,
+    Device, DeviceInfo, DeviceSettings, DeviceType, DisplayData, DisplayReading, FrequencyFilter,
+    Measurement, MeasurementAttribute, MeasurementState, RecordingSummary, SavedSetupInfo,
+    SecondaryFunction, Unit, UnitCategory,
 };
 use crate::error::{Error, Result};
 use async_trait::async_trait;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
 use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::collections::HashMap;
 use std::io::{Read, Write};
-use std::sync::Arc;
+use std::sync::{Arc, LazyLock, Weak};
 use std::time::{Duration, Instant};
 use tokio::sync::Mutex;
 
 const ACK_TIMEOUT: Duration = Duration::from_secs(5);
 const PAYLOAD_IDLE_TIMEOUT: Duration = Duration::from_millis(750);
 const READ_BACKOFF: Duration = Duration::from_millis(10);
+/// Time given to a command that sends no ACK before the next one is written
+const NO_ACK_SETTLE: Duration = Duration::from_millis(100);
+/// Largest response accepted, well above the biggest legitimate reply (a
+/// recording dump); a longer one means a stuck meter or a noisy line
+const MAX_RESPONSE_BYTES: usize = 256 * 1024;
+/// Bytes of a truncated response kept in the error for diagnosis
+const TRUNCATED_RAW_BYTES: usize = 64;
+/// Number of setup memory slots on the 289/287
+const SETUP_SLOTS: usize = 5;
+/// Longest accepted pause between paced write chunks
+pub const MAX_WRITE_DELAY_MS: u64 = 100;
+/// Serial speed the meters use out of the box
+pub const DEFAULT_BAUD_RATE: u32 = 115_200;
+/// Standard serial speeds the meter can be set to
+pub const BAUD_RATES: [u32; 8] = [1200, 2400, 4800, 9600, 19200, 38400, 57600, 115_200];
+
+/// Check `baud` is a standard rate the meter can be set to
+pub fn validate_baud_rate(baud: u32) -> Result<()> {
+    if BAUD_RATES.contains(&baud) {
+        Ok(())
+    } else {
+        let rates: Vec<String> = BAUD_RATES.iter().map(u32::to_string).collect();
+        Err(Error::Config(format!(
+            "Unsupported baud rate {}, expected one of {}",
+            baud,
+            rates.join(", ")
+        )))
+    }
+}
+
+/// Pacing of command writes, for cables whose optocoupler drops bytes
+/// written back to back. The default writes each command at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct WritePacing {
+    /// Bytes written at a time
+    pub chunk_bytes: usize,
+    /// Pause between chunks; 0 writes the command at once
+    pub delay_ms: u64,
+}
+
+impl Default for WritePacing {
+    fn default() -> Self {
+        Self {
+            chunk_bytes: 1,
+            delay_ms: 0,
+        }
+    }
+}
+
+impl WritePacing {
+    pub fn validate(&self) -> Result<()> {
+        if self.chunk_bytes == 0 {
+            return Err(Error::Config(
+                "Write pacing chunk_bytes must be at least 1".to_string(),
+            ));
+        }
+        if self.delay_ms > MAX_WRITE_DELAY_MS {
+            return Err(Error::Config(format!(
+                "Write pacing delay_ms must be at most {}, got {}",
+                MAX_WRITE_DELAY_MS, self.delay_ms
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// How the meter answers a command
+#[derive(Debug, Clone, Copy)]
+struct CommandSpec {
+    /// Command word, the part before any space or comma
+    name: &'static str,
+    /// The meter answers with a CMD_ACK line. Commands without one return as
+    /// soon as they are written instead of waiting out `ACK_TIMEOUT`.
+    expects_ack: bool,
+    /// A payload line follows a successful ACK. Commands without one return
+    /// right after the ACK instead of waiting out `PAYLOAD_IDLE_TIMEOUT`.
+    expects_payload: bool,
+    description: &'static str,
+}
+
+impl CommandSpec {
+    const fn new(
+        name: &'static str,
+        expects_ack: bool,
+        expects_payload: bool,
+        description: &'static str,
+    ) -> Self {
+        Self {
+            name,
+            expects_ack,
+            expects_payload,
+            description,
+        }
+    }
+
+    /// Spec of `command`; unknown (raw) commands are assumed to ACK with a
+    /// possible payload
+    fn of(command: &str) -> Self {
+        let name = command
+            .split([' ', ','])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        COMMANDS
+            .iter()
+            .find(|spec| spec.name == name)
+            .copied()
+            .unwrap_or(Self::new("", true, true, ""))
+    }
+}
+
+/// Commands the backend sends and how the meter answers them. A command
+/// that some firmware executes without acknowledging gets `expects_ack:
+/// false`, so it no longer costs a full `ACK_TIMEOUT`. `RI` is one: the
+/// meter restarts to apply it and may not get its ACK out first.
+const COMMANDS: &[CommandSpec] = &[
+    CommandSpec::new(
+        "ID",
+        true,
+        true,
+        "Identify the meter: model, firmware version and serial number",
+    ),
+    CommandSpec::new("QM", true, true, "Query the primary measurement"),
+    CommandSpec::new(
+        "QDDA",
+        true,
+        true,
+        "Query all display data: functions, modes and every reading shown",
+    ),
+    CommandSpec::new(
+        "QMP",
+        true,
+        true,
+        "Query a meter property, e.g. `QMP CLOCK`",
+    ),
+    CommandSpec::new(
+        "QSSN",
+        true,
+        true,
+        "Query the name of a saved setup, e.g. `QSSN 0`",
+    ),
+    CommandSpec::new("DS", true, false, "Restore the default setup"),
+    CommandSpec::new("RI", false, false, "Reset the instrument"),
+    CommandSpec::new("RMP", true, false, "Reset the meter properties"),
+    CommandSpec::new(
+        "MP",
+        true,
+        false,
+        "Set a meter property, e.g. `MP CLOCK,{seconds}`",
+    ),
+    CommandSpec::new("RCLS", true, false, "Recall a saved setup, e.g. `RCLS 0`"),
+];
+
+/// The command table, for clients building raw commands
+pub fn commands() -> Vec<CommandInfo> {
+    COMMANDS
+        .iter()
+        .map(|spec| CommandInfo {
+            name: spec.name,
+            description: spec.description,
+            expects_ack: spec.expects_ack,
+            expects_payload: spec.expects_payload,
+        })
+        .collect()
+}
+
+/// Firmware revision as (major, minor), parsed from e.g. `V1.16`
+type FirmwareVersion = (u32, u32);
+
+/// Oldest firmware revision known to implement each optional feature
+const QDDA_MIN_VERSION: FirmwareVersion = (1, 10);
+const CLOCK_MIN_VERSION: FirmwareVersion = (1, 0);
+const SETUP_MEMORY_MIN_VERSION: FirmwareVersion = (1, 16);
+
+/// QDDA reading IDs of the documented display areas. A temperature reading
+/// under any other ID is taken as the cold-junction temperature.
+const DISPLAY_AREAS: &[&str] = &[
+    "LIVE",
+    "PRIMARY",
+    "SECONDARY",
+    "REL_LIVE",
+    "BARGRAPH",
+    "MINIMUM",
+    "MAXIMUM",
+    "AVERAGE",
+    "REL_REFERENCE",
+    "DB_REF",
+    "TEMP_OFFSET",
+];
+
+type PortSlot = Mutex<Option<Box<dyn SerialPort>>>;
+type SharedPort = Arc<PortSlot>;
+
+/// Ports opened by addressed devices, by name. Devices on one bus write
+/// through the same handle, and its lock keeps their exchanges apart.
+static BUS_PORTS: LazyLock<std::sync::Mutex<HashMap<String, Weak<PortSlot>>>> =
+    LazyLock::new(Default::default);
+
+/// The open port of another addressed device on `port_name`, if any
+fn bus_port(port_name: &str) -> Option<SharedPort> {
+    let ports = BUS_PORTS.lock().unwrap_or_else(|e| e.into_inner());
+    ports.get(port_name).and_then(Weak::upgrade)
+}
+
+fn register_bus_port(port_name: &str, port: &SharedPort) {
+    let mut ports = BUS_PORTS.lock().unwrap_or_else(|e| e.into_inner());
+    ports.retain(|_, port| port.strong_count() > 0);
+    ports.insert(port_name.to_string(), Arc::downgrade(port));
+}
 
 /// Fluke device implementation
 pub struct FlukeDevice {
     device_type: DeviceType,
     port_name: Option<String>,
-    port: Arc<Mutex<Option<Box<dyn SerialPort>>>>,
+    port: SharedPort,
+    /// Set by `connect`, cleared by `disconnect` or when the port fails
+    /// outright, e.g. because the USB cable was pulled
+    connected: bool,
+    /// Bus address prefixed to every command; `None` for a meter that has
+    /// the port to itself
+    address: Option<u8>,
+    /// Whether the firmware answers QDDA; `None` until first tried
+    qdda_supported: Option<bool>,
+    /// Whether QDDA includes a cold-junction temperature in temperature
+    /// mode; `None` until first tried
+    reference_temperature_reported: Option<bool>,
+    /// Bytes received for the most recent command, kept for parse diagnostics
+    last_response: Vec<u8>,
+    /// Firmware revision reported by `identify`
+    firmware_version: Option<FirmwareVersion>,
+    /// Unit of the most recent reading, for QM replies that omit it
+    last_unit: Option<Unit>,
+    /// Observer of the raw traffic, for protocol debugging
+    wire_tap: Option<WireTap>,
+    /// How commands are split up when written
+    write_pacing: WritePacing,
+    baud_rate: u32,
 }
 
 impl FlukeDevice {
@@ -31,27 +271,175 @@ impl FlukeDevice {
             device_type,
             port_name,
             port: Arc::new(Mutex::new(None)),
+            connected: false,
+            address: None,
+            qdda_supported: None,
+            reference_temperature_reported: None,
+            last_response: Vec::new(),
+            firmware_version: None,
+            last_unit: None,
+            wire_tap: None,
+            write_pacing: WritePacing::default(),
+            baud_rate: DEFAULT_BAUD_RATE,
         }
     }
 
-    /// Send a command and get the response
+    /// Address the meter behind a bus adapter, so several meters can share
+    /// one port
+    pub fn with_address(mut self, address: Option<u8>) -> Self {
+        self.address = address;
+        self
+    }
+
+    /// Pace command writes for a cable that drops bytes
+    pub fn with_write_pacing(mut self, write_pacing: WritePacing) -> Self {
+        self.write_pacing = write_pacing;
+        self
+    }
+
+    /// Open the port at `baud` instead of the default 115200, for a meter
+    /// or adapter set to another speed
+    pub fn with_baud_rate(mut self, baud: Option<u32>) -> Self {
+        self.baud_rate = baud.unwrap_or(DEFAULT_BAUD_RATE);
+        self
+    }
+
+    /// Parse a software version string such as `V1.16` or `1.16`
+    fn parse_firmware_version(version: &str) -> Option<FirmwareVersion> {
+        let digits = version.trim().trim_start_matches(['V', 'v']);
+        let (major, minor) = digits.split_once('.').unwrap_or((digits, "0"));
+        let minor: String = minor.chars().take_while(char::is_ascii_digit).collect();
+        Some((major.parse().ok()?, minor.parse().unwrap_or(0)))
+    }
+
+    /// Capabilities of a given firmware revision. Unknown revisions are
+    /// assumed to support everything; commands still fail safely at runtime.
+    fn capabilities_for(version: Option<FirmwareVersion>) -> Capabilities {
+        let Some(version) = version else {
+            return Capabilities::ALL;
+        };
+
+        Capabilities {
+            display_download: version >= QDDA_MIN_VERSION,
+            clock: version >= CLOCK_MIN_VERSION,
+            saved_setups: version >= SETUP_MEMORY_MIN_VERSION,
+        }
+    }
+
+    /// Send a command and get the response.
+    ///
+    /// A response that is not valid UTF-8 is a corrupt frame, most likely
+    /// line noise. The serial buffers are cleared and the command is sent
+    /// once more before giving up with a parse error carrying the raw bytes.
     async fn send_command_internal(&mut self, command: &str) -> Result<String> {
+        let mut raw = self.tracked_exchange(command).await?;
+        if std::str::from_utf8(&raw).is_err() {
+            tracing::warn!(command = %command, raw = ?raw, "Corrupt frame, retrying once");
+            self.clear_buffers().await;
+            raw = self.tracked_exchange(command).await?;
+        }
+
+        let response = String::from_utf8(raw).map_err(|e| {
+            Error::parse(format!(
+                "Corrupt frame: invalid UTF-8 after {} bytes",
+                e.utf8_error().valid_up_to()
+            ))
+            .with_raw(&self.last_response)
+        })?;
+
+        // Normalise by stripping carriage returns/newlines so the caller sees
+        // the ACK followed directly by any payload content.
+        let mut lines = response
+            .split('\r')
+            .map(|line| line.trim_end_matches('\n'))
+            .filter(|line| !line.is_empty());
+
+        let Some(ack_line) = lines.next() else {
+            if !CommandSpec::of(command).expects_ack {
+                return Ok(String::new());
+            }
+            return Err(Error::parse("Missing ACK").with_raw(&self.last_response));
+        };
+
+        let mut output = String::from(ack_line);
+        for line in lines {
+            output.push_str(line);
+        }
+
+        Ok(output)
+    }
+
+    /// `exchange`, marking the device disconnected when the port itself
+    /// fails. Timeouts and garbled replies leave the connection standing.
+    async fn tracked_exchange(&mut self, command: &str) -> Result<Vec<u8>> {
+        let result = self.exchange(command).await;
+        if let Err(error @ (Error::Io(_) | Error::Serial(_))) = &result {
+            tracing::warn!(command = %command, %error, "Serial port failed, marking disconnected");
+            self.connected = false;
+        }
+        result
+    }
+
+    /// Discard anything pending in the port's input and output buffers
+    async fn clear_buffers(&mut self) {
+        if let Some(port) = self.port.lock().await.as_mut() {
+            if let Err(error) = port.clear(ClearBuffer::All) {
+                tracing::warn!(%error, "Failed to clear serial buffers");
+            }
+        }
+    }
+
+    /// Write a command and collect the raw bytes of its ACK and payload
+    /// lines, also kept in `last_response`
+    async fn exchange(&mut self, command: &str) -> Result<Vec<u8>> {
         let mut port_guard = self.port.lock().await;
         let port = port_guard
             .as_mut()
             .ok_or_else(|| Error::Connection("Not connected".to_string()))?;
 
-    let command_bytes = format!("{}\r", command).into_bytes();
-    tracing::debug!(command = %command, bytes = ?command_bytes, "Sending command");
-    port.write_all(&command_bytes)?;
-    port.flush()?;
+        let command_bytes = match self.address {
+            Some(address) => format!("{}:{}\r", address, command),
+            None => format!("{}\r", command),
+        }
+        .into_bytes();
+        tracing::debug!(command = %command, bytes = ?command_bytes, "Sending command");
+        if self.write_pacing.delay_ms == 0 {
+            port.write_all(&command_bytes)?;
+            port.flush()?;
+            wire::observe(&self.wire_tap, Direction::Write, &command_bytes);
+        } else {
+            let delay = Duration::from_millis(self.write_pacing.delay_ms);
+            for (index, chunk) in command_bytes
+                .chunks(self.write_pacing.chunk_bytes)
+                .enumerate()
+            {
+                if index > 0 {
+                    tokio::time::sleep(delay).await;
+                }
+                // Flushed one chunk at a time so the pause reaches the wire.
+                port.write_all(chunk)?;
+                port.flush()?;
+                wire::observe(&self.wire_tap, Direction::Write, chunk);
+            }
+        }
+
+        let spec = CommandSpec::of(command);
+        self.last_response.clear();
+        if !spec.expects_ack {
+            // Give the meter time to act on the command, then drop any ACK
+            // it did send so the next command doesn't read it as its own.
+            tokio::time::sleep(NO_ACK_SETTLE).await;
+            if let Err(error) = port.clear(ClearBuffer::Input) {
+                tracing::warn!(%error, "Failed to clear serial input buffer");
+            }
+            return Ok(Vec::new());
+        }
 
         // Small delay for device to process
-    tokio::time::sleep(Duration::from_millis(50)).await;
+        tokio::time::sleep(Duration::from_millis(50)).await;
 
         // Read response, capturing both ACK line and optional payload line.
         let mut buffer = [0u8; 1024];
-        let mut response = String::new();
         let mut carriage_returns = 0usize;
         let mut ack_received = false;
         let mut last_activity = Instant::now();
@@ -60,17 +448,31 @@ impl FlukeDevice {
             match port.read(&mut buffer) {
                 Ok(bytes_read) if bytes_read > 0 => {
                     let raw_chunk = &buffer[..bytes_read];
-                    let chunk = String::from_utf8_lossy(raw_chunk);
-                    tracing::debug!(command = %command, raw = ?raw_chunk, chunk = %chunk, "Received serial chunk");
-                    carriage_returns += chunk.matches('\r').count();
-                    response.push_str(&chunk);
+                    self.last_response.extend_from_slice(raw_chunk);
+                    wire::observe(&self.wire_tap, Direction::Read, raw_chunk);
+                    tracing::debug!(command = %command, raw = ?raw_chunk, chunk = %String::from_utf8_lossy(raw_chunk), "Received serial chunk");
+                    carriage_returns += raw_chunk.iter().filter(|&&byte| byte == b'\r').count();
                     last_activity = Instant::now();
 
+                    if self.last_response.len() > MAX_RESPONSE_BYTES {
+                        tracing::warn!(command = %command, "Response exceeds {} bytes, discarding", MAX_RESPONSE_BYTES);
+                        if let Err(error) = port.clear(ClearBuffer::All) {
+                            tracing::warn!(%error, "Failed to clear serial buffers");
+                        }
+                        let received = self.last_response.len();
+                        self.last_response.truncate(TRUNCATED_RAW_BYTES);
+                        return Err(Error::parse(format!(
+                            "Response truncated: more than {} bytes received ({} so far)",
+                            MAX_RESPONSE_BYTES, received
+                        ))
+                        .with_raw(&self.last_response));
+                    }
+
                     // Most commands emit an ACK line (ending with CR) and for
                     // queries an additional payload line (ending with CR). We
                     // continue reading until we either receive both, or we've
                     // seen at least one CR and additional reads time out.
-                    if carriage_returns >= 2 {
+                    if carriage_returns >= 2 || (carriage_returns >= 1 && !spec.expects_payload) {
                         break;
                     }
                     ack_received = ack_received || carriage_returns >= 1;
@@ -94,33 +496,48 @@ impl FlukeDevice {
             tokio::time::sleep(READ_BACKOFF).await;
         }
 
-        // Normalise by stripping carriage returns/newlines so the caller sees
-        // the ACK followed directly by any payload content.
-        if response.is_empty() {
+        if self.last_response.is_empty() {
             return Err(Error::Timeout);
         }
 
-            let mut lines = response
-                .split('\r')
-                .map(|line| line.trim_end_matches('\n'))
-                .filter(|line| !line.is_empty());
+        Ok(self.last_response.clone())
+    }
 
-        let ack_line = lines
-            .next()
-            .ok_or_else(|| Error::Parse("Missing ACK".to_string()))?;
+    /// Check the ACK of `response` and parse its payload, attaching the raw
+    /// frame to any parse error
+    fn parse_payload<T>(
+        &self,
+        response: &str,
+        what: &str,
+        parse: impl FnOnce(&str) -> Result<T>,
+    ) -> Result<T> {
+        Self::parse_ack(response)
+            .and_then(|()| {
+                let payload = response
+                    .get(1..)
+                    .ok_or_else(|| Error::parse(format!("{} payload missing", what)))?;
+                parse(payload)
+            })
+            .map_err(|e| e.with_raw(&self.last_response))
+    }
 
-        let mut output = String::from(ack_line);
-        for line in lines {
-            output.push_str(line);
+    /// Look up the name stored in a setup slot, `None` if it is empty
+    async fn saved_setup_name(&mut self, index: usize) -> Result<Option<String>> {
+        let response = self
+            .send_command_internal(&format!("QSSN {}", index))
+            .await?;
+        match Self::parse_ack(&response) {
+            Err(Error::Device(_)) if response.starts_with('5') => Ok(None),
+            _ => self.parse_payload(&response, "Setup name", |payload| {
+                Ok(Some(payload.trim().trim_matches('"').to_string()))
+            }),
         }
-
-        Ok(output)
     }
 
     /// Parse command acknowledgment
     fn parse_ack(response: &str) -> Result<()> {
         if response.is_empty() {
-            return Err(Error::Parse("Empty response".to_string()));
+            return Err(Error::parse("Empty response"));
         }
 
         match response.chars().next().unwrap() {
@@ -128,69 +545,319 @@ impl FlukeDevice {
             '1' => Err(Error::InvalidCommand("Syntax error".to_string())),
             '2' => Err(Error::Device("Execution error".to_string())),
             '5' => Err(Error::Device("No data available".to_string())),
-            _ => Err(Error::Parse(format!(
+            _ => Err(Error::parse(format!(
                 "Unknown ACK code: {}",
                 response.chars().next().unwrap()
             ))),
         }
     }
 
-    /// Parse measurement from QM command response
+    /// Parse measurement from QM command response. Some firmware leaves the
+    /// unit blank or omits it; such readings get `Unit::Unknown`, as do
+    /// readings in a unit not recognised, which keep its token.
     fn parse_measurement(response: &str) -> Result<Measurement> {
         // Response format: ACK<CR> VALUE,UNIT,STATE,ATTRIBUTE<CR>
-        let parts: Vec<&str> = response.split(',').collect();
-        if parts.len() < 4 {
-            return Err(Error::Parse(
-                "Invalid measurement response format".to_string(),
+        let parts: Vec<&str> = response.split(',').map(str::trim).collect();
+        let (value, unit, state, attribute) = match parts[..] {
+            [value, unit, state, attribute, ..] => (value, unit, state, attribute),
+            [value, state, attribute] => (value, "", state, attribute),
+            _ => return Err(Error::parse("Invalid measurement response format")),
+        };
+
+        let value = Self::parse_value(value)?;
+        let state = Self::parse_state(state)?;
+        let attribute = Self::parse_attribute(attribute)?;
+
+        if unit.is_empty() {
+            return Ok(Measurement::new(
+                value,
+                Unit::Unknown(String::new()),
+                state,
+                attribute,
             ));
         }
+        Ok(Self::with_unit(value, unit, state, attribute))
+    }
+
+    /// Unit of the active function, for a QM reply that lacks one: the
+    /// primary display reading's if QDDA answers, else the last one seen
+    async fn infer_unit(&mut self) -> Unit {
+        if self.qdda_supported != Some(false) {
+            match self.send_command_internal("QDDA").await {
+                Ok(response) => {
+                    match self.parse_payload(&response, "Display", Self::parse_display_readings) {
+                        Ok(readings) => {
+                            self.qdda_supported = Some(true);
+                            if let Some(primary) =
+                                readings.iter().find(|r| r.reading_id == "PRIMARY")
+                            {
+                                return primary.measurement.unit.clone();
+                            }
+                        }
+                        Err(Error::InvalidCommand(_)) => self.qdda_supported = Some(false),
+                        Err(error) => {
+                            tracing::debug!(%error, "Inferring the unit from QDDA failed")
+                        }
+                    }
+                }
+                Err(error) => tracing::debug!(%error, "Inferring the unit from QDDA failed"),
+            }
+        }
+        self.last_unit
+            .clone()
+            .unwrap_or(Unit::Unknown(String::new()))
+    }
+
+    /// Cold-junction temperature in °C from QDDA, for firmware that reports
+    /// one alongside a temperature reading
+    async fn reference_temperature(&mut self) -> Option<f64> {
+        if self.qdda_supported == Some(false) || self.reference_temperature_reported == Some(false)
+        {
+            return None;
+        }
+        let readings = match self.send_command_internal("QDDA").await {
+            Ok(response) => {
+                match self.parse_payload(&response, "Display", Self::parse_display_readings) {
+                    Ok(readings) => readings,
+                    Err(Error::InvalidCommand(_)) => {
+                        self.qdda_supported = Some(false);
+                        return None;
+                    }
+                    Err(error) => {
+                        tracing::debug!(%error, "Reading the cold-junction temperature failed");
+                        return None;
+                    }
+                }
+            }
+            Err(error) => {
+                tracing::debug!(%error, "Reading the cold-junction temperature failed");
+                return None;
+            }
+        };
+        self.qdda_supported = Some(true);
+
+        let reference = readings.iter().find(|reading| {
+            !DISPLAY_AREAS.contains(&reading.reading_id.as_str())
+                && reading.measurement.unit.category() == UnitCategory::Temperature
+        });
+        self.reference_temperature_reported = Some(reference.is_some());
+        reference
+            .filter(|reading| reading.measurement.state == MeasurementState::Normal)
+            .and_then(|reading| reading.measurement.convert_temperature(Unit::Celsius))
+            .map(|celsius| celsius.value)
+    }
 
-        let value = parts[0]
-            .trim()
+    /// Parse a reading value in plain or exponent notation (`-0.0000`,
+    /// `1.234E-3`, `1.234e+3`).
+    ///
+    /// Negative zero is returned as zero so it never displays as `-0`.
+    /// Words Rust would also accept, such as `inf` or `NaN`, are rejected.
+    fn parse_value(raw: &str) -> Result<f64> {
+        let raw = raw.trim();
+        let numeric = raw
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'));
+        let value = raw
             .parse::<f64>()
-            .map_err(|_| Error::Parse(format!("Invalid measurement value: {}", parts[0])))?;
-
-        let unit = Self::parse_unit(parts[1].trim())?;
-        let state = Self::parse_state(parts[2].trim())?;
-        let attribute = Self::parse_attribute(parts[3].trim())?;
-
-        Ok(Measurement {
-            value,
-            unit,
-            state,
-            attribute,
-            timestamp: Some(chrono::Utc::now()),
-        })
+            .ok()
+            .filter(|_| numeric)
+            .ok_or_else(|| Error::parse(format!("Invalid measurement value: {}", raw)))?;
+
+        Ok(if value == 0.0 { 0.0 } else { value })
+    }
+
+    /// Parse the readings out of a QDDA response payload.
+    ///
+    /// The field count varies with the active function, so the response is
+    /// walked using the embedded mode and reading counts.
+    fn parse_display_readings(response: &str) -> Result<Vec<DisplayReading>> {
+        const READING_FIELDS: usize = 9;
+
+        let fields: Vec<&str> = response.split(',').map(str::trim).collect();
+        let field = |index: usize| {
+            fields
+                .get(index)
+                .copied()
+                .ok_or_else(|| Error::parse("Truncated QDDA response"))
+        };
+        let count = |index: usize| -> Result<usize> {
+            let raw = field(index)?;
+            raw.parse::<usize>()
+                .map_err(|_| Error::parse(format!("Invalid QDDA count: {}", raw)))
+        };
+
+        // primaryFunction, secondaryFunction, 4 x rangeData, lightningBolt,
+        // minMaxStartTime precede the mode count.
+        let secondary_function = Self::parse_secondary_function(field(1)?)?;
+        let mode_count = count(8)?;
+        let mut frequency_filter = FrequencyFilter::Off;
+        for index in 9..9 + mode_count {
+            if field(index)? == "LOW_PASS_FILTER" {
+                frequency_filter = FrequencyFilter::LowPass;
+            }
+        }
+        let reading_count_index = 9 + mode_count;
+        let reading_count = count(reading_count_index)?;
+
+        let mut readings = Vec::with_capacity(reading_count);
+        for reading in 0..reading_count {
+            let base = reading_count_index + 1 + reading * READING_FIELDS;
+            let value = Self::parse_value(field(base + 1)?)?;
+
+            let mut measurement = Self::with_unit(
+                value,
+                field(base + 2)?,
+                Self::parse_state(field(base + 6)?)?,
+                Self::parse_attribute(field(base + 7)?)?,
+            );
+            // The last displayed digit is worth 10^(unitMultiplier - decimals).
+            let resolution = field(base + 3)?
+                .parse::<i32>()
+                .ok()
+                .zip(field(base + 4)?.parse::<i32>().ok())
+                .map(|(multiplier, decimals)| 10f64.powi(multiplier - decimals));
+            measurement.uncertainty = accuracy::fluke_uncertainty(&measurement, resolution);
+            if measurement.unit == Unit::Hertz {
+                measurement.metadata.frequency_filter = Some(frequency_filter);
+            }
+            let reading_id = field(base)?;
+            if reading_id == "SECONDARY" && secondary_function != SecondaryFunction::None {
+                measurement.metadata.secondary_function = Some(secondary_function);
+            }
+
+            readings.push(DisplayReading {
+                reading_id: reading_id.to_string(),
+                measurement,
+            });
+        }
+
+        Ok(readings)
+    }
+
+    /// Parse the MIN MAX readings out of a QDDA response payload; `None`
+    /// while the meter isn't in MIN MAX mode
+    fn parse_recording_summary(response: &str) -> Result<Option<RecordingSummary>> {
+        let fields: Vec<&str> = response.split(',').map(str::trim).collect();
+        let mode_count = fields
+            .get(8)
+            .and_then(|raw| raw.parse::<usize>().ok())
+            .ok_or_else(|| Error::parse("Truncated QDDA response"))?;
+        // The specification spells the mode both with and without the last
+        // underscore.
+        let recording = fields
+            .iter()
+            .skip(9)
+            .take(mode_count)
+            .any(|mode| matches!(*mode, "MIN_MAX_AVG" | "MIN_MAX AVG"));
+        if !recording {
+            return Ok(None);
+        }
+
+        // minMaxStartTime is meter clock seconds with a millisecond fraction.
+        let start = fields
+            .get(7)
+            .and_then(|raw| raw.parse::<f64>().ok())
+            .ok_or_else(|| Error::parse("Invalid QDDA minMaxStartTime"))?;
+        let started_at = Self::meter_seconds_to_utc(start.trunc() as i64)?
+            + chrono::Duration::milliseconds((start.fract() * 1000.0).round() as i64);
+
+        let readings = Self::parse_display_readings(response)?;
+        let reading = |id: &str| {
+            readings
+                .iter()
+                .find(|reading| reading.reading_id == id)
+                .map(|reading| &reading.measurement)
+                .ok_or_else(|| Error::parse(format!("QDDA response lacks the {} reading", id)))
+        };
+        Ok(Some(RecordingSummary {
+            unit: reading("PRIMARY")?.unit.clone(),
+            minimum: reading("MINIMUM")?.value,
+            maximum: reading("MAXIMUM")?.value,
+            average: reading("AVERAGE")?.value,
+            started_at,
+            duration_secs: (Utc::now() - started_at).num_milliseconds().max(0) as f64 / 1000.0,
+            active: true,
+        }))
+    }
+
+    /// Parse the primaryFunction field of a QDDA response payload; `None`
+    /// while the dial is between positions (`LIMBO`)
+    fn parse_primary_function(response: &str) -> Result<Option<MeasurementFunction>> {
+        let function = response.split(',').next().unwrap_or_default().trim();
+        if function == "LIMBO" {
+            return Ok(None);
+        }
+        serde_json::from_value(serde_json::Value::String(function.to_string()))
+            .map(Some)
+            .map_err(|_| Error::parse(format!("Unknown primary function: {}", function)))
+    }
+
+    /// Parse the secondaryFunction field of a QDDA response
+    fn parse_secondary_function(function: &str) -> Result<SecondaryFunction> {
+        match function {
+            "NONE" => Ok(SecondaryFunction::None),
+            "HERTZ" => Ok(SecondaryFunction::Hertz),
+            "DUTY_CYCLE" => Ok(SecondaryFunction::DutyCycle),
+            "PULSE_WIDTH" => Ok(SecondaryFunction::PulseWidth),
+            "DBM" => Ok(SecondaryFunction::Dbm),
+            "DBV" => Ok(SecondaryFunction::Dbv),
+            "DBM_HERTZ" => Ok(SecondaryFunction::DbmHertz),
+            "DBV_HERTZ" => Ok(SecondaryFunction::DbvHertz),
+            "CREST_FACTOR" => Ok(SecondaryFunction::CrestFactor),
+            "PEAK_MIN_MAX" => Ok(SecondaryFunction::PeakMinMax),
+            _ => Err(Error::parse(format!(
+                "Unknown secondary function: {}",
+                function
+            ))),
+        }
     }
 
-    /// Parse unit string
-    fn parse_unit(unit_str: &str) -> Result<Unit> {
+    /// Parse a unit token, `None` for tokens not recognised. The
+    /// specification spells ohms `OHMS`, the meters `OHM`; both are
+    /// accepted.
+    fn parse_unit(unit_str: &str) -> Option<Unit> {
         match unit_str {
-            "NONE" => Ok(Unit::None),
-            "VDC" => Ok(Unit::VoltDc),
-            "VAC" => Ok(Unit::VoltAc),
-            "ADC" => Ok(Unit::AmpDc),
-            "AAC" => Ok(Unit::AmpAc),
-            "VAC_PLUS_DC" => Ok(Unit::VoltAcPlusDc),
-            "AAC_PLUS_DC" => Ok(Unit::AmpAcPlusDc),
-            "V" => Ok(Unit::Volt),
-            "A" => Ok(Unit::Amp),
-            "OHM" => Ok(Unit::Ohm),
-            "S" => Ok(Unit::Siemens),
-            "Hz" => Ok(Unit::Hertz),
-            "SEC" => Ok(Unit::Second),
-            "F" => Ok(Unit::Farad),
-            "CEL" => Ok(Unit::Celsius),
-            "FAR" => Ok(Unit::Fahrenheit),
-            "PCT" => Ok(Unit::Percent),
-            "dBm" => Ok(Unit::DecibelM),
-            "dBV" => Ok(Unit::DecibelV),
-            "dB" => Ok(Unit::Decibel),
-            "CREST_FACTOR" => Ok(Unit::CrestFactor),
-            _ => Err(Error::Parse(format!("Unknown unit: {}", unit_str))),
+            "NONE" => Some(Unit::None),
+            "VDC" => Some(Unit::VoltDc),
+            "VAC" => Some(Unit::VoltAc),
+            "ADC" => Some(Unit::AmpDc),
+            "AAC" => Some(Unit::AmpAc),
+            "VAC_PLUS_DC" => Some(Unit::VoltAcPlusDc),
+            "AAC_PLUS_DC" => Some(Unit::AmpAcPlusDc),
+            "V" => Some(Unit::Volt),
+            "A" => Some(Unit::Amp),
+            "OHM" | "OHMS" => Some(Unit::Ohm),
+            "S" => Some(Unit::Siemens),
+            "Hz" => Some(Unit::Hertz),
+            "SEC" => Some(Unit::Second),
+            "F" => Some(Unit::Farad),
+            "CEL" => Some(Unit::Celsius),
+            "FAR" => Some(Unit::Fahrenheit),
+            "PCT" => Some(Unit::Percent),
+            "dBm" => Some(Unit::DecibelM),
+            "dBV" => Some(Unit::DecibelV),
+            "dB" => Some(Unit::Decibel),
+            "CREST_FACTOR" => Some(Unit::CrestFactor),
+            _ => None,
         }
     }
 
+    /// A reading in the unit `unit_str`. A token not recognised gives
+    /// `Unit::Unknown` carrying the token, rather than failing the whole
+    /// reading.
+    fn with_unit(
+        value: f64,
+        unit_str: &str,
+        state: MeasurementState,
+        attribute: MeasurementAttribute,
+    ) -> Measurement {
+        let unit = Self::parse_unit(unit_str).unwrap_or_else(|| {
+            tracing::warn!(unit = %unit_str, "Unrecognised unit, reporting it as unknown");
+            Unit::Unknown(unit_str.to_string())
+        });
+        Measurement::new(value, unit, state, attribute)
+    }
+
     /// Parse state string
     fn parse_state(state_str: &str) -> Result<MeasurementState> {
         match state_str {
@@ -201,10 +868,33 @@ impl FlukeDevice {
             "OL_MINUS" => Ok(MeasurementState::OverloadNegative),
             "OPEN_TC" => Ok(MeasurementState::OpenThermocouple),
             "DISCHARGE" => Ok(MeasurementState::Discharge),
-            _ => Err(Error::Parse(format!("Unknown state: {}", state_str))),
+            _ => Err(Error::parse(format!("Unknown state: {}", state_str))),
         }
     }
 
+    /// Convert a meter clock value to UTC.
+    ///
+    /// The meter keeps local wall-clock time and reports it as seconds since
+    /// the epoch as though that wall-clock time were UTC. We interpret it in
+    /// the host's local timezone, which assumes the meter was set from a host
+    /// in the same zone.
+    fn meter_seconds_to_utc(seconds: i64) -> Result<DateTime<Utc>> {
+        let naive = DateTime::from_timestamp(seconds, 0)
+            .map(|dt| dt.naive_utc())
+            .ok_or_else(|| Error::parse(format!("Invalid clock value: {}", seconds)))?;
+        Local
+            .from_local_datetime(&naive)
+            .earliest()
+            .map(|local| local.with_timezone(&Utc))
+            .ok_or_else(|| Error::parse(format!("Nonexistent local time: {}", naive)))
+    }
+
+    /// Convert a UTC time into the meter's local wall-clock seconds
+    fn utc_to_meter_seconds(time: DateTime<Utc>) -> i64 {
+        let local: NaiveDateTime = time.with_timezone(&Local).naive_local();
+        local.and_utc().timestamp()
+    }
+
     /// Parse attribute string
     fn parse_attribute(attr_str: &str) -> Result<MeasurementAttribute> {
         match attr_str {
@@ -217,7 +907,7 @@ impl FlukeDevice {
             "NEGATIVE_EDGE" => Ok(MeasurementAttribute::NegativeEdge),
             "POSITIVE_EDGE" => Ok(MeasurementAttribute::PositiveEdge),
             "HIGH_CURRENT" => Ok(MeasurementAttribute::HighCurrent),
-            _ => Err(Error::Parse(format!("Unknown attribute: {}", attr_str))),
+            _ => Err(Error::parse(format!("Unknown attribute: {}", attr_str))),
         }
     }
 }
@@ -228,9 +918,29 @@ impl Device for FlukeDevice {
         self.device_type
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Self::capabilities_for(self.firmware_version)
+    }
+
+    fn set_wire_tap(&mut self, tap: WireTap) {
+        self.wire_tap = Some(tap);
+    }
+
     async fn connect(&mut self) -> Result<()> {
+        if self.address.is_some() && self.port.lock().await.is_none() {
+            if let Some(port) = self.port_name.as_deref().and_then(bus_port) {
+                self.port = port;
+                self.connected = true;
+                tracing::info!(address = ?self.address, "Joined shared bus");
+                return Ok(());
+            }
+        }
+
         let mut port_guard = self.port.lock().await;
-        if port_guard.is_some() {
+        if let Some(port) = port_guard.as_mut() {
+            // An open port may still run at the speed it was opened with.
+            port.set_baud_rate(self.baud_rate)?;
+            self.connected = true;
             return Ok(());
         }
 
@@ -239,13 +949,14 @@ impl Device for FlukeDevice {
             .as_ref()
             .ok_or_else(|| Error::Config("No port specified".to_string()))?;
 
-        let mut port = serialport::new(port_name, 115_200)
+        let mut port = serialport::new(port_name, self.baud_rate)
             .data_bits(DataBits::Eight)
             .parity(Parity::None)
             .stop_bits(StopBits::One)
             .flow_control(FlowControl::None)
             .timeout(Duration::from_millis(1000))
-            .open()?;
+            .open()
+            .map_err(|e| map_open_error(port_name, e))?;
 
         if let Err(error) = port.write_data_terminal_ready(true) {
             tracing::warn!(%error, "Failed to assert DTR line");
@@ -260,69 +971,794 @@ impl Device for FlukeDevice {
         tokio::time::sleep(Duration::from_millis(150)).await;
 
         *port_guard = Some(port);
+        if self.address.is_some() {
+            register_bus_port(port_name, &self.port);
+        }
+        self.connected = true;
 
-        tracing::info!("Connected to Fluke device on port {}", port_name);
+        tracing::info!(
+            "Connected to Fluke device on port {} at {} baud",
+            port_name,
+            self.baud_rate
+        );
         Ok(())
     }
 
     async fn disconnect(&mut self) -> Result<()> {
+        self.connected = false;
         let mut port_guard = self.port.lock().await;
         if port_guard.is_none() {
             return Ok(());
         }
 
-        *port_guard = None;
+        if self.address.is_some() {
+            // Leave the bus; the port closes with the last device on it.
+            drop(port_guard);
+            self.port = Arc::new(Mutex::new(None));
+        } else {
+            *port_guard = None;
+        }
 
         tracing::info!("Disconnected from Fluke device");
         Ok(())
     }
 
     fn is_connected(&self) -> bool {
-        // For simplicity, we'll assume we're connected if we have a port
-        // In a more robust implementation, we'd track connection state separately
-        true // TODO: Implement proper connection state tracking
+        self.connected
     }
 
     async fn identify(&mut self) -> Result<DeviceInfo> {
         let response = self.send_command_internal("ID").await?;
-        Self::parse_ack(&response)?;
-
-        // Parse identification string
-        // Format: FLUKE 289,V1.00,95081087
-        let payload = response
-            .get(1..)
-            .ok_or_else(|| Error::Parse("Identification payload missing".to_string()))?;
-        let parts: Vec<&str> = payload.split(',').collect();
-        if parts.len() < 3 {
-            return Err(Error::Parse("Invalid identification response".to_string()));
-        }
-
-        let model = parts[0].trim().to_string();
-        let software_version = parts[1].trim().to_string();
-        let serial_number = parts[2].trim().to_string();
-
-        Ok(DeviceInfo {
-            model,
-            serial_number,
-            software_version,
-        })
+        let info = self.parse_payload(&response, "Identification", |payload| {
+            // Parse identification string
+            // Format: FLUKE 289,V1.00,95081087
+            let parts: Vec<&str> = payload.split(',').collect();
+            if parts.len() < 3 {
+                return Err(Error::parse("Invalid identification response"));
+            }
+
+            Ok(DeviceInfo {
+                model: parts[0].trim().to_string(),
+                serial_number: parts[2].trim().to_string(),
+                software_version: parts[1].trim().to_string(),
+            })
+        })?;
+
+        self.firmware_version = Self::parse_firmware_version(&info.software_version);
+        if self.firmware_version.is_none() {
+            tracing::warn!(
+                version = %info.software_version,
+                "Unrecognised firmware version, assuming all capabilities"
+            );
+        }
+        if !self.capabilities().display_download {
+            self.qdda_supported = Some(false);
+        }
+
+        Ok(info)
     }
 
     async fn get_measurement(&mut self) -> Result<Measurement> {
         let response = self.send_command_internal("QM").await?;
-        Self::parse_ack(&response)?;
-        let payload = response
-            .get(1..)
-            .ok_or_else(|| Error::Parse("Measurement payload missing".to_string()))?;
-        Self::parse_measurement(payload)
+        let mut measurement =
+            self.parse_payload(&response, "Measurement", Self::parse_measurement)?;
+        match &measurement.unit {
+            Unit::Unknown(raw) if raw.is_empty() => {
+                measurement.unit = self.infer_unit().await;
+                tracing::debug!(unit = ?measurement.unit, "QM reply without a unit");
+            }
+            Unit::Unknown(_) => {}
+            unit => self.last_unit = Some(unit.clone()),
+        }
+        measurement.uncertainty = accuracy::fluke_uncertainty(&measurement, None);
+        if measurement.unit.category() == UnitCategory::Temperature {
+            measurement.metadata.reference_temperature = self.reference_temperature().await;
+        }
+        Ok(measurement)
+    }
+
+    async fn get_all_measurements(&mut self) -> Result<Vec<DisplayReading>> {
+        if self.qdda_supported != Some(false) {
+            let response = self.send_command_internal("QDDA").await?;
+            match Self::parse_ack(&response).map_err(|e| e.with_raw(&self.last_response)) {
+                Ok(()) => {
+                    self.qdda_supported = Some(true);
+                    let readings =
+                        self.parse_payload(&response, "Display", Self::parse_display_readings)?;
+                    if let Some(primary) = readings.iter().find(|r| r.reading_id == "PRIMARY") {
+                        self.last_unit = Some(primary.measurement.unit.clone());
+                    }
+                    return Ok(readings);
+                }
+                Err(Error::InvalidCommand(_)) => {
+                    tracing::info!("QDDA not supported by this firmware, falling back to QM");
+                    self.qdda_supported = Some(false);
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(vec![DisplayReading {
+            reading_id: "PRIMARY".to_string(),
+            measurement: self.get_measurement().await?,
+        }])
+    }
+
+    async fn get_full_display(&mut self) -> Result<DisplayData> {
+        require_capability(
+            self.capabilities().display_download && self.qdda_supported != Some(false),
+            "Full display download",
+        )?;
+        let response = self.send_command_internal("QDDA").await?;
+        let readings = self.parse_payload(&response, "Display", Self::parse_display_readings)?;
+        self.qdda_supported = Some(true);
+        let display = DisplayData::from_readings(readings)?;
+        self.last_unit = Some(display.primary.unit.clone());
+        Ok(display)
     }
 
     async fn reset(&mut self) -> Result<()> {
-        let response = self.send_command_internal("RI").await?;
-        Self::parse_ack(&response)
+        // RI is sent without waiting for an ACK, so there is none to check.
+        self.send_command_internal("RI").await.map(drop)
     }
 
     async fn send_command(&mut self, command: &str) -> Result<String> {
+        validate_raw_command(command)?;
         self.send_command_internal(command).await
     }
+
+    async fn get_settings(&mut self) -> Result<DeviceSettings> {
+        // The filter state is only visible as a QDDA measurement mode while
+        // a frequency reading is on the display.
+        if self.qdda_supported == Some(false) {
+            return Ok(DeviceSettings::default());
+        }
+        let readings = self.get_all_measurements().await?;
+        Ok(DeviceSettings {
+            frequency_filter: readings
+                .iter()
+                .find_map(|reading| reading.measurement.metadata.frequency_filter),
+            // Without a SECONDARY reading the secondary display is off.
+            secondary_function: (self.qdda_supported == Some(true)).then(|| {
+                readings
+                    .iter()
+                    .find_map(|reading| reading.measurement.metadata.secondary_function)
+                    .unwrap_or_default()
+            }),
+            ..DeviceSettings::default()
+        })
+    }
+
+    async fn set_frequency_filter(&mut self, _filter: FrequencyFilter) -> Result<()> {
+        Err(Error::Unsupported(
+            "The 289/287 has no known remote command for the input filter; \
+             select it on the meter's front panel"
+                .to_string(),
+        ))
+    }
+
+    async fn available_functions(&mut self) -> Result<Vec<MeasurementFunction>> {
+        require_capability(
+            self.capabilities().display_download && self.qdda_supported != Some(false),
+            "Querying the dial position",
+        )?;
+        let response = self.send_command_internal("QDDA").await?;
+        let function = self.parse_payload(&response, "Display", Self::parse_primary_function)?;
+        Ok(function
+            .map(|function| SwitchPosition::of(function).functions().to_vec())
+            .unwrap_or_default())
+    }
+
+    async fn start_recording(&mut self) -> Result<()> {
+        Err(Error::Unsupported(
+            "The 289/287 has no known remote command for MIN MAX recording; \
+             press MIN MAX on the meter's front panel"
+                .to_string(),
+        ))
+    }
+
+    async fn stop_recording(&mut self) -> Result<()> {
+        Err(Error::Unsupported(
+            "The 289/287 has no known remote command for MIN MAX recording; \
+             leave it with the meter's front panel"
+                .to_string(),
+        ))
+    }
+
+    async fn set_hold(&mut self, _enabled: bool) -> Result<()> {
+        Err(Error::Unsupported(
+            "The 289/287 has no known remote command for HOLD; \
+             press HOLD on the meter's front panel"
+                .to_string(),
+        ))
+    }
+
+    async fn get_recording_summary(&mut self) -> Result<RecordingSummary> {
+        require_capability(
+            self.capabilities().display_download && self.qdda_supported != Some(false),
+            "Reading the MIN MAX recording",
+        )?;
+        let response = self.send_command_internal("QDDA").await?;
+        self.parse_payload(&response, "Display", Self::parse_recording_summary)?
+            .ok_or_else(|| Error::Device("The meter is not in MIN MAX mode".to_string()))
+    }
+
+    async fn list_saved_setups(&mut self) -> Result<Vec<SavedSetupInfo>> {
+        require_capability(self.capabilities().saved_setups, "Setup memory")?;
+        let mut setups = Vec::with_capacity(SETUP_SLOTS);
+        for index in 0..SETUP_SLOTS {
+            setups.push(SavedSetupInfo {
+                index,
+                name: self.saved_setup_name(index).await?,
+            });
+        }
+        Ok(setups)
+    }
+
+    async fn recall_setup(&mut self, index: usize) -> Result<()> {
+        require_capability(self.capabilities().saved_setups, "Setup memory")?;
+        if index >= SETUP_SLOTS {
+            return Err(Error::Config(format!(
+                "Setup slot {} does not exist (0-{})",
+                index,
+                SETUP_SLOTS - 1
+            )));
+        }
+        if self.saved_setup_name(index).await?.is_none() {
+            return Err(Error::Device(format!("Setup slot {} is empty", index)));
+        }
+
+        let response = self
+            .send_command_internal(&format!("RCLS {}", index))
+            .await?;
+        Self::parse_ack(&response).map_err(|e| e.with_raw(&self.last_response))
+    }
+
+    async fn get_clock(&mut self) -> Result<DateTime<Utc>> {
+        require_capability(self.capabilities().clock, "Reading the clock")?;
+        let response = self.send_command_internal("QMP CLOCK").await?;
+        self.parse_payload(&response, "Clock", |payload| {
+            let seconds = payload
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| Error::parse(format!("Invalid clock value: {}", payload)))?;
+            Self::meter_seconds_to_utc(seconds as i64)
+        })
+    }
+
+    async fn set_clock(&mut self, time: DateTime<Utc>) -> Result<()> {
+        require_capability(self.capabilities().clock, "Setting the clock")?;
+        let command = format!("MP CLOCK,{}", Self::utc_to_meter_seconds(time));
+        let response = self.send_command_internal(&command).await?;
+        Self::parse_ack(&response).map_err(|e| e.with_raw(&self.last_response))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::test_port::{Reply, ScriptedPort};
+    use crate::device::DisplayRole;
+
+    /// A connected 289 talking to `port`
+    fn device_on(port: &ScriptedPort) -> FlukeDevice {
+        let mut device = FlukeDevice::new(DeviceType::Fluke289, Some("scripted".to_string()));
+        device.port = Arc::new(Mutex::new(Some(Box::new(port.clone()))));
+        device.connected = true;
+        device
+    }
+
+    #[test]
+    fn only_standard_baud_rates_are_accepted() {
+        for baud in BAUD_RATES {
+            assert!(validate_baud_rate(baud).is_ok(), "{}", baud);
+        }
+        for baud in [0, 14_400, 230_400] {
+            let error = validate_baud_rate(baud).unwrap_err();
+            assert!(matches!(error, Error::Config(_)), "{:?}", error);
+        }
+    }
+
+    /// The MIN MAX example of the specification, on one line
+    const QDDA_MIN_MAX: &str =
+        "MV_AC,PEAK_MIN_MAX,AUTO,VAC,50,-3,OFF,1197309132.612,1,MIN_MAX AVG,5,\
+        LIVE,0.00515,VAC,-3,2,5,NORMAL,NONE,1197309141.806,\
+        PRIMARY,0.00515,VAC,-3,2,5,NORMAL,NONE,1197309141.806,\
+        MINIMUM,-0.0211,V,-3,2,5,NORMAL,NONE,1197309133.616,\
+        MAXIMUM,0.03055,V,-3,2,5,NORMAL,NONE,1197309133.366,\
+        AVERAGE,0.00529,VAC,-3,2,5,NORMAL,NONE,1197309141.806";
+
+    #[test]
+    fn full_display_splits_primary_from_sub_displays() {
+        let readings = FlukeDevice::parse_display_readings(QDDA_MIN_MAX).unwrap();
+        let display = DisplayData::from_readings(readings).unwrap();
+
+        assert_eq!(display.primary.value, 0.00515);
+        assert_eq!(display.primary.unit, Unit::VoltAc);
+        let roles: Vec<_> = display.auxiliary.iter().map(|a| a.role.clone()).collect();
+        assert_eq!(
+            roles,
+            [
+                DisplayRole::Live,
+                DisplayRole::Minimum,
+                DisplayRole::Maximum,
+                DisplayRole::Average
+            ]
+        );
+        assert_eq!(display.auxiliary[1].measurement.value, -0.0211);
+    }
+
+    #[test]
+    fn full_display_tolerates_a_shorter_reply() {
+        let response = "MV_AC,NONE,AUTO,VAC,50,-3,OFF,0.000,0,1,\
+            PRIMARY,0.005029,VAC,-3,3,5,NORMAL,NONE,1197308998.282";
+        let readings = FlukeDevice::parse_display_readings(response).unwrap();
+        let display = DisplayData::from_readings(readings).unwrap();
+
+        assert_eq!(display.primary.value, 0.005029);
+        assert!(display.auxiliary.is_empty());
+    }
+
+    #[tokio::test]
+    async fn no_ack_command_does_not_wait_for_the_ack_timeout() {
+        let port = ScriptedPort::new([Reply::Silent]);
+        let mut device = device_on(&port);
+
+        let started = Instant::now();
+        device.reset().await.unwrap();
+
+        assert!(started.elapsed() < ACK_TIMEOUT / 2);
+        assert_eq!(port.written(), "RI\r");
+    }
+
+    #[tokio::test]
+    async fn late_ack_of_a_no_ack_command_is_discarded() {
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"0\r".to_vec()),
+            Reply::Bytes(b"0\rFLUKE 289,V1.16,12345678\r".to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        assert_eq!(device.send_command_internal("RI").await.unwrap(), "");
+        assert_eq!(
+            device.send_command_internal("ID").await.unwrap(),
+            "0FLUKE 289,V1.16,12345678"
+        );
+    }
+
+    #[test]
+    fn unrecognised_unit_token_is_kept() {
+        for token in ["VAC_HF", "dBm_600", "PCT_DUTY"] {
+            let reading = format!("1.5,{},NORMAL,NONE", token);
+            let measurement = FlukeDevice::parse_measurement(&reading).unwrap();
+            assert_eq!(measurement.unit, Unit::Unknown(token.to_string()));
+            assert_eq!(measurement.value, 1.5);
+            assert_eq!(measurement.unit.symbol(), token);
+        }
+    }
+
+    #[test]
+    fn both_spellings_of_ohms_are_recognised() {
+        for token in ["OHM", "OHMS"] {
+            let reading = format!("1000,{},NORMAL,NONE", token);
+            let measurement = FlukeDevice::parse_measurement(&reading).unwrap();
+            assert_eq!(measurement.unit, Unit::Ohm);
+        }
+    }
+
+    #[test]
+    fn blank_unit_is_unknown_without_a_token() {
+        let measurement = FlukeDevice::parse_measurement("1.234,,NORMAL,NONE").unwrap();
+        assert_eq!(measurement.unit, Unit::Unknown(String::new()));
+        assert_eq!(measurement.unit.symbol(), "UNKNOWN");
+    }
+
+    #[test]
+    fn unknown_unit_serializes_with_its_token() {
+        let json = serde_json::to_value(Unit::Unknown("VAC_HF".to_string())).unwrap();
+        assert_eq!(json, serde_json::json!({ "Unknown": "VAC_HF" }));
+        assert_eq!(serde_json::to_value(Unit::VoltDc).unwrap(), "VoltDc");
+    }
+
+    #[test]
+    fn firmware_versions_parse() {
+        assert_eq!(FlukeDevice::parse_firmware_version("V1.16"), Some((1, 16)));
+        assert_eq!(FlukeDevice::parse_firmware_version(" v2.0 "), Some((2, 0)));
+        assert_eq!(FlukeDevice::parse_firmware_version("1.10b"), Some((1, 10)));
+        assert_eq!(FlukeDevice::parse_firmware_version("V3"), Some((3, 0)));
+        assert_eq!(FlukeDevice::parse_firmware_version("beta"), None);
+    }
+
+    #[test]
+    fn capabilities_follow_the_version_table() {
+        let table = [
+            (Some((0, 9)), false, false, false),
+            (Some((1, 0)), false, true, false),
+            (Some((1, 9)), false, true, false),
+            (Some((1, 10)), true, true, false),
+            (Some((1, 15)), true, true, false),
+            (Some((1, 16)), true, true, true),
+            (Some((2, 0)), true, true, true),
+            (None, true, true, true),
+        ];
+
+        for (version, display_download, clock, saved_setups) in table {
+            assert_eq!(
+                FlukeDevice::capabilities_for(version),
+                Capabilities {
+                    display_download,
+                    clock,
+                    saved_setups,
+                },
+                "{:?}",
+                version
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn identify_gates_features_on_the_firmware_version() {
+        let port = ScriptedPort::new([Reply::Bytes(b"0\rFLUKE 289,V1.09,12345678\r".to_vec())]);
+        let mut device = device_on(&port);
+
+        let info = device.identify().await.unwrap();
+
+        assert_eq!(info.software_version, "V1.09");
+        assert_eq!(
+            device.capabilities(),
+            Capabilities {
+                display_download: false,
+                clock: true,
+                saved_setups: false,
+            }
+        );
+        // No probe commands are sent
+        assert_eq!(port.written(), "ID\r");
+        let error = device.get_full_display().await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(_)), "{:?}", error);
+        let error = device.list_saved_setups().await.unwrap_err();
+        assert!(matches!(error, Error::Unsupported(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn port_failure_marks_the_device_disconnected() {
+        let port = ScriptedPort::new([Reply::Bytes(b"0\r".to_vec()), Reply::Fail]);
+        let mut device = device_on(&port);
+
+        device.send_command_internal("RMP").await.unwrap();
+        assert!(device.is_connected());
+
+        let error = device.send_command_internal("RMP").await.unwrap_err();
+        assert!(matches!(error, Error::Io(_)), "{:?}", error);
+        assert!(!device.is_connected());
+    }
+
+    #[tokio::test]
+    async fn display_download_falls_back_to_qm_when_qdda_is_refused() {
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"1\r".to_vec()),
+            Reply::Bytes(b"0\r1.234E0,VDC,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(b"0\r2.5E0,VDC,NORMAL,NONE\r".to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        let readings = device.get_all_measurements().await.unwrap();
+        assert_eq!(readings.len(), 1);
+        assert_eq!(readings[0].reading_id, "PRIMARY");
+        assert_eq!(readings[0].measurement.value, 1.234);
+        assert_eq!(readings[0].measurement.unit, Unit::VoltDc);
+
+        // Once refused, QDDA is not tried again.
+        let readings = device.get_all_measurements().await.unwrap();
+        assert_eq!(readings[0].measurement.value, 2.5);
+        assert_eq!(port.written(), "QDDA\rQM\rQM\r");
+    }
+
+    #[test]
+    fn values_parse_in_every_exponent_form() {
+        let zero = FlukeDevice::parse_value("-0.0000").unwrap();
+        assert_eq!(zero, 0.0);
+        assert!(zero.is_sign_positive());
+        assert_eq!(FlukeDevice::parse_value("1.234E-3").unwrap(), 1.234e-3);
+        assert_eq!(FlukeDevice::parse_value("1.234e+3").unwrap(), 1234.0);
+        assert_eq!(FlukeDevice::parse_value(" -2.5E0 ").unwrap(), -2.5);
+        for raw in ["inf", "NaN", "1.2V", ""] {
+            let error = FlukeDevice::parse_value(raw).unwrap_err();
+            assert!(matches!(error, Error::Parse { .. }), "{:?}", error);
+        }
+    }
+
+    #[tokio::test]
+    async fn corrupt_frame_is_a_parse_error_not_a_measurement() {
+        let corrupt = b"0\r1.2\xff4E0,VDC,NORMAL,NONE\r";
+        let port = ScriptedPort::new([
+            Reply::Bytes(corrupt.to_vec()),
+            Reply::Bytes(corrupt.to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        let error = device.get_measurement().await.unwrap_err();
+
+        let Error::Parse { message, raw } = &error else {
+            panic!("{:?}", error);
+        };
+        assert!(message.starts_with("Corrupt frame"), "{}", message);
+        assert_eq!(raw, corrupt);
+        // Retried once after clearing the buffers
+        assert_eq!(port.written(), "QM\rQM\r");
+    }
+
+    #[tokio::test]
+    async fn one_corrupt_frame_is_retried() {
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"0\r1.2\xff4E0,VDC,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(b"0\r1.234E0,VDC,NORMAL,NONE\r".to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        let measurement = device.get_measurement().await.unwrap();
+
+        assert_eq!(measurement.value, 1.234);
+        assert_eq!(measurement.unit, Unit::VoltDc);
+    }
+
+    #[test]
+    fn command_table_lists_how_each_command_answers() {
+        let table: Vec<_> = commands()
+            .into_iter()
+            .map(|c| (c.name, c.expects_ack, c.expects_payload))
+            .collect();
+        assert_eq!(
+            table,
+            [
+                ("ID", true, true),
+                ("QM", true, true),
+                ("QDDA", true, true),
+                ("QMP", true, true),
+                ("QSSN", true, true),
+                ("DS", true, false),
+                ("RI", false, false),
+                ("RMP", true, false),
+                ("MP", true, false),
+                ("RCLS", true, false),
+            ]
+        );
+        assert!(commands().iter().all(|c| !c.description.is_empty()));
+    }
+
+    #[test]
+    fn commands_are_looked_up_by_their_first_word() {
+        assert_eq!(CommandSpec::of("qmp clock").name, "QMP");
+        assert_eq!(CommandSpec::of("MP CLOCK,1710408600").name, "MP");
+        assert!(!CommandSpec::of("ri").expects_ack);
+        // Unknown raw commands wait for an ACK and a payload
+        let unknown = CommandSpec::of("QXYZ");
+        assert!(unknown.expects_ack && unknown.expects_payload);
+    }
+
+    #[tokio::test]
+    async fn blank_unit_is_inferred_from_the_display() {
+        let qdda = "0\rMV_AC,NONE,AUTO,VAC,50,-3,OFF,0.000,0,1,\
+            PRIMARY,0.005029,VAC,-3,3,5,NORMAL,NONE,1197308998.282\r";
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"0\r0.005029,,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(qdda.as_bytes().to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        let measurement = device.get_measurement().await.unwrap();
+
+        assert_eq!(measurement.unit, Unit::VoltAc);
+        assert_eq!(measurement.value, 0.005029);
+        assert_eq!(port.written(), "QM\rQDDA\r");
+    }
+
+    #[tokio::test]
+    async fn blank_unit_falls_back_to_the_last_unit_seen() {
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"0\r1.5E0,VDC,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(b"0\r1.6E0,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(b"1\r".to_vec()),
+            Reply::Bytes(b"0\r1.7E0,,NORMAL,NONE\r".to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        device.get_measurement().await.unwrap();
+        let second = device.get_measurement().await.unwrap();
+        assert_eq!(second.unit, Unit::VoltDc);
+        assert_eq!(second.value, 1.6);
+
+        // QDDA was refused, so it isn't asked again
+        let third = device.get_measurement().await.unwrap();
+        assert_eq!(third.unit, Unit::VoltDc);
+        assert_eq!(port.written(), "QM\rQM\rQDDA\rQM\r");
+    }
+
+    #[tokio::test]
+    async fn endless_response_fails_once_over_the_bound() {
+        let port = ScriptedPort::new([Reply::Endless(b'9')]);
+        let mut device = device_on(&port);
+
+        let error = device.send_command_internal("QM").await.unwrap_err();
+        let Error::Parse { message, raw } = &error else {
+            panic!("{:?}", error);
+        };
+        assert!(
+            message.contains(&format!("more than {} bytes", MAX_RESPONSE_BYTES)),
+            "{}",
+            message
+        );
+        assert_eq!(raw.len(), TRUNCATED_RAW_BYTES);
+        assert!(raw.iter().all(|&byte| byte == b'9'));
+    }
+
+    #[tokio::test]
+    async fn wire_tap_sees_both_directions() {
+        let port = ScriptedPort::new([Reply::Bytes(b"0\r1.5E0,VDC,NORMAL,NONE\r".to_vec())]);
+        let mut device = device_on(&port);
+        let (tap, mut listener) = tokio::sync::broadcast::channel(16);
+        device.set_wire_tap(tap);
+
+        device.send_command_internal("QM").await.unwrap();
+
+        let (mut written, mut read) = (String::new(), String::new());
+        while let Ok(chunk) = listener.try_recv() {
+            match chunk.direction {
+                Direction::Write => written.push_str(&chunk.data),
+                Direction::Read => read.push_str(&chunk.data),
+            }
+        }
+        assert_eq!(written, "514d0d");
+        let expected: String = b"0\r1.5E0,VDC,NORMAL,NONE\r"
+            .iter()
+            .map(|byte| format!("{:02x}", byte))
+            .collect();
+        assert_eq!(read, expected);
+    }
+
+    #[tokio::test]
+    async fn thermocouple_reading_carries_the_cold_junction_temperature() {
+        let qdda = "0\rTEMPERATURE,NONE,AUTO,CEL,0,0,OFF,0.000,0,2,\
+            PRIMARY,23.4,CEL,0,1,5,NORMAL,NONE,1197308998.282,\
+            REFERENCE,72.5,FAR,0,1,5,NORMAL,NONE,1197308998.282\r";
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"0\r2.34E1,CEL,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(qdda.as_bytes().to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        let measurement = device.get_measurement().await.unwrap();
+
+        assert_eq!(measurement.unit, Unit::Celsius);
+        assert_eq!(measurement.value, 23.4);
+        let reference = measurement.metadata.reference_temperature.unwrap();
+        assert!((reference - 22.5).abs() < 1e-9, "{}", reference);
+        assert_eq!(port.written(), "QM\rQDDA\r");
+    }
+
+    #[tokio::test]
+    async fn cold_junction_is_not_asked_again_once_absent() {
+        let qdda = "0\rTEMPERATURE,NONE,AUTO,CEL,0,0,OFF,0.000,0,1,\
+            PRIMARY,23.4,CEL,0,1,5,NORMAL,NONE,1197308998.282\r";
+        let port = ScriptedPort::new([
+            Reply::Bytes(b"0\r2.34E1,CEL,NORMAL,NONE\r".to_vec()),
+            Reply::Bytes(qdda.as_bytes().to_vec()),
+            Reply::Bytes(b"0\r2.35E1,CEL,NORMAL,NONE\r".to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        let first = device.get_measurement().await.unwrap();
+        let second = device.get_measurement().await.unwrap();
+
+        assert_eq!(first.metadata.reference_temperature, None);
+        assert_eq!(second.value, 23.5);
+        assert_eq!(port.written(), "QM\rQDDA\rQM\r");
+    }
+
+    #[tokio::test]
+    async fn dial_position_gives_the_functions_it_offers() {
+        let port = ScriptedPort::new([
+            Reply::Bytes(format!("0\r{}\r", QDDA_MIN_MAX).into_bytes()),
+            Reply::Bytes(b"0\rLIMBO,NONE,AUTO,VAC,50,-3,OFF,0.000,0,0\r".to_vec()),
+        ]);
+        let mut device = device_on(&port);
+
+        assert_eq!(
+            device.available_functions().await.unwrap(),
+            [
+                MeasurementFunction::MvDc,
+                MeasurementFunction::MvAc,
+                MeasurementFunction::MvAcPlusDc,
+                MeasurementFunction::MvAcOverDc,
+                MeasurementFunction::MvDcOverDc
+            ]
+        );
+        // Between positions the dial offers nothing
+        assert!(device.available_functions().await.unwrap().is_empty());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn paced_command_is_written_in_chunks_apart() {
+        let port = ScriptedPort::new([Reply::Bytes(b"0\r".to_vec())]);
+        let mut device = device_on(&port);
+        device.write_pacing = WritePacing {
+            chunk_bytes: 2,
+            delay_ms: 10,
+        };
+        let started = tokio::time::Instant::now();
+
+        device.send_command_internal("RMP").await.unwrap();
+
+        let writes: Vec<_> = port
+            .writes()
+            .into_iter()
+            .map(|(at, bytes)| (at - started, bytes))
+            .collect();
+        assert_eq!(
+            writes,
+            [
+                (Duration::ZERO, b"RM".to_vec()),
+                (Duration::from_millis(10), b"P\r".to_vec()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn unpaced_command_is_written_at_once() {
+        let port = ScriptedPort::new([Reply::Bytes(b"0\r".to_vec())]);
+        let mut device = device_on(&port);
+
+        device.send_command_internal("RMP").await.unwrap();
+
+        let writes: Vec<_> = port.writes().into_iter().map(|(_, bytes)| bytes).collect();
+        assert_eq!(writes, [b"RMP\r".to_vec()]);
+    }
+
+    #[tokio::test]
+    async fn accepted_baud_rate_is_the_one_the_port_runs_at() {
+        let port = ScriptedPort::new([]);
+        let mut device = device_on(&port).with_baud_rate(Some(9600));
+
+        device.connect().await.unwrap();
+
+        assert_eq!(port.baud_rate().unwrap(), 9600);
+        let default = FlukeDevice::new(DeviceType::Fluke289, None).with_baud_rate(None);
+        assert_eq!(default.baud_rate, DEFAULT_BAUD_RATE);
+    }
+
+    #[test]
+    fn min_max_summary_parses_from_a_captured_reply() {
+        let summary = FlukeDevice::parse_recording_summary(QDDA_MIN_MAX)
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(summary.unit, Unit::VoltAc);
+        assert_eq!(summary.minimum, -0.0211);
+        assert_eq!(summary.maximum, 0.03055);
+        assert_eq!(summary.average, 0.00529);
+        assert_eq!(
+            summary.started_at,
+            FlukeDevice::meter_seconds_to_utc(1_197_309_132).unwrap()
+                + chrono::Duration::milliseconds(612)
+        );
+        assert!(summary.active);
+
+        // The other spelling of the mode is recognised too
+        let underscored = QDDA_MIN_MAX.replace("MIN_MAX AVG", "MIN_MAX_AVG");
+        assert!(FlukeDevice::parse_recording_summary(&underscored)
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn no_min_max_mode_means_no_summary() {
+        let response = "MV_AC,NONE,AUTO,VAC,50,-3,OFF,0.000,0,1,\
+            PRIMARY,0.005029,VAC,-3,3,5,NORMAL,NONE,1197308998.282";
+        assert!(FlukeDevice::parse_recording_summary(response)
+            .unwrap()
+            .is_none());
+
+        let error = FlukeDevice::parse_recording_summary("MV_AC,NONE,AUTO").unwrap_err();
+        assert!(matches!(error, Error::Parse { .. }), "{:?}", error);
+    }
 }
@@ -0,0 +1,156 @@
+//! Measurement functions and the dial positions that offer them
+//!
+//! Many positions of the rotary switch host several functions, toggled with
+//! the function-select key. Knowing the position tells a client which
+//! functions it can select without asking the user to turn the dial.
+
+use serde::{Deserialize, Serialize};
+use MeasurementFunction::*;
+
+/// Primary measurement function, named as the Fluke QDDA reply spells it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MeasurementFunction {
+    VAc,
+    MvAc,
+    VDc,
+    MvDc,
+    VAcOverDc,
+    VDcOverDc,
+    VAcPlusDc,
+    MvAcOverDc,
+    MvDcOverDc,
+    MvAcPlusDc,
+    AAc,
+    MaAc,
+    UaAc,
+    ADc,
+    MaDc,
+    UaDc,
+    AAcOverDc,
+    ADcOverDc,
+    AAcPlusDc,
+    MaAcOverDc,
+    MaDcOverDc,
+    MaAcPlusDc,
+    UaAcOverDc,
+    UaDcOverDc,
+    UaAcPlusDc,
+    Temperature,
+    Ohms,
+    Conductance,
+    Continuity,
+    Capacitance,
+    DiodeTest,
+    VAcLoz,
+    OhmsLow,
+}
+
+/// Position of the rotary switch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SwitchPosition {
+    VoltsAc,
+    VoltsDc,
+    Millivolts,
+    Ohms,
+    DiodeContinuity,
+    Capacitance,
+    Temperature,
+    /// The A and mA jacks share one position
+    Amps,
+    Microamps,
+}
+
+/// Functions of each position, in the order the function-select key
+/// cycles through them
+const POSITIONS: &[(SwitchPosition, &[MeasurementFunction])] = &[
+    (SwitchPosition::VoltsAc, &[VAc, VAcLoz]),
+    (
+        SwitchPosition::VoltsDc,
+        &[VDc, VAcPlusDc, VAcOverDc, VDcOverDc],
+    ),
+    (
+        SwitchPosition::Millivolts,
+        &[MvDc, MvAc, MvAcPlusDc, MvAcOverDc, MvDcOverDc],
+    ),
+    (SwitchPosition::Ohms, &[Ohms, OhmsLow, Conductance]),
+    (SwitchPosition::DiodeContinuity, &[Continuity, DiodeTest]),
+    (SwitchPosition::Capacitance, &[Capacitance]),
+    (SwitchPosition::Temperature, &[Temperature]),
+    (
+        SwitchPosition::Amps,
+        &[
+            AAc, ADc, AAcPlusDc, AAcOverDc, ADcOverDc, MaAc, MaDc, MaAcPlusDc, MaAcOverDc,
+            MaDcOverDc,
+        ],
+    ),
+    (
+        SwitchPosition::Microamps,
+        &[UaAc, UaDc, UaAcPlusDc, UaAcOverDc, UaDcOverDc],
+    ),
+];
+
+impl SwitchPosition {
+    /// Position the dial is in while `function` is active
+    pub fn of(function: MeasurementFunction) -> Self {
+        POSITIONS
+            .iter()
+            .find(|(_, functions)| functions.contains(&function))
+            .map(|(position, _)| *position)
+            .expect("every function belongs to a position")
+    }
+
+    /// Functions selectable in this position without turning the dial
+    pub fn functions(self) -> &'static [MeasurementFunction] {
+        POSITIONS
+            .iter()
+            .find(|(position, _)| *position == self)
+            .map_or(&[], |(_, functions)| functions)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_function_belongs_to_exactly_one_position() {
+        for (position, functions) in POSITIONS {
+            for function in *functions {
+                assert_eq!(SwitchPosition::of(*function), *position, "{:?}", function);
+                let hosts = POSITIONS
+                    .iter()
+                    .filter(|(_, functions)| functions.contains(function))
+                    .count();
+                assert_eq!(hosts, 1, "{:?}", function);
+            }
+        }
+    }
+
+    #[test]
+    fn a_position_offers_its_functions_in_key_order() {
+        assert_eq!(
+            SwitchPosition::of(OhmsLow).functions(),
+            [Ohms, OhmsLow, Conductance]
+        );
+        assert_eq!(
+            SwitchPosition::of(Continuity).functions(),
+            [Continuity, DiodeTest]
+        );
+        assert_eq!(SwitchPosition::Capacitance.functions(), [Capacitance]);
+    }
+
+    #[test]
+    fn functions_are_named_as_the_meter_spells_them() {
+        for (name, function) in [
+            ("MV_AC", MvAc),
+            ("V_AC_LOZ", VAcLoz),
+            ("UA_DC_OVER_DC", UaDcOverDc),
+        ] {
+            let parsed: MeasurementFunction =
+                serde_json::from_value(serde_json::Value::String(name.to_string())).unwrap();
+            assert_eq!(parsed, function);
+        }
+    }
+}
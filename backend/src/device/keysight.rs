@@ -0,0 +1,370 @@
+//! Keysight (formerly Agilent) U1241/U1242 handheld multimeter implementation
+//!
+//! The U1240 series speaks a small SCPI subset over its IR-to-USB cable.
+//! Commands and replies are single lines terminated by CR LF, and only
+//! queries (commands ending in `?`) are answered; there is no
+//! acknowledgement line as on the Fluke. `CONF?` reports the function the
+//! dial is on, which gives the unit, and `FETC?` the displayed reading.
+
+use crate::device::wire::{self, Direction, WireTap};
+use crate::device::{
+    map_open_error, validate_raw_command, CommandInfo, Device, DeviceInfo, DeviceType, Measurement,
+    MeasurementAttribute, MeasurementState, Unit,
+};
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::io::{Read, Write};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+/// Serial speed the meters use out of the box
+pub const DEFAULT_BAUD_RATE: u32 = 9600;
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(2);
+const READ_TIMEOUT: Duration = Duration::from_millis(50);
+const READ_BACKOFF: Duration = Duration::from_millis(10);
+/// Time given to a command that is not answered before the next one is written
+const NO_REPLY_SETTLE: Duration = Duration::from_millis(100);
+/// Largest reply accepted; every answer of the meter is one short line
+const MAX_RESPONSE_BYTES: usize = 1024;
+/// Readings at or beyond this magnitude are SCPI's overload value, 9.9E+37
+const OVERLOAD_MAGNITUDE: f64 = 9.9e37;
+
+/// Commands the backend sends, as (name, description)
+const COMMANDS: &[(&str, &str)] = &[
+    (
+        "*IDN?",
+        "Identify the meter: manufacturer, model, serial number and firmware",
+    ),
+    (
+        "CONF?",
+        "Query the function the dial is on, e.g. `\"VOLT:AC\"`",
+    ),
+    ("FETC?", "Query the displayed reading, e.g. `+1.23456E-01`"),
+    ("*RST", "Reset the meter"),
+];
+
+/// The command table, for clients building raw commands
+pub fn commands() -> Vec<CommandInfo> {
+    COMMANDS
+        .iter()
+        .map(|&(name, description)| CommandInfo {
+            name,
+            description,
+            expects_ack: false,
+            expects_payload: is_query(name),
+        })
+        .collect()
+}
+
+/// SCPI answers queries only
+fn is_query(command: &str) -> bool {
+    command
+        .split([' ', ','])
+        .next()
+        .unwrap_or_default()
+        .ends_with('?')
+}
+
+/// Keysight U1241/U1242 device implementation
+pub struct KeysightDevice {
+    port_name: Option<String>,
+    port: Mutex<Option<Box<dyn SerialPort>>>,
+    /// Set by `connect`, cleared by `disconnect` or when the port fails
+    /// outright
+    connected: bool,
+    /// Bytes received for the most recent command, kept for parse diagnostics
+    last_response: Vec<u8>,
+    /// Observer of the raw traffic, for protocol debugging
+    wire_tap: Option<WireTap>,
+    baud_rate: u32,
+}
+
+impl KeysightDevice {
+    /// Create a new Keysight device instance
+    pub fn new(port_name: Option<String>) -> Self {
+        Self {
+            port_name,
+            port: Mutex::new(None),
+            connected: false,
+            last_response: Vec::new(),
+            wire_tap: None,
+            baud_rate: DEFAULT_BAUD_RATE,
+        }
+    }
+
+    /// Open the port at `baud` instead of the default 9600, for a meter set
+    /// to another speed
+    pub fn with_baud_rate(mut self, baud: Option<u32>) -> Self {
+        self.baud_rate = baud.unwrap_or(DEFAULT_BAUD_RATE);
+        self
+    }
+
+    /// Send a command and return its reply line without the terminator,
+    /// empty for commands that are not answered
+    async fn send_command_internal(&mut self, command: &str) -> Result<String> {
+        let result = self.exchange(command).await;
+        if let Err(error @ (Error::Io(_) | Error::Serial(_))) = &result {
+            tracing::warn!(command = %command, %error, "Serial port failed, marking disconnected");
+            self.connected = false;
+        }
+        let raw = result?;
+
+        let response = String::from_utf8(raw).map_err(|e| {
+            Error::parse(format!(
+                "Corrupt frame: invalid UTF-8 after {} bytes",
+                e.utf8_error().valid_up_to()
+            ))
+            .with_raw(&self.last_response)
+        })?;
+        Ok(response.trim_end_matches(['\r', '\n']).to_string())
+    }
+
+    /// Write a command and collect the raw bytes of its reply line, also
+    /// kept in `last_response`
+    async fn exchange(&mut self, command: &str) -> Result<Vec<u8>> {
+        let mut port_guard = self.port.lock().await;
+        let port = port_guard
+            .as_mut()
+            .ok_or_else(|| Error::Connection("Not connected".to_string()))?;
+
+        let command_bytes = format!("{}\r\n", command).into_bytes();
+        tracing::debug!(command = %command, bytes = ?command_bytes, "Sending command");
+        port.write_all(&command_bytes)?;
+        port.flush()?;
+        wire::observe(&self.wire_tap, Direction::Write, &command_bytes);
+
+        self.last_response.clear();
+        if !is_query(command) {
+            tokio::time::sleep(NO_REPLY_SETTLE).await;
+            return Ok(Vec::new());
+        }
+
+        let started = Instant::now();
+        let mut buffer = [0u8; 256];
+        while !self.last_response.ends_with(b"\n") {
+            match port.read(&mut buffer) {
+                Ok(bytes_read) if bytes_read > 0 => {
+                    let raw_chunk = &buffer[..bytes_read];
+                    self.last_response.extend_from_slice(raw_chunk);
+                    wire::observe(&self.wire_tap, Direction::Read, raw_chunk);
+                    if self.last_response.len() > MAX_RESPONSE_BYTES {
+                        if let Err(error) = port.clear(ClearBuffer::All) {
+                            tracing::warn!(%error, "Failed to clear serial buffers");
+                        }
+                        return Err(Error::parse(format!(
+                            "Response truncated: more than {} bytes received",
+                            MAX_RESPONSE_BYTES
+                        ))
+                        .with_raw(&self.last_response));
+                    }
+                    continue;
+                }
+                Ok(_) => {}
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {}
+                Err(e) => return Err(e.into()),
+            }
+            if started.elapsed() > RESPONSE_TIMEOUT {
+                tracing::warn!(command = %command, "Timeout waiting for reply");
+                return Err(Error::Timeout);
+            }
+            tokio::time::sleep(READ_BACKOFF).await;
+        }
+
+        Ok(self.last_response.clone())
+    }
+
+    /// Send a query and parse its reply, attaching the raw frame to any
+    /// parse error
+    async fn query<T>(
+        &mut self,
+        command: &str,
+        parse: impl FnOnce(&str) -> Result<T>,
+    ) -> Result<T> {
+        let response = self.send_command_internal(command).await?;
+        parse(&response).map_err(|e| e.with_raw(&self.last_response))
+    }
+
+    /// Parse an `*IDN?` reply, e.g.
+    /// `Agilent Technologies,U1242B,MY12345678,V1.02`
+    fn parse_identification(response: &str) -> Result<DeviceInfo> {
+        let parts: Vec<&str> = response.split(',').map(str::trim).collect();
+        if parts.len() < 4 {
+            return Err(Error::parse("Invalid identification response"));
+        }
+        Ok(DeviceInfo {
+            model: parts[1].to_string(),
+            serial_number: parts[2].to_string(),
+            software_version: parts[3].to_string(),
+        })
+    }
+
+    /// Map the function of a `CONF?` reply, e.g. `"VOLT:AC +5.00000E+00"`,
+    /// onto its unit. Functions not listed here give `Unit::Unknown` with
+    /// the function name rather than failing the reading.
+    fn parse_function(response: &str) -> Unit {
+        let function = response
+            .trim()
+            .trim_matches('"')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .to_ascii_uppercase();
+        match function.as_str() {
+            "VOLT" | "VOLT:DC" | "DIOD" => Unit::VoltDc,
+            "VOLT:AC" => Unit::VoltAc,
+            "VOLT:DCAC" | "VOLT:ACDC" => Unit::VoltAcPlusDc,
+            "CURR" | "CURR:DC" => Unit::AmpDc,
+            "CURR:AC" => Unit::AmpAc,
+            "CURR:DCAC" | "CURR:ACDC" => Unit::AmpAcPlusDc,
+            "RES" | "CONT" => Unit::Ohm,
+            "COND" => Unit::Siemens,
+            "CAP" => Unit::Farad,
+            "FREQ" => Unit::Hertz,
+            "DUTY" => Unit::Percent,
+            "PWID" => Unit::Second,
+            "TEMP" => Unit::Celsius,
+            _ => Unit::Unknown(function),
+        }
+    }
+
+    /// Parse a `FETC?` reply such as `+1.23456E-01`. SCPI reports an
+    /// overload as ±9.9E+37.
+    fn parse_reading(response: &str) -> Result<(f64, MeasurementState)> {
+        let raw = response.trim();
+        let numeric = raw
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '+' | '-' | '.' | 'e' | 'E'));
+        let value = raw
+            .parse::<f64>()
+            .ok()
+            .filter(|_| numeric && !raw.is_empty())
+            .ok_or_else(|| Error::parse(format!("Invalid measurement value: {}", raw)))?;
+
+        let state = if value >= OVERLOAD_MAGNITUDE {
+            MeasurementState::Overload
+        } else if value <= -OVERLOAD_MAGNITUDE {
+            MeasurementState::OverloadNegative
+        } else {
+            MeasurementState::Normal
+        };
+        Ok((if value == 0.0 { 0.0 } else { value }, state))
+    }
+}
+
+#[async_trait]
+impl Device for KeysightDevice {
+    fn device_type(&self) -> DeviceType {
+        DeviceType::KeysightU124x
+    }
+
+    fn set_wire_tap(&mut self, tap: WireTap) {
+        self.wire_tap = Some(tap);
+    }
+
+    async fn connect(&mut self) -> Result<()> {
+        let mut port_guard = self.port.lock().await;
+        if port_guard.is_some() {
+            self.connected = true;
+            return Ok(());
+        }
+
+        let port_name = self
+            .port_name
+            .as_ref()
+            .ok_or_else(|| Error::Config("No port specified".to_string()))?;
+
+        let port = serialport::new(port_name, self.baud_rate)
+            .data_bits(DataBits::Eight)
+            .parity(Parity::None)
+            .stop_bits(StopBits::One)
+            .flow_control(FlowControl::None)
+            .timeout(READ_TIMEOUT)
+            .open()
+            .map_err(|e| map_open_error(port_name, e))?;
+        if let Err(error) = port.clear(ClearBuffer::All) {
+            tracing::warn!(%error, "Failed to clear serial buffers");
+        }
+
+        *port_guard = Some(port);
+        self.connected = true;
+
+        tracing::info!(
+            "Connected to Keysight device on port {} at {} baud",
+            port_name,
+            self.baud_rate
+        );
+        Ok(())
+    }
+
+    async fn disconnect(&mut self) -> Result<()> {
+        self.connected = false;
+        if self.port.lock().await.take().is_some() {
+            tracing::info!("Disconnected from Keysight device");
+        }
+        Ok(())
+    }
+
+    fn is_connected(&self) -> bool {
+        self.connected
+    }
+
+    async fn identify(&mut self) -> Result<DeviceInfo> {
+        self.query("*IDN?", Self::parse_identification).await
+    }
+
+    async fn get_measurement(&mut self) -> Result<Measurement> {
+        // The dial may have moved since the last reading, so the function
+        // is queried every time.
+        let function = self.send_command_internal("CONF?").await?;
+        let unit = Self::parse_function(&function);
+        if matches!(unit, Unit::Unknown(_)) {
+            tracing::debug!(function = %function, "Unrecognised function, unit unknown");
+        }
+        let (value, state) = self.query("FETC?", Self::parse_reading).await?;
+        Ok(Measurement::new(
+            value,
+            unit,
+            state,
+            MeasurementAttribute::None,
+        ))
+    }
+
+    async fn reset(&mut self) -> Result<()> {
+        self.send_command_internal("*RST").await.map(|_| ())
+    }
+
+    async fn send_command(&mut self, command: &str) -> Result<String> {
+        validate_raw_command(command)?;
+        self.send_command_internal(command).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scientific_reading_is_parsed() {
+        let (value, state) = KeysightDevice::parse_reading("+1.23456E-01\n").unwrap();
+        assert!((value - 0.123456).abs() < 1e-12);
+        assert_eq!(state, MeasurementState::Normal);
+    }
+
+    #[test]
+    fn overload_sentinels_set_the_state() {
+        let (_, state) = KeysightDevice::parse_reading("+9.90000000E+37").unwrap();
+        assert_eq!(state, MeasurementState::Overload);
+        let (_, state) = KeysightDevice::parse_reading("-9.90000000E+37").unwrap();
+        assert_eq!(state, MeasurementState::OverloadNegative);
+    }
+
+    #[test]
+    fn non_numeric_reply_is_a_parse_error() {
+        for reply in ["", "inf", "NaN", "+1.0V"] {
+            let error = KeysightDevice::parse_reading(reply).unwrap_err();
+            assert!(matches!(error, Error::Parse { .. }), "{:?}", error);
+        }
+    }
+}
@@ -3,17 +3,171 @@
 //! This provides a fully functional mock device that simulates a multimeter
 //! for development purposes without requiring actual hardware.
 
+use crate::device::function::{MeasurementFunction, SwitchPosition};
 use crate::device::{
-    Device, DeviceInfo, DeviceType, Measurement, MeasurementAttribute, MeasurementState, Unit,
+    validate_raw_command, CalibrationInfo, Capabilities, CommandInfo, Device, DeviceInfo,
+    DeviceSettings, DeviceType, DisplayData, DisplayReading, FrequencyFilter, Measurement,
+    MeasurementAttribute, MeasurementState, RecordingSummary, SavedMeasurement, SavedSetupInfo,
+    SecondaryFunction, SelfTestResult, Unit,
 };
 use crate::error::{Error, Result};
 use async_trait::async_trait;
-use rand::Rng;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
 use std::f64::consts::PI;
 use std::time::{Duration, Instant};
 
 const TAU: f64 = 2.0 * PI;
 
+const DEFAULT_CONNECT_LATENCY: LatencyDistribution = LatencyDistribution::Fixed { ms: 100 };
+const DEFAULT_MEASUREMENT_LATENCY: LatencyDistribution = LatencyDistribution::Fixed { ms: 20 };
+/// Time constant of the simulated settling ramp after a function change
+const SETTLING_TIME_CONSTANT_SECS: f64 = 0.1;
+/// Simulated accuracy of every reading, in percent of the value
+const ACCURACY_PERCENT: f64 = 0.5;
+/// Highest display brightness (backlight off, low, high) and contrast levels
+const MAX_BRIGHTNESS: u8 = 2;
+const MAX_CONTRAST: u8 = 15;
+/// Integration times offered, in power-line cycles
+const NPLC_VALUES: [f64; 5] = [0.02, 0.2, 1.0, 10.0, 100.0];
+const DEFAULT_NPLC: f64 = 1.0;
+/// Mains frequency the simulated integration is timed against
+const LINE_FREQUENCY_HZ: f64 = 50.0;
+/// Simulated cold-junction temperature: room temperature drifting slowly
+/// by `AMBIENT_DRIFT_C` over `AMBIENT_PERIOD_SECS`
+const AMBIENT_C: f64 = 23.0;
+const AMBIENT_DRIFT_C: f64 = 0.5;
+const AMBIENT_PERIOD_SECS: f64 = 600.0;
+/// With auto-save on, a reading counts as stable once the settling ramp is
+/// past five time constants, and is saved at most once per period
+const AUTO_SAVE_STABLE_SECS: f64 = 5.0 * SETTLING_TIME_CONSTANT_SECS;
+const AUTO_SAVE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Distribution the mock draws simulated delays from
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LatencyDistribution {
+    /// Always the same delay
+    Fixed { ms: u64 },
+    /// Uniformly distributed between `min_ms` and `max_ms` inclusive
+    Uniform { min_ms: u64, max_ms: u64 },
+    /// `base_ms` normally, `tail_ms` with probability `tail_probability`
+    LongTail {
+        base_ms: u64,
+        tail_ms: u64,
+        tail_probability: f64,
+    },
+}
+
+impl LatencyDistribution {
+    fn validate(&self) -> Result<()> {
+        match *self {
+            Self::Fixed { .. } => Ok(()),
+            Self::Uniform { min_ms, max_ms } if min_ms > max_ms => Err(Error::Config(format!(
+                "Latency min_ms ({}) exceeds max_ms ({})",
+                min_ms, max_ms
+            ))),
+            Self::Uniform { .. } => Ok(()),
+            Self::LongTail {
+                tail_probability, ..
+            } if !(0.0..=1.0).contains(&tail_probability) => Err(Error::Config(format!(
+                "Latency tail_probability {} is outside 0..=1",
+                tail_probability
+            ))),
+            Self::LongTail { .. } => Ok(()),
+        }
+    }
+
+    fn sample(&self, rng: &mut impl Rng) -> Duration {
+        let ms = match *self {
+            Self::Fixed { ms } => ms,
+            Self::Uniform { min_ms, max_ms } => rng.gen_range(min_ms..=max_ms),
+            Self::LongTail {
+                base_ms,
+                tail_ms,
+                tail_probability,
+            } => {
+                if rng.gen_bool(tail_probability) {
+                    tail_ms
+                } else {
+                    base_ms
+                }
+            }
+        };
+        Duration::from_millis(ms)
+    }
+}
+
+/// Behaviour knobs for the mock device, supplied in the connect request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MockConfig {
+    /// Seed for the latency generator so runs are reproducible
+    pub seed: Option<u64>,
+    /// Delay of `connect`; defaults to a fixed 100 ms
+    pub connect_latency: Option<LatencyDistribution>,
+    /// Delay of each measurement; defaults to a fixed 20 ms
+    pub measurement_latency: Option<LatencyDistribution>,
+    /// Waveform to simulate; picked at random when unset
+    pub profile: Option<MockProfileKind>,
+    /// Probability in `0..=1` that a measurement fails with a timeout
+    pub fault_rate: Option<f64>,
+    /// Probability in `0..=1` that a measurement finds the mock
+    /// disconnected, as if its cable were pulled; it stays down until
+    /// reconnected
+    pub disconnect_rate: Option<f64>,
+    /// Drop the connection once, on the measurement after this many
+    /// successful ones since connect; a deterministic `disconnect_rate`
+    pub disconnect_after: Option<u64>,
+}
+
+impl MockConfig {
+    fn validate(&self) -> Result<()> {
+        for distribution in [self.connect_latency, self.measurement_latency]
+            .into_iter()
+            .flatten()
+        {
+            distribution.validate()?;
+        }
+        for (name, rate) in [
+            ("fault_rate", self.fault_rate),
+            ("disconnect_rate", self.disconnect_rate),
+        ] {
+            match rate {
+                Some(rate) if !(0.0..=1.0).contains(&rate) => {
+                    return Err(Error::Config(format!("{} {} is outside 0..=1", name, rate)));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Waveforms the mock can simulate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MockProfileKind {
+    VoltageSine,
+    TemperatureDrift,
+    ResistanceSweep,
+    FrequencyPulse,
+    CurrentSine,
+    AudioLevel,
+}
+
+impl MockProfileKind {
+    const ALL: [Self; 6] = [
+        Self::VoltageSine,
+        Self::TemperatureDrift,
+        Self::ResistanceSweep,
+        Self::FrequencyPulse,
+        Self::CurrentSine,
+        Self::AudioLevel,
+    ];
+}
+
 #[derive(Clone, Copy, Debug)]
 enum MockMeasurementProfile {
     VoltageSine {
@@ -46,6 +200,13 @@ enum MockMeasurementProfile {
         frequency_hz: f64,
         noise: f64,
     },
+    /// Signal level in dBV, fading slowly up and down
+    AudioLevel {
+        level: f64,
+        swing: f64,
+        period_sec: f64,
+        noise: f64,
+    },
 }
 
 impl MockMeasurementProfile {
@@ -56,6 +217,7 @@ impl MockMeasurementProfile {
             Self::ResistanceSweep { .. } => Unit::Ohm,
             Self::FrequencyPulse { .. } => Unit::Hertz,
             Self::CurrentSine { .. } => Unit::AmpDc,
+            Self::AudioLevel { .. } => Unit::DecibelV,
         }
     }
 
@@ -139,64 +301,317 @@ impl MockMeasurementProfile {
                 let base = offset + amplitude * phase.sin();
                 (base + rng.gen_range(-noise..=noise)).max(0.0)
             }
+            Self::AudioLevel {
+                level,
+                swing,
+                period_sec,
+                noise,
+            } => {
+                let noise = *noise;
+                let phase = (elapsed_sec / period_sec.max(5.0)) * TAU;
+                level + swing * phase.sin() + rng.gen_range(-noise..=noise)
+            }
+        }
+    }
+
+    /// The profile as seen through `filter`; the low-pass filter removes
+    /// most of the jitter from frequency readings
+    fn filtered(self, filter: FrequencyFilter) -> Self {
+        match (self, filter) {
+            (
+                Self::FrequencyPulse {
+                    baseline,
+                    swing,
+                    frequency_hz,
+                    noise,
+                },
+                FrequencyFilter::LowPass,
+            ) => Self::FrequencyPulse {
+                baseline,
+                swing,
+                frequency_hz,
+                noise: noise * 0.1,
+            },
+            (profile, _) => profile,
+        }
+    }
+
+    /// The profile integrated over `nplc` power-line cycles; noise averages
+    /// out with the square root of the integration time
+    fn integrated(mut self, nplc: f64) -> Self {
+        let scale = 1.0 / nplc.sqrt();
+        match &mut self {
+            Self::VoltageSine { noise, .. }
+            | Self::TemperatureDrift { noise, .. }
+            | Self::ResistanceSweep { noise, .. }
+            | Self::FrequencyPulse { noise, .. }
+            | Self::CurrentSine { noise, .. }
+            | Self::AudioLevel { noise, .. } => *noise *= scale,
         }
+        self
+    }
+
+    /// Profiles stored in the mock's setup memory; `None` marks an empty slot
+    fn saved_setups() -> [Option<(&'static str, Self)>; 4] {
+        [
+            Some((
+                "BENCH 5V",
+                Self::VoltageSine {
+                    offset: 5.0,
+                    amplitude: 0.05,
+                    frequency_hz: 0.1,
+                    noise: 0.002,
+                },
+            )),
+            Some((
+                "THERMOCOUPLE",
+                Self::TemperatureDrift {
+                    baseline: 23.0,
+                    swing: 2.0,
+                    period_sec: 300.0,
+                    noise: 0.05,
+                },
+            )),
+            None,
+            None,
+        ]
+    }
+
+    /// Readings in the mock's measurement memory as (value, unit, saved at
+    /// seconds since the epoch); `None` marks an empty slot
+    fn saved_measurements() -> [Option<(f64, Unit, i64)>; 4] {
+        [
+            Some((4.9987, Unit::VoltDc, 1_710_408_600)),
+            Some((1_003.2, Unit::Ohm, 1_710_409_500)),
+            None,
+            None,
+        ]
     }
 
     fn random(rng: &mut impl Rng) -> Self {
-        match rng.gen_range(0..5) {
-            0 => Self::VoltageSine {
+        let kind = MockProfileKind::ALL[rng.gen_range(0..MockProfileKind::ALL.len())];
+        Self::random_of(kind, rng)
+    }
+
+    /// A profile of `kind` with randomised parameters
+    fn random_of(kind: MockProfileKind, rng: &mut impl Rng) -> Self {
+        match kind {
+            MockProfileKind::VoltageSine => Self::VoltageSine {
                 offset: rng.gen_range(0.5..12.0),
                 amplitude: rng.gen_range(0.25..3.5),
                 frequency_hz: rng.gen_range(0.05..0.5),
                 noise: rng.gen_range(0.002..0.025),
             },
-            1 => Self::TemperatureDrift {
+            MockProfileKind::TemperatureDrift => Self::TemperatureDrift {
                 baseline: rng.gen_range(18.0..35.0),
                 swing: rng.gen_range(1.0..6.0),
                 period_sec: rng.gen_range(180.0..420.0),
                 noise: rng.gen_range(0.05..0.25),
             },
-            2 => Self::ResistanceSweep {
+            MockProfileKind::ResistanceSweep => Self::ResistanceSweep {
                 min: rng.gen_range(10.0..500.0),
                 max: rng.gen_range(5000.0..50000.0),
                 period_sec: rng.gen_range(8.0..20.0),
                 noise: rng.gen_range(0.5..25.0),
             },
-            3 => Self::FrequencyPulse {
+            MockProfileKind::FrequencyPulse => Self::FrequencyPulse {
                 baseline: rng.gen_range(800.0..1200.0),
                 swing: rng.gen_range(50.0..250.0),
                 frequency_hz: rng.gen_range(0.2..2.0),
                 noise: rng.gen_range(1.0..20.0),
             },
-            _ => Self::CurrentSine {
+            MockProfileKind::CurrentSine => Self::CurrentSine {
                 offset: rng.gen_range(0.2..2.0),
                 amplitude: rng.gen_range(0.1..0.8),
                 frequency_hz: rng.gen_range(0.1..0.7),
                 noise: rng.gen_range(0.001..0.02),
             },
+            MockProfileKind::AudioLevel => Self::AudioLevel {
+                level: rng.gen_range(-30.0..0.0),
+                swing: rng.gen_range(1.0..6.0),
+                period_sec: rng.gen_range(10.0..30.0),
+                noise: rng.gen_range(0.05..0.3),
+            },
         }
     }
 }
 
+/// Raw commands the mock answers; anything else is a syntax error
+pub fn commands() -> Vec<CommandInfo> {
+    let command = |name, expects_payload, description| CommandInfo {
+        name,
+        description,
+        expects_ack: true,
+        expects_payload,
+    };
+    vec![
+        command("ID", true, "Identify the simulated meter"),
+        command("QM", true, "Query a simulated measurement"),
+        command("DS", false, "Acknowledged without effect"),
+        command("RI", false, "Acknowledged without effect"),
+        command("RMP", false, "Acknowledged without effect"),
+    ]
+}
+
+/// Running MIN MAX recording of the simulated meter
+#[derive(Debug, Clone)]
+struct MockRecording {
+    unit: Unit,
+    minimum: f64,
+    maximum: f64,
+    sum: f64,
+    count: u64,
+    /// By the simulated meter clock
+    started_at: chrono::DateTime<chrono::Utc>,
+    stopped_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+impl MockRecording {
+    fn new(first: &Measurement, started_at: chrono::DateTime<chrono::Utc>) -> Self {
+        Self {
+            unit: first.unit.clone(),
+            minimum: first.value,
+            maximum: first.value,
+            sum: first.value,
+            count: 1,
+            started_at,
+            stopped_at: None,
+        }
+    }
+
+    fn add(&mut self, value: f64) {
+        self.minimum = self.minimum.min(value);
+        self.maximum = self.maximum.max(value);
+        self.sum += value;
+        self.count += 1;
+    }
+}
+
 /// Mock device implementation
 pub struct MockDevice {
     connected: bool,
     measurement_count: u64,
     profile: Option<MockMeasurementProfile>,
     started_at: Option<Instant>,
+    /// Offset of the simulated meter clock from host time
+    clock_offset: chrono::Duration,
+    config: MockConfig,
+    latency_rng: StdRng,
+    frequency_filter: FrequencyFilter,
+    secondary_function: SecondaryFunction,
+    display_brightness: u8,
+    display_contrast: u8,
+    nplc: f64,
+    /// High input impedance, for DC volts; dropped when the function changes
+    high_input_impedance: bool,
+    auto_save: bool,
+    /// When the last reading was auto-saved
+    last_auto_save: Option<Instant>,
+    /// Reading nulled by `zero_adjust`, subtracted from readings in its unit
+    zero: Option<(Unit, f64)>,
+    recording: Option<MockRecording>,
+    /// Reading frozen on the display by `set_hold`
+    held: Option<Measurement>,
+    /// The drop of `disconnect_after` happened; rearmed by `configure_mock`
+    dropped: bool,
+}
+
+impl Default for MockDevice {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MockDevice {
     /// Create a new mock device
     pub fn new() -> Self {
+        Self::with_config(MockConfig::default())
+    }
+
+    /// Create a new mock device with custom behaviour
+    pub fn with_config(config: MockConfig) -> Self {
+        let latency_rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
         Self {
             connected: false,
             measurement_count: 0,
             profile: None,
             started_at: None,
+            clock_offset: chrono::Duration::zero(),
+            config,
+            latency_rng,
+            frequency_filter: FrequencyFilter::Off,
+            secondary_function: SecondaryFunction::None,
+            display_brightness: MAX_BRIGHTNESS,
+            display_contrast: MAX_CONTRAST / 2,
+            nplc: DEFAULT_NPLC,
+            high_input_impedance: false,
+            auto_save: false,
+            last_auto_save: None,
+            zero: None,
+            recording: None,
+            held: None,
+            dropped: false,
         }
     }
 
+    /// A profile of the configured kind, or of a random one
+    fn new_profile(&self, rng: &mut impl Rng) -> MockMeasurementProfile {
+        match self.config.profile {
+            Some(kind) => MockMeasurementProfile::random_of(kind, rng),
+            None => MockMeasurementProfile::random(rng),
+        }
+    }
+
+    /// Draw a delay from the configured distribution, or `default` if unset
+    fn latency(
+        &mut self,
+        distribution: Option<LatencyDistribution>,
+        default: LatencyDistribution,
+    ) -> Duration {
+        distribution
+            .unwrap_or(default)
+            .sample(&mut self.latency_rng)
+    }
+
+    /// Reading of the secondary display derived from the primary reading
+    fn secondary_reading(&self, primary: &Measurement) -> Option<Measurement> {
+        let magnitude = primary.value.abs().max(f64::MIN_POSITIVE);
+        let (value, unit) = match self.secondary_function {
+            SecondaryFunction::None => return None,
+            SecondaryFunction::Hertz => (60.0, Unit::Hertz),
+            SecondaryFunction::DutyCycle => (50.0, Unit::Percent),
+            SecondaryFunction::PulseWidth => (1.0 / 120.0, Unit::Second),
+            SecondaryFunction::Dbm | SecondaryFunction::DbmHertz => (
+                10.0 * (magnitude * magnitude / 600.0 / 0.001).log10(),
+                Unit::DecibelM,
+            ),
+            SecondaryFunction::Dbv | SecondaryFunction::DbvHertz => {
+                (20.0 * magnitude.log10(), Unit::DecibelV)
+            }
+            SecondaryFunction::CrestFactor => (2.0_f64.sqrt(), Unit::CrestFactor),
+            SecondaryFunction::PeakMinMax => {
+                let unit = match primary.unit {
+                    Unit::AmpDc | Unit::AmpAc | Unit::AmpAcPlusDc => Unit::Amp,
+                    _ => Unit::Volt,
+                };
+                (magnitude * 2.0_f64.sqrt(), unit)
+            }
+        };
+
+        let mut reading = Measurement::new(
+            value,
+            unit,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        );
+        reading.metadata.secondary_function = Some(self.secondary_function);
+        Some(reading)
+    }
+
     /// Generate a realistic mock measurement
     fn generate_measurement(&mut self) -> Measurement {
         self.measurement_count += 1;
@@ -205,7 +620,7 @@ impl MockDevice {
         let profile = match self.profile {
             Some(profile) => profile,
             None => {
-                let profile = MockMeasurementProfile::random(&mut rng);
+                let profile = self.new_profile(&mut rng);
                 self.profile = Some(profile);
                 profile
             }
@@ -220,16 +635,59 @@ impl MockDevice {
             }
         };
 
+        // `started_at` restarts with every function change; readings ramp up
+        // to the signal like a real input settling.
         let elapsed = started_at.elapsed().as_secs_f64();
-        let value = profile.sample(elapsed, &mut rng);
+        let settling = 1.0 - (-elapsed / SETTLING_TIME_CONSTANT_SECS).exp();
+        let value = profile
+            .filtered(self.frequency_filter)
+            .integrated(self.nplc)
+            .sample(elapsed, &mut rng)
+            * settling;
 
-        Measurement {
+        let mut measurement = Measurement::new(
             value,
-            unit: profile.unit(),
-            state: MeasurementState::Normal,
-            attribute: MeasurementAttribute::None,
-            timestamp: Some(chrono::Utc::now()),
+            profile.unit(),
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        );
+        measurement.uncertainty = Some(value.abs() * ACCURACY_PERCENT / 100.0);
+        measurement.metadata.nplc = Some(self.nplc);
+        if measurement.unit == Unit::VoltDc {
+            measurement.metadata.high_input_impedance = Some(self.high_input_impedance);
+        }
+        if let Some((_, zero)) = self
+            .zero
+            .clone()
+            .filter(|(unit, _)| *unit == measurement.unit)
+        {
+            measurement.value -= zero;
+            measurement.metadata.zero_offset = Some(zero);
+        }
+        if measurement.unit == Unit::Hertz {
+            measurement.metadata.frequency_filter = Some(self.frequency_filter);
+        }
+        if measurement.unit == Unit::Celsius {
+            let drift = AMBIENT_DRIFT_C * (TAU * elapsed / AMBIENT_PERIOD_SECS).sin();
+            measurement.metadata.reference_temperature =
+                Some(AMBIENT_C + drift + rng.gen_range(-0.05..=0.05));
+        }
+        let save_due = self
+            .last_auto_save
+            .is_none_or(|saved| saved.elapsed() >= AUTO_SAVE_PERIOD);
+        if self.auto_save && elapsed >= AUTO_SAVE_STABLE_SECS && save_due {
+            measurement.metadata.auto_saved = true;
+            self.last_auto_save = Some(Instant::now());
         }
+        // Like the meter, leave MIN MAX mode when the function changes.
+        if let Some(recording) = self.recording.as_mut().filter(|r| r.stopped_at.is_none()) {
+            if recording.unit == measurement.unit {
+                recording.add(measurement.value);
+            } else {
+                self.recording = None;
+            }
+        }
+        measurement
     }
 }
 
@@ -239,19 +697,28 @@ impl Device for MockDevice {
         DeviceType::Mock
     }
 
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::ALL
+    }
+
     async fn connect(&mut self) -> Result<()> {
         if self.connected {
             return Ok(());
         }
 
+        self.config.validate()?;
+
         // Simulate connection delay
-        tokio::time::sleep(Duration::from_millis(100)).await;
+        let delay = self.latency(self.config.connect_latency, DEFAULT_CONNECT_LATENCY);
+        tokio::time::sleep(delay).await;
 
         self.connected = true;
         self.measurement_count = 0;
         self.started_at = Some(Instant::now());
         let mut rng = rand::thread_rng();
-        self.profile = Some(MockMeasurementProfile::random(&mut rng));
+        self.profile = Some(self.new_profile(&mut rng));
+        self.high_input_impedance = false;
+        self.held = None;
         tracing::info!("Connected to mock device");
         Ok(())
     }
@@ -267,6 +734,7 @@ impl Device for MockDevice {
         self.connected = false;
         self.profile = None;
         self.started_at = None;
+        self.held = None;
         tracing::info!("Disconnected from mock device");
         Ok(())
     }
@@ -295,12 +763,85 @@ impl Device for MockDevice {
             return Err(Error::Connection("Not connected".to_string()));
         }
 
-        // Simulate measurement delay
-        tokio::time::sleep(Duration::from_millis(20)).await;
+        // Simulate measurement delay, on top of integrating the input
+        let delay = self.latency(self.config.measurement_latency, DEFAULT_MEASUREMENT_LATENCY);
+        tokio::time::sleep(delay + Duration::from_secs_f64(self.nplc / LINE_FREQUENCY_HZ)).await;
+
+        if let Some(rate) = self.config.fault_rate {
+            if self.latency_rng.gen_bool(rate) {
+                return Err(Error::Timeout);
+            }
+        }
+        let drop_now = match self.config.disconnect_after {
+            Some(after) if !self.dropped && self.measurement_count >= after => {
+                self.dropped = true;
+                true
+            }
+            _ => false,
+        };
+        if drop_now
+            || self
+                .config
+                .disconnect_rate
+                .is_some_and(|rate| self.latency_rng.gen_bool(rate))
+        {
+            tracing::warn!("Simulating a mock disconnect");
+            self.connected = false;
+            self.profile = None;
+            self.started_at = None;
+            return Err(Error::Connection("Simulated disconnect".to_string()));
+        }
 
+        // A held display keeps showing the frozen reading.
+        if let Some(held) = &self.held {
+            return Ok(Measurement {
+                timestamp: Some(chrono::Utc::now()),
+                ..held.clone()
+            });
+        }
         Ok(self.generate_measurement())
     }
 
+    async fn get_all_measurements(&mut self) -> Result<Vec<DisplayReading>> {
+        let primary = self.get_measurement().await?;
+        let secondary = self.secondary_reading(&primary);
+
+        let mut readings = vec![DisplayReading {
+            reading_id: "PRIMARY".to_string(),
+            measurement: primary,
+        }];
+        if let Some(measurement) = secondary {
+            readings.push(DisplayReading {
+                reading_id: "SECONDARY".to_string(),
+                measurement,
+            });
+        }
+        Ok(readings)
+    }
+
+    async fn get_full_display(&mut self) -> Result<DisplayData> {
+        let mut readings = self.get_all_measurements().await?;
+        // Like the meter, show MIN MAX AVG while recording.
+        if let Some(recording) = self.recording.clone().filter(|r| r.stopped_at.is_none()) {
+            for (reading_id, value) in [
+                ("MINIMUM", recording.minimum),
+                ("MAXIMUM", recording.maximum),
+                ("AVERAGE", recording.sum / recording.count as f64),
+            ] {
+                readings.push(DisplayReading {
+                    reading_id: reading_id.to_string(),
+                    measurement: Measurement::new(
+                        value,
+                        recording.unit.clone(),
+                        MeasurementState::Normal,
+                        MeasurementAttribute::None,
+                    ),
+                });
+            }
+        }
+        DisplayData::from_readings(readings)
+    }
+
     async fn reset(&mut self) -> Result<()> {
         if !self.connected {
             return Err(Error::Connection("Not connected".to_string()));
@@ -311,6 +852,8 @@ impl Device for MockDevice {
 
         self.measurement_count = 0;
         self.started_at = Some(Instant::now());
+        self.frequency_filter = FrequencyFilter::Off;
+        self.secondary_function = SecondaryFunction::None;
         tracing::info!("Mock device reset");
         Ok(())
     }
@@ -319,6 +862,7 @@ impl Device for MockDevice {
         if !self.connected {
             return Err(Error::Connection("Not connected".to_string()));
         }
+        validate_raw_command(command)?;
 
         // Simulate command processing delay
         tokio::time::sleep(Duration::from_millis(30)).await;
@@ -328,17 +872,7 @@ impl Device for MockDevice {
             "ID" => Ok("0\rMOCK-MULTIMETER,V1.0.0-MOCK,MOCK123456\r".to_string()),
             "QM" => {
                 let measurement = self.generate_measurement();
-                let unit_str = match measurement.unit {
-                    Unit::VoltDc => "VDC",
-                    Unit::VoltAc => "VAC",
-                    Unit::AmpDc => "ADC",
-                    Unit::AmpAc => "AAC",
-                    Unit::Ohm => "OHM",
-                    Unit::Hertz => "Hz",
-                    Unit::Celsius => "CEL",
-                    Unit::Fahrenheit => "FAR",
-                    _ => "NONE",
-                };
+                let unit_str = measurement.unit.symbol();
                 let state_str = match measurement.state {
                     MeasurementState::Normal => "NORMAL",
                     MeasurementState::Overload => "OL",
@@ -359,4 +893,659 @@ impl Device for MockDevice {
             _ => Ok("1\r".to_string()), // Syntax error for unknown commands
         }
     }
+
+    async fn get_settings(&mut self) -> Result<DeviceSettings> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        Ok(DeviceSettings {
+            frequency_filter: Some(self.frequency_filter),
+            secondary_function: Some(self.secondary_function),
+            display_brightness: Some(self.display_brightness),
+            display_contrast: Some(self.display_contrast),
+            nplc: Some(self.nplc),
+            high_input_impedance: Some(self.high_input_impedance),
+            auto_save: Some(self.auto_save),
+            ..DeviceSettings::default()
+        })
+    }
+
+    async fn set_frequency_filter(&mut self, filter: FrequencyFilter) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+        if self.profile.map(|profile| profile.unit()) != Some(Unit::Hertz) {
+            return Err(Error::Config(
+                "The frequency filter only applies in frequency mode".to_string(),
+            ));
+        }
+
+        self.frequency_filter = filter;
+        tracing::info!("Mock device frequency filter set to {:?}", filter);
+        Ok(())
+    }
+
+    async fn set_secondary_function(&mut self, function: SecondaryFunction) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+        let primary = self
+            .profile
+            .map(|profile| profile.unit())
+            .ok_or_else(|| Error::Config("No measurement function is active yet".to_string()))?;
+        function.check_compatible(primary)?;
+
+        self.secondary_function = function;
+        tracing::info!("Mock device secondary function set to {:?}", function);
+        Ok(())
+    }
+
+    async fn set_display_brightness(&mut self, level: u8) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+        if level > MAX_BRIGHTNESS {
+            return Err(Error::Config(format!(
+                "Brightness level {} is out of range 0-{}",
+                level, MAX_BRIGHTNESS
+            )));
+        }
+
+        self.display_brightness = level;
+        tracing::info!("Mock device display brightness set to {}", level);
+        Ok(())
+    }
+
+    async fn set_display_contrast(&mut self, level: u8) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+        if level > MAX_CONTRAST {
+            return Err(Error::Config(format!(
+                "Contrast level {} is out of range 0-{}",
+                level, MAX_CONTRAST
+            )));
+        }
+
+        self.display_contrast = level;
+        tracing::info!("Mock device display contrast set to {}", level);
+        Ok(())
+    }
+
+    async fn set_nplc(&mut self, cycles: f64) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+        if !NPLC_VALUES.contains(&cycles) {
+            return Err(Error::Config(format!(
+                "Unsupported NPLC {}, expected one of {:?}",
+                cycles, NPLC_VALUES
+            )));
+        }
+
+        self.nplc = cycles;
+        tracing::info!("Mock device integration set to {} NPLC", cycles);
+        Ok(())
+    }
+
+    async fn available_functions(&mut self) -> Result<Vec<MeasurementFunction>> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        // The mock's dial never leaves the DC volts position.
+        Ok(SwitchPosition::VoltsDc.functions().to_vec())
+    }
+
+    async fn zero_adjust(&mut self) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        self.zero = None;
+        let reading = self.generate_measurement();
+        self.zero = Some((reading.unit.clone(), reading.value));
+        tracing::info!(
+            "Mock device zeroed at {} {}",
+            reading.value,
+            reading.unit.symbol()
+        );
+        Ok(())
+    }
+
+    async fn clear_zero(&mut self) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        self.zero = None;
+        Ok(())
+    }
+
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        Ok(SelfTestResult {
+            passed: true,
+            fault_codes: Vec::new(),
+        })
+    }
+
+    async fn set_high_input_impedance(&mut self, enabled: bool) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+        if self.profile.map(|profile| profile.unit()) != Some(Unit::VoltDc) {
+            return Err(Error::Config(
+                "High input impedance only applies to DC volts".to_string(),
+            ));
+        }
+
+        self.high_input_impedance = enabled;
+        tracing::info!("Mock device high input impedance set to {}", enabled);
+        Ok(())
+    }
+
+    async fn set_auto_save(&mut self, enabled: bool) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        self.auto_save = enabled;
+        self.last_auto_save = None;
+        tracing::info!("Mock device auto-save set to {}", enabled);
+        Ok(())
+    }
+
+    fn configure_mock(&mut self, config: MockConfig) -> Result<()> {
+        config.validate()?;
+
+        if let Some(seed) = config.seed {
+            self.latency_rng = StdRng::seed_from_u64(seed);
+        }
+        let new_profile = config.profile.is_some();
+        self.config = config;
+        self.dropped = false;
+        if new_profile && self.connected {
+            self.profile = Some(self.new_profile(&mut rand::thread_rng()));
+            self.started_at = Some(Instant::now());
+            self.secondary_function = SecondaryFunction::None;
+            self.high_input_impedance = false;
+        }
+
+        tracing::info!("Mock device reconfigured: {:?}", self.config);
+        Ok(())
+    }
+
+    async fn list_saved_setups(&mut self) -> Result<Vec<SavedSetupInfo>> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        Ok(MockMeasurementProfile::saved_setups()
+            .iter()
+            .enumerate()
+            .map(|(index, slot)| SavedSetupInfo {
+                index,
+                name: slot.map(|(name, _)| name.to_string()),
+            })
+            .collect())
+    }
+
+    async fn recall_setup(&mut self, index: usize) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        let setups = MockMeasurementProfile::saved_setups();
+        let slot = setups.get(index).ok_or_else(|| {
+            Error::Config(format!(
+                "Setup slot {} does not exist (0-{})",
+                index,
+                setups.len() - 1
+            ))
+        })?;
+        let (name, profile) =
+            slot.ok_or_else(|| Error::Device(format!("Setup slot {} is empty", index)))?;
+
+        self.profile = Some(profile);
+        self.started_at = Some(Instant::now());
+        self.secondary_function = SecondaryFunction::None;
+        self.high_input_impedance = false;
+        tracing::info!("Mock device recalled setup {} ({})", index, name);
+        Ok(())
+    }
+
+    async fn start_recording(&mut self) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        // The recording starts from the reading on display.
+        let first = self.generate_measurement();
+        self.recording = Some(MockRecording::new(
+            &first,
+            chrono::Utc::now() + self.clock_offset,
+        ));
+        tracing::info!("Mock device started MIN MAX recording");
+        Ok(())
+    }
+
+    async fn stop_recording(&mut self) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        let recording = self
+            .recording
+            .as_mut()
+            .filter(|recording| recording.stopped_at.is_none())
+            .ok_or_else(|| Error::Device("Not recording".to_string()))?;
+        recording.stopped_at = Some(chrono::Utc::now() + self.clock_offset);
+        tracing::info!("Mock device stopped MIN MAX recording");
+        Ok(())
+    }
+
+    async fn get_recording_summary(&mut self) -> Result<RecordingSummary> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        let recording = self
+            .recording
+            .clone()
+            .ok_or_else(|| Error::Device("No MIN MAX recording".to_string()))?;
+        let end = recording
+            .stopped_at
+            .unwrap_or_else(|| chrono::Utc::now() + self.clock_offset);
+        Ok(RecordingSummary {
+            unit: recording.unit,
+            minimum: recording.minimum,
+            maximum: recording.maximum,
+            average: recording.sum / recording.count as f64,
+            started_at: recording.started_at,
+            duration_secs: (end - recording.started_at).num_milliseconds() as f64 / 1000.0,
+            active: recording.stopped_at.is_none(),
+        })
+    }
+
+    async fn set_hold(&mut self, enabled: bool) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        if !enabled {
+            self.held = None;
+            tracing::info!("Mock device released hold");
+        } else if self.held.is_none() {
+            let mut held = self.generate_measurement();
+            held.metadata.held = true;
+            self.held = Some(held);
+            tracing::info!("Mock device holding the display");
+        }
+        Ok(())
+    }
+
+    async fn trigger_capture(&mut self) -> Result<Measurement> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        let mut measurement = self.generate_measurement();
+        if self.held.is_some() {
+            measurement.metadata.held = true;
+            self.held = Some(measurement.clone());
+        }
+        Ok(measurement)
+    }
+
+    async fn get_calibration_info(&mut self) -> Result<CalibrationInfo> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        Ok(CalibrationInfo {
+            calibration_date: chrono::NaiveDate::from_ymd_opt(2026, 3, 2)
+                .ok_or_else(|| Error::Device("Invalid calibration date".to_string()))?,
+            calibration_count: 4,
+        })
+    }
+
+    async fn list_saved_measurements(&mut self) -> Result<Vec<SavedMeasurement>> {
+        let mut saved = Vec::new();
+        for index in 0..MockMeasurementProfile::saved_measurements().len() {
+            saved.extend(self.get_saved_measurement(index).await?);
+        }
+        Ok(saved)
+    }
+
+    async fn get_saved_measurement(&mut self, index: usize) -> Result<Option<SavedMeasurement>> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        let slots = MockMeasurementProfile::saved_measurements();
+        let slot = slots.get(index).ok_or_else(|| {
+            Error::Config(format!(
+                "Measurement slot {} does not exist (0-{})",
+                index,
+                slots.len() - 1
+            ))
+        })?;
+        Ok(slot.clone().and_then(|(value, unit, seconds)| {
+            let saved_at = chrono::DateTime::from_timestamp(seconds, 0)?;
+            let mut measurement = Measurement::new(
+                value,
+                unit,
+                MeasurementState::Normal,
+                MeasurementAttribute::None,
+            );
+            measurement.timestamp = Some(saved_at);
+            Some(SavedMeasurement {
+                index,
+                measurement,
+                saved_at,
+            })
+        }))
+    }
+
+    async fn get_clock(&mut self) -> Result<chrono::DateTime<chrono::Utc>> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        Ok(chrono::Utc::now() + self.clock_offset)
+    }
+
+    async fn set_clock(&mut self, time: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        if !self.connected {
+            return Err(Error::Connection("Not connected".to_string()));
+        }
+
+        self.clock_offset = time - chrono::Utc::now();
+        tracing::info!("Mock device clock set to {}", time);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A mock connected without delay, simulating `kind`
+    async fn connected(kind: MockProfileKind) -> MockDevice {
+        let mut device = MockDevice::with_config(MockConfig {
+            seed: Some(1),
+            connect_latency: Some(LatencyDistribution::Fixed { ms: 0 }),
+            measurement_latency: Some(LatencyDistribution::Fixed { ms: 0 }),
+            profile: Some(kind),
+            ..MockConfig::default()
+        });
+        device.connect().await.unwrap();
+        device
+    }
+
+    /// Time `get_measurement` takes, on the paused test clock
+    async fn measurement_delay(device: &mut MockDevice) -> Duration {
+        let started = tokio::time::Instant::now();
+        device.get_measurement().await.unwrap();
+        started.elapsed()
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn measurement_latency_stays_within_the_configured_range() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+        device.config.measurement_latency = Some(LatencyDistribution::Uniform {
+            min_ms: 10,
+            max_ms: 30,
+        });
+        // Integrating over the default 1 NPLC adds 20 ms at 50 Hz.
+        let integration = Duration::from_millis(20);
+
+        let mut delays = Vec::new();
+        for _ in 0..50 {
+            delays.push(measurement_delay(&mut device).await - integration);
+        }
+        let range = Duration::from_millis(10)..=Duration::from_millis(30);
+        assert!(
+            delays.iter().all(|delay| range.contains(delay)),
+            "{:?}",
+            delays
+        );
+        assert!(delays.iter().any(|delay| *delay != delays[0]));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn long_tail_latency_takes_the_tail_delay() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+        device.config.measurement_latency = Some(LatencyDistribution::LongTail {
+            base_ms: 5,
+            tail_ms: 200,
+            tail_probability: 1.0,
+        });
+
+        let delay = measurement_delay(&mut device).await;
+        assert_eq!(delay, Duration::from_millis(220));
+    }
+
+    #[tokio::test]
+    async fn frequency_filter_is_reported_on_frequency_readings() {
+        let mut device = connected(MockProfileKind::FrequencyPulse).await;
+
+        device
+            .set_frequency_filter(FrequencyFilter::LowPass)
+            .await
+            .unwrap();
+
+        let reading = device.get_measurement().await.unwrap();
+        assert_eq!(
+            reading.metadata.frequency_filter,
+            Some(FrequencyFilter::LowPass)
+        );
+    }
+
+    #[tokio::test]
+    async fn frequency_filter_outside_frequency_mode_is_a_config_error() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        let error = device
+            .set_frequency_filter(FrequencyFilter::LowPass)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn secondary_function_adds_a_secondary_reading() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        device
+            .set_secondary_function(SecondaryFunction::Hertz)
+            .await
+            .unwrap();
+
+        let readings = device.get_all_measurements().await.unwrap();
+        let secondary = readings
+            .iter()
+            .find(|r| r.reading_id == "SECONDARY")
+            .unwrap();
+        assert_eq!(secondary.measurement.unit, Unit::Hertz);
+    }
+
+    #[tokio::test]
+    async fn secondary_function_must_suit_the_primary_one() {
+        let mut device = connected(MockProfileKind::TemperatureDrift).await;
+
+        let error = device
+            .set_secondary_function(SecondaryFunction::CrestFactor)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn saved_measurement_slots() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        let saved = device.get_saved_measurement(0).await.unwrap().unwrap();
+        assert_eq!(saved.index, 0);
+        assert_eq!(saved.measurement.unit, Unit::VoltDc);
+        assert_eq!(saved.measurement.timestamp, Some(saved.saved_at));
+        assert!(device.get_saved_measurement(2).await.unwrap().is_none());
+        let error = device.get_saved_measurement(4).await.unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn calibration_record_needs_a_connection() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        let calibration = device.get_calibration_info().await.unwrap();
+        assert_eq!(calibration.calibration_count, 4);
+
+        device.disconnect().await.unwrap();
+        let error = device.get_calibration_info().await.unwrap_err();
+        assert!(matches!(error, Error::Connection(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn display_levels_are_stored_within_range() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        device.set_display_brightness(2).await.unwrap();
+        device.set_display_contrast(MAX_CONTRAST).await.unwrap();
+        let settings = device.get_settings().await.unwrap();
+        assert_eq!(settings.display_brightness, Some(2));
+        assert_eq!(settings.display_contrast, Some(MAX_CONTRAST));
+
+        let error = device
+            .set_display_brightness(MAX_BRIGHTNESS + 1)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn readings_carry_the_nplc() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        device.set_nplc(10.0).await.unwrap();
+        assert_eq!(
+            device.get_measurement().await.unwrap().metadata.nplc,
+            Some(10.0)
+        );
+
+        let error = device.set_nplc(3.0).await.unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn zero_is_subtracted_until_cleared() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        device.zero_adjust().await.unwrap();
+        let zeroed = device.get_measurement().await.unwrap();
+        let (unit, zero) = device.zero.clone().unwrap();
+        assert_eq!(unit, Unit::VoltDc);
+        assert_eq!(zeroed.metadata.zero_offset, Some(zero));
+
+        device.clear_zero().await.unwrap();
+        assert_eq!(
+            device.get_measurement().await.unwrap().metadata.zero_offset,
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn self_test_passes_without_faults() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        let result = device.self_test().await.unwrap();
+        assert!(result.passed);
+        assert!(result.fault_codes.is_empty());
+    }
+
+    #[tokio::test]
+    async fn high_input_impedance_applies_to_dc_volts_only() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+        device.set_high_input_impedance(true).await.unwrap();
+        let reading = device.get_measurement().await.unwrap();
+        assert_eq!(reading.metadata.high_input_impedance, Some(true));
+
+        let mut device = connected(MockProfileKind::ResistanceSweep).await;
+        let error = device.set_high_input_impedance(true).await.unwrap_err();
+        assert!(matches!(error, Error::Config(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn auto_save_marks_a_settled_reading_once_per_period() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+        device.started_at = Some(Instant::now() - Duration::from_secs(60));
+
+        device.set_auto_save(true).await.unwrap();
+        let saved = device.get_measurement().await.unwrap();
+        let next = device.get_measurement().await.unwrap();
+
+        assert!(saved.metadata.auto_saved);
+        assert!(!next.metadata.auto_saved);
+    }
+
+    #[tokio::test]
+    async fn recording_summarises_readings_until_stopped() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        device.start_recording().await.unwrap();
+        for _ in 0..3 {
+            device.get_measurement().await.unwrap();
+        }
+        let summary = device.get_recording_summary().await.unwrap();
+        assert!(summary.active);
+        assert_eq!(summary.unit, Unit::VoltDc);
+        assert!(summary.minimum <= summary.average && summary.average <= summary.maximum);
+
+        device.stop_recording().await.unwrap();
+        assert!(!device.get_recording_summary().await.unwrap().active);
+        let error = device.stop_recording().await.unwrap_err();
+        assert!(matches!(error, Error::Device(_)), "{:?}", error);
+    }
+
+    #[tokio::test]
+    async fn hold_freezes_the_reading_until_a_capture_or_release() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+
+        device.set_hold(true).await.unwrap();
+        let held = device.get_measurement().await.unwrap();
+        assert!(held.metadata.held);
+        assert_eq!(device.get_measurement().await.unwrap().value, held.value);
+
+        let captured = device.trigger_capture().await.unwrap();
+        assert!(captured.metadata.held);
+        assert_eq!(
+            device.get_measurement().await.unwrap().value,
+            captured.value
+        );
+
+        device.set_hold(false).await.unwrap();
+        assert!(!device.get_measurement().await.unwrap().metadata.held);
+    }
+
+    #[tokio::test]
+    async fn disconnect_after_drops_the_link_once() {
+        let mut device = connected(MockProfileKind::VoltageSine).await;
+        device.config.disconnect_after = Some(2);
+
+        device.get_measurement().await.unwrap();
+        device.get_measurement().await.unwrap();
+        let error = device.get_measurement().await.unwrap_err();
+        assert!(matches!(error, Error::Connection(_)), "{:?}", error);
+        assert!(!device.is_connected());
+
+        device.connect().await.unwrap();
+        for _ in 0..5 {
+            device.get_measurement().await.unwrap();
+        }
+    }
 }
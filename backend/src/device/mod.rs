@@ -2,22 +2,73 @@
 //!
 //! This module provides abstractions for communicating with different types of multimeters.
 
+pub mod accuracy;
 pub mod fluke;
+pub mod function;
+pub mod keysight;
 pub mod mock;
+#[cfg(test)]
+pub(crate) mod test_port;
+pub mod wire;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 use serde::{Deserialize, Serialize};
 
+/// Options supplied when creating a device, taken from the connect request
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceOptions {
+    /// Mock behaviour; ignored for real hardware
+    pub mock: mock::MockConfig,
+    /// Display units for this device, overriding the global preferences
+    pub display_preferences: crate::config::DisplayPreferences,
+    /// Wait this long for the input to settle when the measured function
+    /// changes between readings
+    pub settle_ms: Option<u64>,
+    /// Flag readings as warming up for this long after connect or a reset
+    pub warm_up_secs: Option<u64>,
+    /// Moving average applied to live readings; off when absent
+    pub smoothing: Option<crate::config::Smoothing>,
+    /// Readings whose sign is flipped; none when absent
+    pub invert_sign: Option<crate::config::SignInversion>,
+    /// Address of the meter on a port shared with other meters
+    pub address: Option<u8>,
+    /// Clock that timestamps live readings
+    pub timestamp_source: TimestampSource,
+    /// Pacing of serial command writes; ignored for the mock
+    pub write_pacing: fluke::WritePacing,
+    /// Serial speed, the device type's default when absent; ignored for
+    /// the mock
+    pub baud: Option<u32>,
+    /// Reopen the device with backoff when it drops, overriding the
+    /// configured `auto_reconnect`
+    pub auto_reconnect: Option<bool>,
+}
+
+/// Clock a reading's timestamp comes from
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimestampSource {
+    /// Time the host received the reading
+    #[default]
+    Host,
+    /// The meter's real-time clock, queried after each reading; more
+    /// accurate but costs a round trip
+    Device,
+}
+
 /// Supported device types
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DeviceType {
     Fluke289,
     Fluke287,
+    /// Keysight (Agilent) U1241/U1242 handhelds
+    KeysightU124x,
     Mock,
 }
 
 /// Measurement units
-#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Unit {
     None,
     VoltDc,
@@ -35,11 +86,85 @@ pub enum Unit {
     Farad,
     Celsius,
     Fahrenheit,
+    /// Only produced by host-side conversion; the meters report °C or °F
+    Kelvin,
     Percent,
     DecibelM,
     DecibelV,
     Decibel,
     CrestFactor,
+    /// A token not listed here, kept as the device sent it; empty when
+    /// the device reported no unit and none could be inferred
+    Unknown(String),
+}
+
+impl Unit {
+    /// Unit name as the Fluke remote protocol spells it, e.g. `VDC`
+    pub fn symbol(&self) -> &str {
+        match self {
+            Self::None => "NONE",
+            Self::VoltDc => "VDC",
+            Self::VoltAc => "VAC",
+            Self::AmpDc => "ADC",
+            Self::AmpAc => "AAC",
+            Self::VoltAcPlusDc => "VAC_PLUS_DC",
+            Self::AmpAcPlusDc => "AAC_PLUS_DC",
+            Self::Volt => "V",
+            Self::Amp => "A",
+            Self::Ohm => "OHM",
+            Self::Siemens => "S",
+            Self::Hertz => "Hz",
+            Self::Second => "SEC",
+            Self::Farad => "F",
+            Self::Celsius => "CEL",
+            Self::Fahrenheit => "FAR",
+            Self::Kelvin => "K",
+            Self::Percent => "PCT",
+            Self::DecibelM => "dBm",
+            Self::DecibelV => "dBV",
+            Self::Decibel => "dB",
+            Self::CrestFactor => "CREST_FACTOR",
+            Self::Unknown(raw) if raw.is_empty() => "UNKNOWN",
+            Self::Unknown(raw) => raw,
+        }
+    }
+}
+
+/// Physical quantity a unit measures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UnitCategory {
+    None,
+    Voltage,
+    Current,
+    Resistance,
+    Conductance,
+    Frequency,
+    Time,
+    Capacitance,
+    Temperature,
+    /// Percentages and crest factor
+    Ratio,
+    /// dBm, dBV and relative dB
+    Level,
+}
+
+impl Unit {
+    pub fn category(&self) -> UnitCategory {
+        match self {
+            Self::None | Self::Unknown(_) => UnitCategory::None,
+            Self::VoltDc | Self::VoltAc | Self::VoltAcPlusDc | Self::Volt => UnitCategory::Voltage,
+            Self::AmpDc | Self::AmpAc | Self::AmpAcPlusDc | Self::Amp => UnitCategory::Current,
+            Self::Ohm => UnitCategory::Resistance,
+            Self::Siemens => UnitCategory::Conductance,
+            Self::Hertz => UnitCategory::Frequency,
+            Self::Second => UnitCategory::Time,
+            Self::Farad => UnitCategory::Capacitance,
+            Self::Celsius | Self::Fahrenheit | Self::Kelvin => UnitCategory::Temperature,
+            Self::Percent | Self::CrestFactor => UnitCategory::Ratio,
+            Self::DecibelM | Self::DecibelV | Self::Decibel => UnitCategory::Level,
+        }
+    }
 }
 
 /// Measurement state
@@ -54,6 +179,21 @@ pub enum MeasurementState {
     Discharge, // Capacitance discharge error
 }
 
+impl MeasurementState {
+    /// State name as the Fluke remote protocol spells it, e.g. `OL`
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::Normal => "NORMAL",
+            Self::Invalid => "INVALID",
+            Self::Blank => "BLANK",
+            Self::Overload => "OL",
+            Self::OverloadNegative => "OL_MINUS",
+            Self::OpenThermocouple => "OPEN_TC",
+            Self::Discharge => "DISCHARGE",
+        }
+    }
+}
+
 /// Measurement attribute
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub enum MeasurementAttribute {
@@ -68,18 +208,514 @@ pub enum MeasurementAttribute {
     HighCurrent, // Displayed value is flashing
 }
 
+impl MeasurementAttribute {
+    /// Attribute name as the Fluke remote protocol spells it, e.g. `OPEN_CIRCUIT`
+    pub fn symbol(self) -> &'static str {
+        match self {
+            Self::None => "NONE",
+            Self::OpenCircuit => "OPEN_CIRCUIT",
+            Self::ShortCircuit => "SHORT_CIRCUIT",
+            Self::GlitchCircuit => "GLITCH_CIRCUIT",
+            Self::GoodDiode => "GOOD_DIODE",
+            Self::LowOhms => "LEO_OHMS",
+            Self::NegativeEdge => "NEGATIVE_EDGE",
+            Self::PositiveEdge => "POSITIVE_EDGE",
+            Self::HighCurrent => "HIGH_CURRENT",
+        }
+    }
+}
+
+/// Input filter applied to frequency (counter) measurements
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FrequencyFilter {
+    #[default]
+    Off,
+    /// Low-pass input filter rejecting high-frequency noise
+    LowPass,
+}
+
+/// Outcome of checking a reading against the device's limits
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Verdict {
+    Pass,
+    Fail,
+}
+
+/// Reading shown on the meter's secondary display alongside the primary
+/// function
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SecondaryFunction {
+    #[default]
+    None,
+    Hertz,
+    DutyCycle,
+    PulseWidth,
+    Dbm,
+    Dbv,
+    DbmHertz,
+    DbvHertz,
+    CrestFactor,
+    PeakMinMax,
+}
+
+impl SecondaryFunction {
+    /// Check the meter offers this secondary reading for a primary reading
+    /// in `primary`
+    pub fn check_compatible(self, primary: Unit) -> Result<()> {
+        let ac = matches!(
+            primary,
+            Unit::VoltAc | Unit::VoltAcPlusDc | Unit::AmpAc | Unit::AmpAcPlusDc
+        );
+        let volts_or_amps = ac || matches!(primary, Unit::VoltDc | Unit::AmpDc);
+        let compatible = match self {
+            Self::None => true,
+            Self::Hertz | Self::DutyCycle | Self::PulseWidth | Self::PeakMinMax => volts_or_amps,
+            Self::Dbm | Self::Dbv | Self::DbmHertz | Self::DbvHertz => {
+                matches!(primary, Unit::VoltAc | Unit::VoltAcPlusDc)
+            }
+            Self::CrestFactor => ac,
+        };
+        if compatible {
+            Ok(())
+        } else {
+            Err(Error::Config(format!(
+                "Secondary function {:?} is not available while measuring {:?}",
+                self, primary
+            )))
+        }
+    }
+}
+
+/// Reading taken as 0 dB, which later readings in its unit are expressed
+/// relative to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DbReference {
+    pub value: f64,
+    pub unit: Unit,
+}
+
+impl DbReference {
+    /// Take `reading` as the reference; only in-range dB readings qualify
+    pub fn capture(reading: &Measurement) -> Result<Self> {
+        if reading.unit.category() != UnitCategory::Level {
+            return Err(Error::Config(format!(
+                "A dB reference needs a dB reading, the device is measuring {:?}",
+                reading.unit
+            )));
+        }
+        if reading.state != MeasurementState::Normal {
+            return Err(Error::Config(format!(
+                "A dB reference needs an in-range reading, not {:?}",
+                reading.state
+            )));
+        }
+        Ok(Self {
+            value: reading.value,
+            unit: reading.unit.clone(),
+        })
+    }
+}
+
+/// Conditions a measurement was taken under
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct MeasurementMetadata {
+    /// Input filter in effect, for frequency readings
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_filter: Option<FrequencyFilter>,
+    /// User offset in °C added to a temperature reading by the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub temperature_offset: Option<f64>,
+    /// Function of a `SECONDARY` display reading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub secondary_function: Option<SecondaryFunction>,
+    /// Reading before smoothing, present when `value` is smoothed
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_value: Option<f64>,
+    /// The host flipped the sign of the reading
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub sign_inverted: bool,
+    /// Host arrival time, present when `timestamp` was snapped to the
+    /// polling grid
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub raw_timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// The reading arrived further from its grid point than the tolerance
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub timestamp_drifted: bool,
+    /// Temperature of the meter's internal cold-junction sensor in °C, for
+    /// thermocouple readings when the device reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reference_temperature: Option<f64>,
+    /// Null offset the device subtracted from `value`, while a zero is set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zero_offset: Option<f64>,
+    /// Clock that stamped a live reading
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp_source: Option<TimestampSource>,
+    /// Integration time of the reading in power-line cycles
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nplc: Option<f64>,
+    /// The input was switched to high impedance (>10 GΩ) instead of the
+    /// usual 10 MΩ, present on readings where the choice applies
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub high_input_impedance: Option<bool>,
+    /// Reference a relative dB reading was taken against, in the unit the
+    /// device reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub db_reference: Option<DbReference>,
+    /// Taken before the device's warm-up period was over, so it may not
+    /// meet the specified accuracy
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub warming_up: bool,
+    /// The meter auto-saved this reading to its memory once it was stable
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_saved: bool,
+    /// Result against the device's limits, present while limits are set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub verdict: Option<Verdict>,
+    /// Power of ten `value` and `uncertainty` are scaled by, e.g. `-3` for
+    /// a value in millivolts. The devices report base units and leave it
+    /// out; clients that scale readings record it so `to_base_units` can
+    /// undo it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub unit_multiplier: Option<i32>,
+    /// The display was held; the reading is the frozen one, not a fresh
+    /// measurement of the input
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub held: bool,
+}
+
+impl MeasurementMetadata {
+    pub fn is_empty(&self) -> bool {
+        self.frequency_filter.is_none()
+            && self.temperature_offset.is_none()
+            && self.secondary_function.is_none()
+            && self.raw_value.is_none()
+            && !self.sign_inverted
+            && self.raw_timestamp.is_none()
+            && !self.timestamp_drifted
+            && self.reference_temperature.is_none()
+            && self.zero_offset.is_none()
+            && self.timestamp_source.is_none()
+            && self.nplc.is_none()
+            && self.high_input_impedance.is_none()
+            && self.db_reference.is_none()
+            && !self.warming_up
+            && !self.auto_saved
+            && self.verdict.is_none()
+            && self.unit_multiplier.is_none()
+            && !self.held
+    }
+}
+
+/// Device settings configurable over the API; `None` where the device
+/// can't report a setting
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DeviceSettings {
+    pub frequency_filter: Option<FrequencyFilter>,
+    pub secondary_function: Option<SecondaryFunction>,
+    /// Offset in °C applied to temperature readings
+    pub temperature_offset: Option<f64>,
+    /// Readings whose sign the host flips
+    pub invert_sign: Option<crate::config::SignInversion>,
+    /// Backlight brightness level, 0 being off
+    pub display_brightness: Option<u8>,
+    pub display_contrast: Option<u8>,
+    /// Integration time in power-line cycles
+    pub nplc: Option<f64>,
+    /// High input impedance in low DC voltage ranges
+    pub high_input_impedance: Option<bool>,
+    /// Saving each reading automatically once it is stable
+    pub auto_save: Option<bool>,
+    /// 0 dB point of relative dB readings, while one is captured
+    pub db_reference: Option<DbReference>,
+}
+
 /// A single measurement reading
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
 pub struct Measurement {
     pub value: f64,
+    /// Accuracy of `value` per the device specifications, ± in `unit`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub uncertainty: Option<f64>,
     pub unit: Unit,
     pub state: MeasurementState,
     pub attribute: MeasurementAttribute,
     pub timestamp: Option<chrono::DateTime<chrono::Utc>>,
+    /// Per-device acquisition counter, strictly increasing across reads.
+    /// Assigned by the communication layer; `0` means not yet assigned.
+    #[serde(default)]
+    pub sequence: u64,
+    #[serde(default, skip_serializing_if = "MeasurementMetadata::is_empty")]
+    pub metadata: MeasurementMetadata,
+}
+
+impl Measurement {
+    /// Create a measurement stamped with the current host time
+    pub fn new(
+        value: f64,
+        unit: Unit,
+        state: MeasurementState,
+        attribute: MeasurementAttribute,
+    ) -> Self {
+        Self {
+            value,
+            uncertainty: None,
+            unit,
+            state,
+            attribute,
+            timestamp: Some(chrono::Utc::now()),
+            sequence: 0,
+            metadata: MeasurementMetadata::default(),
+        }
+    }
+}
+
+/// A reading from one area of the meter display
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DisplayReading {
+    /// Display area the reading belongs to (e.g. `PRIMARY`, `SECONDARY`, `MINIMUM`)
+    pub reading_id: String,
+    pub measurement: Measurement,
+}
+
+/// Display area of a reading besides the primary one, after the QDDA
+/// `readingID` it was reported under
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DisplayRole {
+    /// Live reading in the status bar, e.g. while the primary display is held
+    Live,
+    Secondary,
+    /// Live relative reading in the status bar
+    RelativeLive,
+    Bargraph,
+    Minimum,
+    Maximum,
+    Average,
+    /// Reference of a relative reading
+    RelativeReference,
+    DbReference,
+    TemperatureOffset,
+    /// An area not in the specification, with its id as reported
+    Other(String),
+}
+
+impl DisplayRole {
+    /// Role of a QDDA `readingID` other than `PRIMARY`
+    pub fn from_reading_id(reading_id: &str) -> Self {
+        match reading_id {
+            "LIVE" => Self::Live,
+            "SECONDARY" => Self::Secondary,
+            "REL_LIVE" => Self::RelativeLive,
+            "BARGRAPH" => Self::Bargraph,
+            "MINIMUM" => Self::Minimum,
+            "MAXIMUM" => Self::Maximum,
+            "AVERAGE" => Self::Average,
+            "REL_REFERENCE" | "REL" => Self::RelativeReference,
+            "DB_REF" => Self::DbReference,
+            "TEMP_OFFSET" => Self::TemperatureOffset,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+/// A reading of a sub-display, with the area it appears in
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct AuxiliaryReading {
+    pub role: DisplayRole,
+    pub measurement: Measurement,
+}
+
+/// Everything on the meter display: the primary reading and the readings
+/// of the other areas, in the order the device reported them
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct DisplayData {
+    pub primary: Measurement,
+    pub auxiliary: Vec<AuxiliaryReading>,
+}
+
+impl DisplayData {
+    /// Split display readings into the `PRIMARY` one and the others
+    pub fn from_readings(readings: Vec<DisplayReading>) -> Result<Self> {
+        let mut primary = None;
+        let mut auxiliary = Vec::with_capacity(readings.len().saturating_sub(1));
+        for reading in readings {
+            if reading.reading_id == "PRIMARY" && primary.is_none() {
+                primary = Some(reading.measurement);
+            } else {
+                auxiliary.push(AuxiliaryReading {
+                    role: DisplayRole::from_reading_id(&reading.reading_id),
+                    measurement: reading.measurement,
+                });
+            }
+        }
+        let primary = primary.ok_or_else(|| Error::parse("Display has no PRIMARY reading"))?;
+        Ok(Self { primary, auxiliary })
+    }
+}
+
+/// A setup memory slot on the meter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct SavedSetupInfo {
+    pub index: usize,
+    /// Name of the stored setup, `None` if the slot is empty
+    pub name: Option<String>,
+}
+
+/// A single measurement stored on the meter with its SAVE key
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct SavedMeasurement {
+    pub index: usize,
+    /// The saved reading, stamped with `saved_at`
+    pub measurement: Measurement,
+    /// When the reading was saved, by the device clock
+    pub saved_at: chrono::DateTime<chrono::Utc>,
+}
+
+/// Calibration record stored in the meter
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct CalibrationInfo {
+    /// Date of the last calibration
+    pub calibration_date: chrono::NaiveDate,
+    /// Number of calibrations performed on the meter
+    pub calibration_count: u32,
+}
+
+/// Minimum, maximum and average the meter recorded in MIN MAX mode
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct RecordingSummary {
+    pub unit: Unit,
+    pub minimum: f64,
+    pub maximum: f64,
+    pub average: f64,
+    /// When recording started, by the device clock
+    pub started_at: chrono::DateTime<chrono::Utc>,
+    /// Time recorded so far, or in total once stopped
+    pub duration_secs: f64,
+    /// The meter is still recording
+    pub active: bool,
+}
+
+/// Outcome of a device's built-in self-test
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct SelfTestResult {
+    pub passed: bool,
+    /// Faults the device reported, in its own codes; empty when it passed
+    pub fault_codes: Vec<String>,
+}
+
+/// Optional features a device supports
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct Capabilities {
+    /// Multi-reading display download (QDDA on Fluke)
+    pub display_download: bool,
+    /// Reading and setting the real-time clock
+    pub clock: bool,
+    /// Setup memory slots
+    pub saved_setups: bool,
+}
+
+impl Capabilities {
+    /// Everything supported
+    pub const ALL: Self = Self {
+        display_download: true,
+        clock: true,
+        saved_setups: true,
+    };
+
+    /// Only the core measurement commands
+    pub const NONE: Self = Self {
+        display_download: false,
+        clock: false,
+        saved_setups: false,
+    };
+}
+
+/// Longest raw command accepted from API clients
+pub const MAX_RAW_COMMAND_LEN: usize = 128;
+
+/// A command a device understands, as offered for the raw command endpoint
+#[derive(Debug, Clone, Serialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
+pub struct CommandInfo {
+    /// Command word; arguments follow after a space or comma
+    pub name: &'static str,
+    pub description: &'static str,
+    /// The device answers with an acknowledgement line
+    pub expects_ack: bool,
+    /// A payload line follows a successful acknowledgement
+    pub expects_payload: bool,
+}
+
+/// Check a raw command from an API client before it is written to a device.
+///
+/// The serial protocols terminate commands with CR, so an embedded CR or LF
+/// would smuggle in a second command. Only printable ASCII is accepted.
+pub fn validate_raw_command(command: &str) -> Result<()> {
+    if command.trim().is_empty() {
+        return Err(Error::InvalidCommand("Command is empty".to_string()));
+    }
+    if command.len() > MAX_RAW_COMMAND_LEN {
+        return Err(Error::InvalidCommand(format!(
+            "Command is {} bytes long, the maximum is {}",
+            command.len(),
+            MAX_RAW_COMMAND_LEN
+        )));
+    }
+    if let Some(c) = command
+        .chars()
+        .find(|c| !c.is_ascii() || c.is_ascii_control())
+    {
+        return Err(Error::InvalidCommand(format!(
+            "Command contains disallowed character {:?}",
+            c
+        )));
+    }
+    Ok(())
+}
+
+/// Return `Error::Unsupported` for `operation` unless `supported`
+pub(crate) fn require_capability(supported: bool, operation: &str) -> Result<()> {
+    if supported {
+        Ok(())
+    } else {
+        Err(Error::Unsupported(format!(
+            "{} is not supported by this device or firmware",
+            operation
+        )))
+    }
+}
+
+/// Translate a failed port open, calling out ports that no longer exist
+pub(crate) fn map_open_error(port_name: &str, error: serialport::Error) -> Error {
+    match error.kind() {
+        serialport::ErrorKind::NoDevice
+        | serialport::ErrorKind::Io(std::io::ErrorKind::NotFound) => Error::Connection(format!(
+            "Port {} no longer exists; the device may have been unplugged or the port name \
+                 is stale. Rescan the available ports and try again",
+            port_name
+        )),
+        _ => Error::Serial(error),
+    }
 }
 
 /// Device identification information
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "camel-case", serde(rename_all = "camelCase"))]
 pub struct DeviceInfo {
     pub model: String,
     pub serial_number: String,
@@ -92,6 +728,16 @@ pub trait Device: Send + Sync {
     /// Get device type
     fn device_type(&self) -> DeviceType;
 
+    /// Optional features of this particular unit. Only meaningful after
+    /// `identify`, which may refine them from the firmware version.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::NONE
+    }
+
+    /// Report every chunk written to or read from the serial port to `tap`.
+    /// Devices without a serial link ignore it.
+    fn set_wire_tap(&mut self, _tap: wire::WireTap) {}
+
     /// Connect to the device
     async fn connect(&mut self) -> Result<()>;
 
@@ -107,19 +753,319 @@ pub trait Device: Send + Sync {
     /// Get primary measurement
     async fn get_measurement(&mut self) -> Result<Measurement>;
 
+    /// Get every reading currently shown on the display.
+    ///
+    /// Devices without a multi-value query report the primary measurement only.
+    async fn get_all_measurements(&mut self) -> Result<Vec<DisplayReading>> {
+        Ok(vec![DisplayReading {
+            reading_id: "PRIMARY".to_string(),
+            measurement: self.get_measurement().await?,
+        }])
+    }
+
+    /// Download the whole display: the primary reading and every
+    /// sub-display reading with its role
+    async fn get_full_display(&mut self) -> Result<DisplayData> {
+        Err(Error::Unsupported("Full display download".to_string()))
+    }
+
     /// Reset device to factory settings
     async fn reset(&mut self) -> Result<()>;
 
-    /// Send a raw command and get response
+    /// Send a raw command and get response.
+    ///
+    /// Implementations reject commands failing `validate_raw_command`.
     async fn send_command(&mut self, command: &str) -> Result<String>;
+
+    /// Change the simulation parameters of a running mock device
+    fn configure_mock(&mut self, _config: mock::MockConfig) -> Result<()> {
+        Err(Error::Unsupported(
+            "Simulation control is only available for mock devices".to_string(),
+        ))
+    }
+
+    /// Read the current values of the configurable settings
+    async fn get_settings(&mut self) -> Result<DeviceSettings> {
+        Ok(DeviceSettings::default())
+    }
+
+    /// Select the input filter for frequency measurements. Only valid while
+    /// a frequency function is active.
+    async fn set_frequency_filter(&mut self, _filter: FrequencyFilter) -> Result<()> {
+        Err(Error::Unsupported(
+            "Selecting the frequency filter".to_string(),
+        ))
+    }
+
+    /// Choose the reading on the secondary display. It must be compatible
+    /// with the active primary function.
+    async fn set_secondary_function(&mut self, _function: SecondaryFunction) -> Result<()> {
+        Err(Error::Unsupported(
+            "Selecting the secondary function".to_string(),
+        ))
+    }
+
+    /// Set the display backlight brightness; levels beyond the device's
+    /// range are refused with `Error::Config`
+    async fn set_display_brightness(&mut self, _level: u8) -> Result<()> {
+        Err(Error::Unsupported(
+            "Setting the display brightness".to_string(),
+        ))
+    }
+
+    /// Set the display contrast, validated like the brightness
+    async fn set_display_contrast(&mut self, _level: u8) -> Result<()> {
+        Err(Error::Unsupported(
+            "Setting the display contrast".to_string(),
+        ))
+    }
+
+    /// Integrate each reading over `cycles` power-line cycles, trading
+    /// speed for mains noise rejection. Values the device doesn't offer are
+    /// refused with `Error::Config`.
+    async fn set_nplc(&mut self, _cycles: f64) -> Result<()> {
+        Err(Error::Unsupported(
+            "Setting the integration time".to_string(),
+        ))
+    }
+
+    /// Functions selectable in the current dial position without turning
+    /// it; empty while the dial is between positions
+    async fn available_functions(&mut self) -> Result<Vec<function::MeasurementFunction>> {
+        Err(Error::Unsupported(
+            "Querying the available functions".to_string(),
+        ))
+    }
+
+    /// Null the current reading, e.g. lead resistance in low ohms or the
+    /// offset of a current clamp. Later readings in the same function have
+    /// it subtracted until `clear_zero`.
+    async fn zero_adjust(&mut self) -> Result<()> {
+        Err(Error::Unsupported("Zero adjustment".to_string()))
+    }
+
+    /// Stop subtracting the zero taken by `zero_adjust`
+    async fn clear_zero(&mut self) -> Result<()> {
+        Err(Error::Unsupported("Zero adjustment".to_string()))
+    }
+
+    /// Run the device's built-in self-test. The device may not take
+    /// readings while it runs.
+    async fn self_test(&mut self) -> Result<SelfTestResult> {
+        Err(Error::Unsupported("Self-test".to_string()))
+    }
+
+    /// Switch the input to high impedance (>10 GΩ), which loads
+    /// high-impedance circuits less. Only low DC voltage ranges offer it;
+    /// in other functions it is refused with `Error::Config`.
+    async fn set_high_input_impedance(&mut self, _enabled: bool) -> Result<()> {
+        Err(Error::Unsupported(
+            "Setting the input impedance".to_string(),
+        ))
+    }
+
+    /// Have the meter save a reading to its memory each time it becomes
+    /// stable. Saved readings carry `metadata.auto_saved`.
+    async fn set_auto_save(&mut self, _enabled: bool) -> Result<()> {
+        Err(Error::Unsupported("Auto-save".to_string()))
+    }
+
+    /// Read the device's real-time clock
+    async fn get_clock(&mut self) -> Result<chrono::DateTime<chrono::Utc>> {
+        Err(Error::Unsupported("Reading the clock".to_string()))
+    }
+
+    /// Set the device's real-time clock
+    async fn set_clock(&mut self, _time: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        Err(Error::Unsupported("Setting the clock".to_string()))
+    }
+
+    /// List the setup memory slots
+    async fn list_saved_setups(&mut self) -> Result<Vec<SavedSetupInfo>> {
+        Err(Error::Unsupported("Saved setups".to_string()))
+    }
+
+    /// Recall the setup stored in slot `index`
+    async fn recall_setup(&mut self, _index: usize) -> Result<()> {
+        Err(Error::Unsupported("Saved setups".to_string()))
+    }
+
+    /// Start recording the minimum, maximum and average of the readings,
+    /// discarding the previous recording
+    async fn start_recording(&mut self) -> Result<()> {
+        Err(Error::Unsupported("MIN MAX recording".to_string()))
+    }
+
+    /// Stop recording; the summary stays readable until the next start
+    async fn stop_recording(&mut self) -> Result<()> {
+        Err(Error::Unsupported("MIN MAX recording".to_string()))
+    }
+
+    /// Read the minimum, maximum and average recorded so far
+    async fn get_recording_summary(&mut self) -> Result<RecordingSummary> {
+        Err(Error::Unsupported("MIN MAX recording".to_string()))
+    }
+
+    /// Freeze the display on the current reading, or release it; while
+    /// held, readings repeat the frozen value
+    async fn set_hold(&mut self, _enabled: bool) -> Result<()> {
+        Err(Error::Unsupported("Display hold".to_string()))
+    }
+
+    /// Capture a reading on demand. While held, the capture replaces the
+    /// frozen reading. Devices without a trigger take an ordinary reading.
+    async fn trigger_capture(&mut self) -> Result<Measurement> {
+        self.get_measurement().await
+    }
+
+    /// Read the date and counter of the last calibration
+    async fn get_calibration_info(&mut self) -> Result<CalibrationInfo> {
+        Err(Error::Unsupported("Reading calibration info".to_string()))
+    }
+
+    /// List the single saved measurements, skipping empty slots
+    async fn list_saved_measurements(&mut self) -> Result<Vec<SavedMeasurement>> {
+        Err(Error::Unsupported("Saved measurements".to_string()))
+    }
+
+    /// Read the saved measurement in slot `index`, `None` if the slot is empty
+    async fn get_saved_measurement(&mut self, _index: usize) -> Result<Option<SavedMeasurement>> {
+        Err(Error::Unsupported("Saved measurements".to_string()))
+    }
+}
+
+/// Commands known for raw use with a device type
+pub fn device_commands(device_type: DeviceType) -> Vec<CommandInfo> {
+    match device_type {
+        DeviceType::Fluke289 | DeviceType::Fluke287 => fluke::commands(),
+        DeviceType::KeysightU124x => keysight::commands(),
+        DeviceType::Mock => mock::commands(),
+    }
 }
 
 /// Create a device instance based on device type
-pub fn create_device(device_type: DeviceType, port: Option<String>) -> Box<dyn Device> {
+pub fn create_device(
+    device_type: DeviceType,
+    port: Option<String>,
+    options: &DeviceOptions,
+) -> Box<dyn Device> {
     match device_type {
-        DeviceType::Fluke289 | DeviceType::Fluke287 => {
-            Box::new(fluke::FlukeDevice::new(device_type, port))
+        DeviceType::Fluke289 | DeviceType::Fluke287 => Box::new(
+            fluke::FlukeDevice::new(device_type, port)
+                .with_address(options.address)
+                .with_write_pacing(options.write_pacing)
+                .with_baud_rate(options.baud),
+        ),
+        DeviceType::KeysightU124x => {
+            Box::new(keysight::KeysightDevice::new(port).with_baud_rate(options.baud))
+        }
+        DeviceType::Mock => Box::new(mock::MockDevice::with_config(options.mock.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading() -> Measurement {
+        let mut measurement = Measurement::new(
+            1.5,
+            Unit::VoltDc,
+            MeasurementState::Normal,
+            MeasurementAttribute::None,
+        );
+        measurement.metadata.unit_multiplier = Some(-3);
+        measurement
+    }
+
+    #[cfg(not(feature = "camel-case"))]
+    #[test]
+    fn fields_are_snake_case_by_default() {
+        let json = serde_json::to_value(reading()).unwrap();
+        assert_eq!(json["metadata"]["unit_multiplier"], -3);
+        assert!(json.get("sequence").is_some());
+
+        let info = DeviceInfo {
+            model: "289".to_string(),
+            serial_number: "12345678".to_string(),
+            software_version: "V1.16".to_string(),
+        };
+        let json = serde_json::to_value(info).unwrap();
+        assert_eq!(json["serial_number"], "12345678");
+        assert_eq!(json["software_version"], "V1.16");
+    }
+
+    #[cfg(feature = "camel-case")]
+    #[test]
+    fn fields_are_camel_case_with_the_feature() {
+        let json = serde_json::to_value(reading()).unwrap();
+        assert_eq!(json["metadata"]["unitMultiplier"], -3);
+        assert!(json["metadata"].get("unit_multiplier").is_none());
+
+        let info = DeviceInfo {
+            model: "289".to_string(),
+            serial_number: "12345678".to_string(),
+            software_version: "V1.16".to_string(),
+        };
+        let json = serde_json::to_value(info).unwrap();
+        assert_eq!(json["serialNumber"], "12345678");
+        assert_eq!(json["softwareVersion"], "V1.16");
+    }
+
+    #[test]
+    fn measurement_round_trips_through_json() {
+        let json = serde_json::to_string(&reading()).unwrap();
+        let back: Measurement = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.value, 1.5);
+        assert_eq!(back.unit, Unit::VoltDc);
+        assert_eq!(back.metadata.unit_multiplier, Some(-3));
+    }
+
+    #[test]
+    fn vanished_port_is_a_connection_error() {
+        for kind in [
+            serialport::ErrorKind::NoDevice,
+            serialport::ErrorKind::Io(std::io::ErrorKind::NotFound),
+        ] {
+            let error = map_open_error("/dev/ttyUSB0", serialport::Error::new(kind, "gone"));
+            let Error::Connection(message) = &error else {
+                panic!("{:?}", error);
+            };
+            assert!(
+                message.contains("/dev/ttyUSB0 no longer exists"),
+                "{}",
+                message
+            );
+        }
+    }
+
+    #[test]
+    fn other_open_failures_stay_serial_errors() {
+        let denied = serialport::Error::new(
+            serialport::ErrorKind::Io(std::io::ErrorKind::PermissionDenied),
+            "denied",
+        );
+        let error = map_open_error("/dev/ttyUSB0", denied);
+        assert!(matches!(error, Error::Serial(_)), "{:?}", error);
+    }
+
+    #[test]
+    fn raw_commands_cannot_smuggle_a_second_command() {
+        for command in ["QM\rRI", "QM\nRI", "QM\r", "QM\u{7f}", "QM\u{e9}"] {
+            let error = validate_raw_command(command).unwrap_err();
+            assert!(matches!(error, Error::InvalidCommand(_)), "{:?}", error);
         }
-        DeviceType::Mock => Box::new(mock::MockDevice::new()),
+        validate_raw_command("QM").unwrap();
+        validate_raw_command("QMP CLOCK").unwrap();
+    }
+
+    #[test]
+    fn overlong_raw_commands_are_rejected() {
+        validate_raw_command(&"Q".repeat(MAX_RAW_COMMAND_LEN)).unwrap();
+
+        let error = validate_raw_command(&"Q".repeat(MAX_RAW_COMMAND_LEN + 1)).unwrap_err();
+        assert!(matches!(error, Error::InvalidCommand(_)), "{:?}", error);
+        let error = validate_raw_command("  ").unwrap_err();
+        assert!(matches!(error, Error::InvalidCommand(_)), "{:?}", error);
     }
 }
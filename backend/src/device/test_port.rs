@@ -0,0 +1,206 @@
+//! Scripted serial port standing in for a meter in unit tests
+
+use serialport::{ClearBuffer, DataBits, FlowControl, Parity, SerialPort, StopBits};
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How the port answers one command, i.e. one write ending in `<CR>`
+pub(crate) enum Reply {
+    /// These bytes become readable
+    Bytes(Vec<u8>),
+    /// Nothing comes back; reads time out
+    Silent,
+    /// The port fails, as when the cable is pulled; every later read errors
+    Fail,
+    /// The meter streams this byte without end, as a misbehaving one might
+    Endless(u8),
+}
+
+#[derive(Default)]
+struct State {
+    replies: VecDeque<Reply>,
+    input: VecDeque<u8>,
+    written: Vec<u8>,
+    /// Each write call and when it happened
+    writes: Vec<(Instant, Vec<u8>)>,
+    failed: bool,
+    endless: Option<u8>,
+    /// Set by `set_baud_rate`; 115200 until then
+    baud_rate: Option<u32>,
+}
+
+/// A port that answers each command with the next scripted reply. Clones
+/// share state, so a test can keep one to inspect what was written.
+#[derive(Clone, Default)]
+pub(crate) struct ScriptedPort {
+    state: Arc<Mutex<State>>,
+}
+
+impl ScriptedPort {
+    pub(crate) fn new(replies: impl IntoIterator<Item = Reply>) -> Self {
+        let port = Self::default();
+        port.state.lock().unwrap().replies = replies.into_iter().collect();
+        port
+    }
+
+    /// Everything written to the port so far
+    pub(crate) fn written(&self) -> String {
+        String::from_utf8_lossy(&self.state.lock().unwrap().written).into_owned()
+    }
+
+    /// The bytes of each write call, with the time it was made
+    pub(crate) fn writes(&self) -> Vec<(Instant, Vec<u8>)> {
+        self.state.lock().unwrap().writes.clone()
+    }
+}
+
+impl Read for ScriptedPort {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        if state.failed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "port failed"));
+        }
+        if let Some(byte) = state.endless {
+            buf.fill(byte);
+            return Ok(buf.len());
+        }
+        if state.input.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::TimedOut, "no data"));
+        }
+        let count = buf.len().min(state.input.len());
+        for (slot, byte) in buf.iter_mut().zip(state.input.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+}
+
+impl Write for ScriptedPort {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut state = self.state.lock().unwrap();
+        state.written.extend_from_slice(buf);
+        state.writes.push((Instant::now(), buf.to_vec()));
+        for _ in buf.iter().filter(|&&byte| byte == b'\r') {
+            match state.replies.pop_front() {
+                Some(Reply::Bytes(bytes)) => state.input.extend(bytes),
+                Some(Reply::Fail) => state.failed = true,
+                Some(Reply::Endless(byte)) => state.endless = Some(byte),
+                Some(Reply::Silent) | None => {}
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl SerialPort for ScriptedPort {
+    fn name(&self) -> Option<String> {
+        Some("scripted".to_string())
+    }
+
+    fn baud_rate(&self) -> serialport::Result<u32> {
+        Ok(self.state.lock().unwrap().baud_rate.unwrap_or(115_200))
+    }
+
+    fn data_bits(&self) -> serialport::Result<DataBits> {
+        Ok(DataBits::Eight)
+    }
+
+    fn flow_control(&self) -> serialport::Result<FlowControl> {
+        Ok(FlowControl::None)
+    }
+
+    fn parity(&self) -> serialport::Result<Parity> {
+        Ok(Parity::None)
+    }
+
+    fn stop_bits(&self) -> serialport::Result<StopBits> {
+        Ok(StopBits::One)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::ZERO
+    }
+
+    fn set_baud_rate(&mut self, baud_rate: u32) -> serialport::Result<()> {
+        self.state.lock().unwrap().baud_rate = Some(baud_rate);
+        Ok(())
+    }
+
+    fn set_data_bits(&mut self, _: DataBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_flow_control(&mut self, _: FlowControl) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_parity(&mut self, _: Parity) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_stop_bits(&mut self, _: StopBits) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, _: Duration) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn write_request_to_send(&mut self, _: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn write_data_terminal_ready(&mut self, _: bool) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn read_clear_to_send(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn read_data_set_ready(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn read_ring_indicator(&mut self) -> serialport::Result<bool> {
+        Ok(false)
+    }
+
+    fn read_carrier_detect(&mut self) -> serialport::Result<bool> {
+        Ok(true)
+    }
+
+    fn bytes_to_read(&self) -> serialport::Result<u32> {
+        Ok(self.state.lock().unwrap().input.len() as u32)
+    }
+
+    fn bytes_to_write(&self) -> serialport::Result<u32> {
+        Ok(0)
+    }
+
+    fn clear(&self, buffer_to_clear: ClearBuffer) -> serialport::Result<()> {
+        if !matches!(buffer_to_clear, ClearBuffer::Output) {
+            self.state.lock().unwrap().input.clear();
+        }
+        Ok(())
+    }
+
+    fn try_clone(&self) -> serialport::Result<Box<dyn SerialPort>> {
+        Ok(Box::new(self.clone()))
+    }
+
+    fn set_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+
+    fn clear_break(&self) -> serialport::Result<()> {
+        Ok(())
+    }
+}
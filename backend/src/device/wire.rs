@@ -0,0 +1,50 @@
+//! Tap on the bytes a device exchanges over its serial link
+//!
+//! Devices with a wire send every chunk they write or read to an installed
+//! tap, for protocol debugging. Nothing is copied while no one listens.
+
+use serde::Serialize;
+use tokio::sync::broadcast;
+
+/// Chunks buffered per listener before the oldest are dropped
+pub const WIRE_TAP_CAPACITY: usize = 256;
+
+/// Which way a chunk went over the wire
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// Host to meter
+    Write,
+    /// Meter to host
+    Read,
+}
+
+/// Bytes written or read in one call to the port
+#[derive(Debug, Clone, Serialize)]
+pub struct WireChunk {
+    pub direction: Direction,
+    /// The bytes as lowercase hex, two digits each
+    pub data: String,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+impl WireChunk {
+    pub fn new(direction: Direction, bytes: &[u8]) -> Self {
+        Self {
+            direction,
+            data: bytes.iter().map(|byte| format!("{:02x}", byte)).collect(),
+            timestamp: chrono::Utc::now(),
+        }
+    }
+}
+
+/// Sender a device reports its traffic to
+pub type WireTap = broadcast::Sender<WireChunk>;
+
+/// Report `bytes` to `tap` if anyone listens
+pub fn observe(tap: &Option<WireTap>, direction: Direction, bytes: &[u8]) {
+    if let Some(tap) = tap.as_ref().filter(|tap| tap.receiver_count() > 0) {
+        // Listeners may leave between the check and the send.
+        let _ = tap.send(WireChunk::new(direction, bytes));
+    }
+}
@@ -9,8 +9,12 @@ pub enum Error {
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
 
-    #[error("Parse error: {0}")]
-    Parse(String),
+    #[error("Parse error: {message}{}", format_raw(.raw))]
+    Parse {
+        message: String,
+        /// Raw bytes of the frame that failed to parse, if known
+        raw: Vec<u8>,
+    },
 
     #[error("Device error: {0}")]
     Device(String),
@@ -21,14 +25,121 @@ pub enum Error {
     #[error("Invalid command: {0}")]
     InvalidCommand(String),
 
+    #[error("Device busy: {0}")]
+    Busy(String),
+
     #[error("Connection error: {0}")]
     Connection(String),
 
+    /// The device id is known, but the device is connecting or was
+    /// disconnected
+    #[error("Device not ready: {0}")]
+    NotReady(String),
+
     #[error("Configuration error: {0}")]
     Config(String),
 
+    #[error("Storage error: {0}")]
+    Storage(String),
+
+    #[error("Unexpected unit: expected {expected:?}, device reported {actual:?}")]
+    UnexpectedUnit {
+        expected: crate::device::Unit,
+        actual: crate::device::Unit,
+    },
+
+    #[error("Unsupported operation: {0}")]
+    Unsupported(String),
+
     #[error("JSON serialization error: {0}")]
     Json(#[from] serde_json::Error),
 }
 
+impl Error {
+    /// Create a parse error without raw frame context
+    pub fn parse(message: impl Into<String>) -> Self {
+        Self::Parse {
+            message: message.into(),
+            raw: Vec::new(),
+        }
+    }
+
+    /// Whether the error suggests the link to the device dropped, so that
+    /// reopening it may help. A timeout doesn't: a busy or slow meter times
+    /// out on a link that is fine.
+    pub fn is_connection_failure(&self) -> bool {
+        matches!(self, Self::Serial(_) | Self::Io(_) | Self::Connection(_))
+    }
+
+    /// Attach the raw frame to a parse error that does not carry one yet.
+    /// Other variants are returned unchanged.
+    pub fn with_raw(self, frame: &[u8]) -> Self {
+        match self {
+            Self::Parse { message, raw } if raw.is_empty() => Self::Parse {
+                message,
+                raw: frame.to_vec(),
+            },
+            other => other,
+        }
+    }
+}
+
+/// Render raw frame bytes as hex plus lossy UTF-8 for error messages
+fn format_raw(raw: &[u8]) -> String {
+    if raw.is_empty() {
+        return String::new();
+    }
+    let hex: Vec<String> = raw.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!(
+        " [raw: {} | {:?}]",
+        hex.join(" "),
+        String::from_utf8_lossy(raw)
+    )
+}
+
 pub type Result<T> = std::result::Result<T, Error>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_error_renders_its_raw_frame() {
+        let error = Error::parse("Invalid measurement").with_raw(b"1.2\xff\r");
+        let Error::Parse { raw, .. } = &error else {
+            panic!("{:?}", error);
+        };
+        assert_eq!(raw, b"1.2\xff\r");
+        assert_eq!(
+            error.to_string(),
+            "Parse error: Invalid measurement [raw: 31 2e 32 ff 0d | \"1.2\u{fffd}\\r\"]"
+        );
+    }
+
+    #[test]
+    fn first_raw_frame_is_kept() {
+        let error = Error::parse("Missing ACK")
+            .with_raw(b"first")
+            .with_raw(b"second");
+        assert!(error.to_string().ends_with("| \"first\"]"), "{}", error);
+    }
+
+    #[test]
+    fn parse_error_without_raw_frame_has_no_suffix() {
+        assert_eq!(
+            Error::parse("Missing ACK").to_string(),
+            "Parse error: Missing ACK"
+        );
+        assert!(matches!(Error::Timeout.with_raw(b"x"), Error::Timeout));
+    }
+
+    #[test]
+    fn only_link_errors_are_connection_failures() {
+        let io = std::io::Error::new(std::io::ErrorKind::BrokenPipe, "pipe");
+        assert!(Error::Io(io).is_connection_failure());
+        assert!(Error::Connection("Not connected".into()).is_connection_failure());
+        assert!(!Error::Timeout.is_connection_failure());
+        assert!(!Error::parse("Invalid value").is_connection_failure());
+        assert!(!Error::Busy("resetting".into()).is_connection_failure());
+    }
+}
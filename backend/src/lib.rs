@@ -8,6 +8,7 @@
 //! The backend is structured into several modules:
 //! - `device`: Device communication and protocol implementations
 //! - `communication`: IPC communication with the frontend
+//! - `conversion`: Unit conversions applied to measurements
 //! - Error handling and configuration management
 //!
 //! ## Supported Devices
@@ -16,6 +17,8 @@
 //! - Fluke 289/287 (serial interface)
 
 pub mod communication;
+pub mod config;
+pub mod conversion;
 pub mod device;
 pub mod error;
 
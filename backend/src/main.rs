@@ -3,15 +3,41 @@
 //! This is the main entry point for the TSMultimeter backend.
 //! It starts an HTTP server that the Electron frontend can communicate with.
 
+// The combined warp filter type of all routes nests deeper than the default limit.
+#![recursion_limit = "256"]
+
+use futures_util::{SinkExt, StreamExt};
+use std::collections::HashMap;
+use std::convert::Infallible;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use tokio::sync::{Mutex, Notify};
+use tsmultimeter_backend::communication::export::{ExportFormat, ExportTimezone};
 use tsmultimeter_backend::communication::{
-    connect_device, disconnect_device, get_available_ports, get_connected_devices, get_measurement,
-    AppState,
+    capture_measurement, clear_captures, configure_mock, connect_device, connect_device_async,
+    disconnect_device, export, get_all_measurements, get_available_functions, get_available_ports,
+    get_calibration_info, get_captures, get_command_timing, get_connected_devices,
+    get_connecting_devices, get_connection_events, get_device_clock, get_device_commands,
+    get_device_settings, get_differential, get_display_preferences, get_full_display, get_history,
+    get_last_measurement, get_limits, get_measurement, get_metrics, get_recording_summary,
+    get_saved_measurement, get_snapshot, get_statistics, get_trend, grafana,
+    list_saved_measurements, list_saved_setups, parse_device_type, polling, probe_port, profile,
+    recall_setup, replay, reset_limit_counts, run_self_test, scan_ports, set_auto_save,
+    set_db_reference, set_device_clock, set_display_brightness, set_display_contrast,
+    set_display_preferences, set_frequency_filter, set_high_input_impedance, set_hold,
+    set_invert_sign, set_limits, set_nplc, set_secondary_function, set_smoothing,
+    set_temperature_offset, set_zero, settings, settle_duration, shutdown, start_recording,
+    stop_recording, stream, subscribe_wire, trigger_capture, AppState, DEVICE_TYPE_NAMES,
 };
+use tsmultimeter_backend::config::{Config, DisplayPreferences};
+use tsmultimeter_backend::conversion::DEFAULT_DBM_REFERENCE_OHMS;
+use tsmultimeter_backend::device::mock::MockConfig;
+use tsmultimeter_backend::device::{DeviceOptions, Unit};
 use tsmultimeter_backend::init;
 use warp::http::{Method, StatusCode};
-use warp::Filter;
+use warp::sse::Event;
+use warp::{Filter, Reply};
 
 #[tokio::main]
 async fn main() {
@@ -23,12 +49,39 @@ async fn main() {
 
     println!("Starting TSMultimeter Backend HTTP Server on http://localhost:8080");
 
-    let app_state = Arc::new(Mutex::new(AppState::new()));
+    let config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("Failed to load configuration: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let stream_config = config.stream;
+    let body_limit = config.max_body_bytes;
+    let shutdown_report_path = config.shutdown_report_path.clone();
+    let app_state = match AppState::with_config(config) {
+        Ok(state) => Arc::new(Mutex::new(state)),
+        Err(e) => {
+            eprintln!("Failed to open measurement store: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(stream_config) = stream_config {
+        let state = app_state.clone();
+        tokio::spawn(async move {
+            if let Err(e) = stream::serve(stream_config, state).await {
+                eprintln!("Measurement stream server stopped: {}", e);
+            }
+        });
+    }
 
     // Define routes
     let connect_route = warp::path("connect")
         .and(warp::post())
-        .and(warp::body::json())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(json_body(body_limit))
         .and(with_state(app_state.clone()))
         .and_then(connect_device_handler);
 
@@ -38,26 +91,424 @@ async fn main() {
 
     let disconnect_route = warp::path!("disconnect" / String)
         .and(warp::post())
+        .and(warp::query::<HashMap<String, String>>())
         .and(with_state(app_state.clone()))
         .and_then(disconnect_device_handler);
 
     let measurement_route = warp::path!("measurement" / String)
         .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
         .and(with_state(app_state.clone()))
         .and_then(get_measurement_handler);
 
+    let all_measurements_route = warp::path!("measurement" / String / "all")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_all_measurements_handler);
+
+    let display_route = warp::path!("measurement" / String / "display")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_full_display_handler);
+
+    let last_measurement_route = warp::path!("measurement" / String / "last")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_last_measurement_handler);
+
+    let differential_route = warp::path!("differential")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(get_differential_handler);
+
+    let trend_route = warp::path!("trend" / String)
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(get_trend_handler);
+
+    let stats_route = warp::path!("stats" / String)
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_statistics_handler);
+
+    let history_route = warp::path!("history" / String)
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(get_history_handler);
+
+    let replay_route = warp::path!("replay" / String)
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(replay_handler);
+
+    let export_route = warp::path!("export" / String)
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(export_handler);
+
+    let export_csv_route = warp::path!("export" / String / "csv")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, mut query: HashMap<String, String>, state| {
+            query.insert("format".to_string(), "csv".to_string());
+            export_handler(device_id, query, state)
+        });
+
+    let export_jsonl_route = warp::path!("export" / String / "jsonl")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, mut query: HashMap<String, String>, state| {
+            query.insert("format".to_string(), "jsonl".to_string());
+            export_handler(device_id, query, state)
+        });
+
+    let grafana_health_route = warp::path!("grafana")
+        .and(warp::get())
+        .map(|| warp::reply::with_status(warp::reply(), StatusCode::OK));
+
+    let grafana_search_route = warp::path!("grafana" / "search")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(grafana_search_handler);
+
+    let grafana_query_route = warp::path!("grafana" / "query")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(grafana_query_handler);
+
+    let grafana_series_route = warp::path!("grafana" / String)
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(grafana_series_handler);
+
+    let snapshot_route = warp::path("snapshot")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_snapshot_handler);
+
+    let metrics_route = warp::path("metrics")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_metrics_handler);
+
     let status_route = warp::path("status")
         .and(warp::get())
         .and(with_state(app_state.clone()))
         .and_then(get_status_handler);
 
+    let events_route = warp::path("events")
+        .and(warp::get())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(get_events_handler);
+
     let ports_route = warp::path("ports")
         .and(warp::get())
         .and_then(get_ports_handler);
 
+    let probe_route = warp::path("probe")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and_then(probe_port_handler);
+
+    let scan_route = warp::path("scan")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and_then(scan_handler);
+
+    let get_clock_route = warp::path!("device" / String / "clock")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_clock_handler);
+
+    let set_clock_route = warp::path!("device" / String / "clock")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_clock_handler);
+
+    let settings_route = warp::path!("device" / String / "settings")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_settings_handler);
+
+    let patch_settings_route = warp::path!("device" / String / "settings")
+        .and(warp::patch())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(patch_settings_handler);
+
+    let frequency_filter_route = warp::path!("device" / String / "settings" / "frequency-filter")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_frequency_filter_handler);
+
+    let display_brightness_route =
+        warp::path!("device" / String / "settings" / "display-brightness")
+            .and(warp::post())
+            .and(json_body(body_limit))
+            .and(with_state(app_state.clone()))
+            .and_then(set_display_brightness_handler);
+
+    let display_contrast_route = warp::path!("device" / String / "settings" / "display-contrast")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_display_contrast_handler);
+
+    let raw_stream_route = warp::path!("device" / String / "raw-stream")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(raw_stream_handler);
+
+    let measurement_stream_route = warp::path!("stream" / String)
+        .and(warp::get())
+        .and(warp::ws())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(measurement_stream_handler);
+
+    let nplc_route = warp::path!("device" / String / "settings" / "nplc")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_nplc_handler);
+
+    let input_impedance_route =
+        warp::path!("device" / String / "settings" / "high-input-impedance")
+            .and(warp::post())
+            .and(json_body(body_limit))
+            .and(with_state(app_state.clone()))
+            .and_then(set_high_input_impedance_handler);
+
+    let auto_save_route = warp::path!("device" / String / "settings" / "auto-save")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_auto_save_handler);
+
+    let secondary_function_route =
+        warp::path!("device" / String / "settings" / "secondary-function")
+            .and(warp::post())
+            .and(json_body(body_limit))
+            .and(with_state(app_state.clone()))
+            .and_then(set_secondary_function_handler);
+
+    let temperature_offset_route =
+        warp::path!("device" / String / "settings" / "temperature-offset")
+            .and(warp::post())
+            .and(json_body(body_limit))
+            .and(with_state(app_state.clone()))
+            .and_then(set_temperature_offset_handler);
+
+    let invert_sign_route = warp::path!("device" / String / "settings" / "invert-sign")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_invert_sign_handler);
+
+    let smoothing_route = warp::path!("device" / String / "smoothing")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_smoothing_handler);
+
+    let set_limits_route = warp::path!("device" / String / "limits")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_limits_handler);
+
+    let get_limits_route = warp::path!("device" / String / "limits")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_limits_handler);
+
+    let reset_limit_counts_route = warp::path!("device" / String / "limits" / "reset")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(reset_limit_counts_handler);
+
+    let get_profile_route = warp::path!("device" / String / "profile")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_profile_handler);
+
+    let import_profile_route = warp::path!("device" / String / "profile")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(import_profile_handler);
+
+    let zero_route = warp::path!("device" / String / "zero")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_zero_handler(device_id, true, state));
+
+    let clear_zero_route = warp::path!("device" / String / "zero")
+        .and(warp::delete())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_zero_handler(device_id, false, state));
+
+    let db_reference_route = warp::path!("device" / String / "settings" / "db-reference")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_db_reference_handler(device_id, true, state));
+
+    let clear_db_reference_route = warp::path!("device" / String / "settings" / "db-reference")
+        .and(warp::delete())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_db_reference_handler(device_id, false, state));
+
+    let pause_route = warp::path!("device" / String / "pause")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_paused_handler(device_id, true, state));
+
+    let resume_route = warp::path!("device" / String / "resume")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_paused_handler(device_id, false, state));
+
+    let polling_route = warp::path!("device" / String / "polling")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_polling_handler);
+
+    let start_polling_route = warp::path!("poll" / String / "start")
+        .and(warp::post())
+        .and(warp::query::<HashMap<String, String>>())
+        .and(with_state(app_state.clone()))
+        .and_then(start_polling_handler);
+
+    let stop_polling_route = warp::path!("poll" / String / "stop")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(stop_polling_handler);
+
+    let capture_route = warp::path!("device" / String / "capture")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(capture_handler);
+
+    let get_captures_route = warp::path!("device" / String / "captures")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_captures_handler);
+
+    let clear_captures_route = warp::path!("device" / String / "captures")
+        .and(warp::delete())
+        .and(with_state(app_state.clone()))
+        .and_then(clear_captures_handler);
+
+    let timing_route = warp::path!("device" / String / "timing")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_timing_handler);
+
+    let recording_route = warp::path!("device" / String / "recording")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_recording_handler);
+
+    let start_recording_route = warp::path!("device" / String / "recording" / "start")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_recording_handler(device_id, true, state));
+
+    let stop_recording_route = warp::path!("device" / String / "recording" / "stop")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(|device_id, state| set_recording_handler(device_id, false, state));
+
+    let hold_route = warp::path!("hold" / String)
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_hold_handler);
+
+    let trigger_route = warp::path!("trigger" / String)
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(trigger_capture_handler);
+
+    let calibration_route = warp::path!("device" / String / "calibration")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_calibration_handler);
+
+    let commands_route = warp::path!("device" / String / "commands")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_commands_handler);
+
+    let mock_control_route = warp::path!("device" / String / "mock-control")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(mock_control_handler);
+
+    let functions_route = warp::path!("device" / String / "functions")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_functions_handler);
+
+    let self_test_route = warp::path!("device" / String / "self-test")
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(self_test_handler);
+
+    let setups_route = warp::path!("setups" / String)
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(list_setups_handler);
+
+    let recall_setup_route = warp::path!("setups" / String / "recall" / usize)
+        .and(warp::post())
+        .and(with_state(app_state.clone()))
+        .and_then(recall_setup_handler);
+
+    let saved_measurements_route = warp::path!("saved" / String)
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(list_saved_measurements_handler);
+
+    let saved_measurement_route = warp::path!("saved" / String / usize)
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_saved_measurement_handler);
+
+    let get_display_preferences_route = warp::path!("device" / String / "display-preferences")
+        .and(warp::get())
+        .and(with_state(app_state.clone()))
+        .and_then(get_display_preferences_handler);
+
+    let set_display_preferences_route = warp::path!("device" / String / "display-preferences")
+        .and(warp::post())
+        .and(json_body(body_limit))
+        .and(with_state(app_state.clone()))
+        .and_then(set_display_preferences_handler);
+
     let cors = warp::cors()
         .allow_any_origin()
-        .allow_methods(vec![Method::GET, Method::POST, Method::OPTIONS])
+        .allow_methods(vec![
+            Method::GET,
+            Method::POST,
+            Method::DELETE,
+            Method::PATCH,
+            Method::OPTIONS,
+        ])
         .allow_headers(vec![
             warp::http::header::CONTENT_TYPE,
             warp::http::header::ACCEPT,
@@ -67,33 +518,242 @@ async fn main() {
         .or(connect_options_route)
         .or(disconnect_route)
         .or(measurement_route)
+        .or(all_measurements_route)
+        .or(display_route)
+        .or(last_measurement_route)
+        .or(differential_route)
+        .or(trend_route)
+        .or(stats_route)
+        .or(history_route)
+        .or(replay_route)
+        .or(export_route)
+        .or(export_csv_route)
+        .or(export_jsonl_route)
+        .or(grafana_health_route)
+        .or(grafana_search_route)
+        .or(grafana_query_route)
+        .or(grafana_series_route)
         .or(status_route)
+        .or(snapshot_route)
+        .or(metrics_route)
+        .or(events_route)
         .or(ports_route)
+        .or(probe_route)
+        .or(scan_route)
+        .or(get_clock_route)
+        .or(set_clock_route)
+        .or(settings_route)
+        .or(patch_settings_route)
+        .or(frequency_filter_route)
+        .or(secondary_function_route)
+        .or(temperature_offset_route)
+        .or(invert_sign_route)
+        .or(display_brightness_route)
+        .or(display_contrast_route)
+        .or(nplc_route)
+        .or(input_impedance_route)
+        .or(auto_save_route)
+        .or(raw_stream_route)
+        .or(smoothing_route)
+        .or(set_limits_route)
+        .or(get_limits_route)
+        .or(reset_limit_counts_route)
+        .or(zero_route)
+        .or(functions_route)
+        .or(self_test_route)
+        .or(clear_zero_route)
+        .or(db_reference_route)
+        .or(clear_db_reference_route)
+        .or(get_profile_route)
+        .or(import_profile_route)
+        .or(polling_route)
+        .or(pause_route)
+        .or(resume_route)
+        .or(start_polling_route)
+        .or(stop_polling_route)
+        .or(measurement_stream_route)
+        .or(timing_route)
+        .or(commands_route)
+        .or(calibration_route)
+        .or(recording_route)
+        .or(start_recording_route)
+        .or(stop_recording_route)
+        .or(hold_route)
+        .or(trigger_route)
+        .or(capture_route)
+        .or(get_captures_route)
+        .or(clear_captures_route)
+        .or(mock_control_route)
+        .or(setups_route)
+        .or(recall_setup_route)
+        .or(saved_measurements_route)
+        .or(saved_measurement_route)
+        .or(get_display_preferences_route)
+        .or(set_display_preferences_route)
+        .recover(handle_rejection)
         .with(cors);
 
-    warp::serve(routes).run(([127, 0, 0, 1], 8080)).await;
+    let stopping = Arc::new(Notify::new());
+    let (_, server) = warp::serve(routes).bind_with_graceful_shutdown(([127, 0, 0, 1], 8080), {
+        let stopping = stopping.clone();
+        async move {
+            shutdown_signal().await;
+            println!("Shutting down");
+            stopping.notify_one();
+        }
+    });
+    // Open streams would hold the server up indefinitely.
+    tokio::select! {
+        _ = server => {}
+        _ = async {
+            stopping.notified().await;
+            tokio::time::sleep(SHUTDOWN_GRACE).await;
+        } => eprintln!("Connections still open after {:?}, closing", SHUTDOWN_GRACE),
+    }
+
+    let report = shutdown::shutdown(&app_state).await;
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => {
+            println!("{}", json);
+            if let Some(path) = shutdown_report_path {
+                if let Err(e) = std::fs::write(&path, json) {
+                    eprintln!(
+                        "Failed to write shutdown report to {}: {}",
+                        path.display(),
+                        e
+                    );
+                }
+            }
+        }
+        Err(e) => eprintln!("Failed to serialize shutdown report: {}", e),
+    }
+}
+
+/// Time in-flight requests get to finish once shutdown starts
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// Resolve on Ctrl-C, or on SIGTERM where there is one
+async fn shutdown_signal() {
+    let interrupt = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("Failed to listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                terminate.recv().await;
+            }
+            Err(e) => {
+                eprintln!("Failed to listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = interrupt => {}
+        _ = terminate => {}
+    }
+}
+
+/// JSON request body of at most `limit` bytes
+fn json_body<T: serde::de::DeserializeOwned + Send>(
+    limit: u64,
+) -> impl Filter<Extract = (T,), Error = warp::Rejection> + Clone {
+    warp::body::content_length_limit(limit).and(warp::body::json())
+}
+
+/// Answer oversized bodies in the usual `{"success": false}` shape; other
+/// rejections keep warp's default handling
+async fn handle_rejection(rejection: warp::Rejection) -> Result<impl warp::Reply, warp::Rejection> {
+    let (error, status) = if rejection.find::<warp::reject::PayloadTooLarge>().is_some() {
+        ("Request body too large", StatusCode::PAYLOAD_TOO_LARGE)
+    } else if rejection.find::<warp::reject::LengthRequired>().is_some() {
+        (
+            "Request body needs a Content-Length",
+            StatusCode::LENGTH_REQUIRED,
+        )
+    } else {
+        return Err(rejection);
+    };
+    Ok(warp::reply::with_status(
+        warp::reply::json(&serde_json::json!({"success": false, "error": error})),
+        status,
+    ))
 }
 
 fn with_state(
     state: Arc<Mutex<AppState>>,
-) -> impl Filter<Extract = (Arc<Mutex<AppState>>,), Error = std::convert::Infallible> + Clone {
+) -> impl Filter<Extract = (Arc<Mutex<AppState>>,), Error = Infallible> + Clone {
     warp::any().map(move || state.clone())
 }
 
 async fn connect_device_handler(
+    query: HashMap<String, String>,
     body: serde_json::Value,
     state: Arc<Mutex<AppState>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
     println!("Incoming connect request: {}", body);
-    let device_type_str = body
-        .get("device_type")
-        .and_then(|v| v.as_str())
-        .unwrap_or("Mock");
+    // A missing device type connects the mock, but a present one must be
+    // valid so typos don't silently end up on the mock.
+    let device_type_str = match body.get("device_type") {
+        None | Some(serde_json::Value::Null) => {
+            println!("Connect request has no device_type, defaulting to Mock");
+            "Mock"
+        }
+        Some(serde_json::Value::String(device_type)) => device_type.as_str(),
+        Some(other) => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": format!(
+                    "device_type must be a string, one of: {} (got {})",
+                    DEVICE_TYPE_NAMES.join(", "),
+                    other
+                ),
+            })))
+        }
+    };
     let port = body.get("port").and_then(|v| v.as_str());
+    let options = match serde_json::from_value::<DeviceOptions>(body.clone()) {
+        Ok(options) => options,
+        Err(e) => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": format!("Invalid connect options: {}", e),
+            })))
+        }
+    };
+
+    if query.get("async").is_some_and(|flag| flag == "true") {
+        return match connect_device_async(
+            device_type_str.to_string(),
+            port.map(|s| s.to_string()),
+            options,
+            &state,
+        )
+        .await
+        {
+            Ok(pending) => Ok(warp::reply::json(&serde_json::json!({
+                "success": true,
+                "status": "connecting",
+                "device": pending,
+            }))),
+            Err(e) => Ok(warp::reply::json(
+                &serde_json::json!({"success": false, "error": e}),
+            )),
+        };
+    }
 
     match connect_device(
         device_type_str.to_string(),
         port.map(|s| s.to_string()),
+        options,
         &state,
     )
     .await
@@ -119,12 +779,16 @@ async fn connect_device_handler(
 
 async fn disconnect_device_handler(
     device_id: String,
+    query: HashMap<String, String>,
     state: Arc<Mutex<AppState>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    match disconnect_device(device_id.clone(), &state).await {
-        Ok(message) => Ok(warp::reply::json(
-            &serde_json::json!({"success": true, "message": message}),
-        )),
+    let force = query.get("force").is_some_and(|flag| flag == "true");
+    match disconnect_device(device_id.clone(), force, &state).await {
+        Ok(response) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "message": response.message,
+            "summary": response.summary,
+        }))),
         Err(e) => Ok(warp::reply::json(
             &serde_json::json!({"success": false, "error": e}),
         )),
@@ -133,9 +797,64 @@ async fn disconnect_device_handler(
 
 async fn get_measurement_handler(
     device_id: String,
+    query: HashMap<String, String>,
     state: Arc<Mutex<AppState>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    match get_measurement(device_id, &state).await {
+    let settle = match query.get("settle_ms").map(|raw| {
+        raw.parse::<u64>()
+            .map_err(|_| format!("Invalid settle_ms '{}'", raw))
+            .and_then(settle_duration)
+    }) {
+        Some(Ok(settle)) => Some(settle),
+        Some(Err(e)) => {
+            return Ok(warp::reply::json(
+                &serde_json::json!({"success": false, "error": e}),
+            ))
+        }
+        None => None,
+    };
+    // `unit` is the short form of `expected_unit`.
+    let unit = query.get("unit").or_else(|| query.get("expected_unit"));
+    let expected_unit = match unit.map(|raw| {
+        serde_json::from_value::<Unit>(serde_json::Value::String(raw.clone()))
+            .map_err(|_| format!("Invalid unit '{}'", raw))
+    }) {
+        Some(Ok(unit)) => Some(unit),
+        Some(Err(e)) => {
+            return Ok(warp::reply::json(
+                &serde_json::json!({"success": false, "error": e}),
+            ))
+        }
+        None => None,
+    };
+
+    let reference_ohms = match query.get("impedance").map(|raw| {
+        raw.parse::<f64>()
+            .ok()
+            .filter(|ohms| ohms.is_finite() && *ohms > 0.0)
+            .ok_or_else(|| format!("Invalid impedance '{}'", raw))
+    }) {
+        Some(Ok(ohms)) => ohms,
+        Some(Err(e)) => {
+            return Ok(warp::reply::json(
+                &serde_json::json!({"success": false, "error": e}),
+            ))
+        }
+        None => DEFAULT_DBM_REFERENCE_OHMS,
+    };
+
+    let reconnect = query.get("reconnect").is_none_or(|flag| flag != "false");
+
+    match get_measurement(
+        device_id,
+        settle,
+        expected_unit,
+        reference_ohms,
+        reconnect,
+        &state,
+    )
+    .await
+    {
         Ok(data) => Ok(warp::reply::json(
             &serde_json::json!({"success": true, "data": data}),
         )),
@@ -145,13 +864,1177 @@ async fn get_measurement_handler(
     }
 }
 
-async fn get_status_handler(
+async fn get_all_measurements_handler(
+    device_id: String,
     state: Arc<Mutex<AppState>>,
 ) -> Result<impl warp::Reply, warp::Rejection> {
-    match get_connected_devices(&state).await {
+    match get_all_measurements(device_id, &state).await {
+        Ok(readings) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "readings": readings}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_full_display_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_full_display(device_id, &state).await {
+        Ok(display) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "display": display}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_last_measurement_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_last_measurement(device_id, &state).await {
+        Ok(data) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "data": data}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_differential_handler(
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (Some(a), Some(b)) = (query.get("a"), query.get("b")) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected ?a=<device id>&b=<device id>",
+        })));
+    };
+
+    match get_differential(a.clone(), b.clone(), &state).await {
+        Ok(differential) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "differential": differential,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_trend_handler(
+    device_id: String,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let window_secs = query
+        .get("window_secs")
+        .and_then(|v| v.parse::<f64>().ok())
+        .filter(|secs| secs.is_finite() && *secs > 0.0)
+        .unwrap_or(30.0);
+
+    match get_trend(device_id, Duration::from_secs_f64(window_secs), &state).await {
+        Ok(trend) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "trend": trend}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_statistics_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_statistics(device_id, &state).await {
+        Ok(statistics) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "statistics": statistics}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_history_handler(
+    device_id: String,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(e) => {
+            return Ok(warp::reply::json(
+                &serde_json::json!({"success": false, "error": e}),
+            ))
+        }
+    };
+    let limit = match query.get("limit").map(|raw| raw.parse::<usize>()) {
+        Some(Ok(limit)) => Some(limit),
+        Some(Err(_)) => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": format!("Invalid limit '{}'", query["limit"]),
+            })))
+        }
+        None => None,
+    };
+
+    match get_history(device_id, from, to, limit, &state).await {
+        Ok(measurements) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "measurements": measurements,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn replay_handler(
+    device_id: String,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let failure = |e: String| {
+        warp::reply::json(&serde_json::json!({"success": false, "error": e})).into_response()
+    };
+    let speed = match query.get("speed").map(|raw| {
+        raw.parse::<f64>()
+            .map_err(|_| format!("Invalid speed '{}'", raw))
+            .and_then(replay::validate_speed)
+    }) {
+        Some(Ok(speed)) => speed,
+        Some(Err(e)) => return Ok(failure(e)),
+        None => 1.0,
+    };
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(e) => return Ok(failure(e)),
+    };
+    let measurements = match get_history(device_id, from, to, None, &state).await {
+        Ok(measurements) => measurements,
+        Err(e) => return Ok(failure(e)),
+    };
+
+    // The closing event tells clients the replay is over, so an
+    // EventSource doesn't reconnect and start it again.
+    let events = replay::paced(measurements, speed)
+        .map(|measurement| {
+            Event::default()
+                .event("measurement")
+                .json_data(measurement)
+                .unwrap_or_default()
+        })
+        .chain(futures_util::stream::once(async {
+            Event::default().event("end").data("")
+        }))
+        .map(Ok::<_, Infallible>);
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(events)).into_response())
+}
+
+async fn export_handler(
+    device_id: String,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let failure = |e: String| {
+        Ok(warp::reply::json(&serde_json::json!({"success": false, "error": e})).into_response())
+    };
+    let format = match ExportFormat::parse(query.get("format").map_or("csv", String::as_str)) {
+        Ok(format) => format,
+        Err(e) => return failure(e),
+    };
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(e) => return failure(e),
+    };
+    let preamble = query.get("preamble").is_none_or(|flag| flag != "false");
+    let timezone = match query.get("tz").map(|tz| ExportTimezone::parse(tz)) {
+        Some(Ok(timezone)) => timezone,
+        Some(Err(e)) => return failure(e),
+        None => ExportTimezone::Utc,
+    };
+
+    let disposition = |filename: &str| format!("attachment; filename=\"{}\"", filename);
+    if format == ExportFormat::Jsonl {
+        // Streamed, so a long recording isn't rendered into memory at once
+        return match export::export_jsonl(device_id, from, to, preamble, timezone, &state).await {
+            Ok(file) => {
+                let body = warp::hyper::Body::wrap_stream(futures_util::stream::iter(file.lines));
+                Ok(warp::reply::with_header(
+                    warp::reply::with_header(
+                        warp::reply::Response::new(body),
+                        "content-type",
+                        format.content_type(),
+                    ),
+                    "content-disposition",
+                    disposition(&file.filename),
+                )
+                .into_response())
+            }
+            Err(e) => failure(e),
+        };
+    }
+
+    match export::export(device_id, format, from, to, preamble, timezone, &state).await {
+        Ok(file) => Ok(warp::reply::with_header(
+            warp::reply::with_header(file.body, "content-type", format.content_type()),
+            "content-disposition",
+            disposition(&file.filename),
+        )
+        .into_response()),
+        Err(e) => failure(e),
+    }
+}
+
+type TimeBound = Option<chrono::DateTime<chrono::Utc>>;
+
+/// Parse the optional RFC 3339 `from` and `to` query parameters
+fn time_range(query: &HashMap<String, String>) -> Result<(TimeBound, TimeBound), String> {
+    let parse = |key: &str| match query.get(key) {
+        Some(raw) => chrono::DateTime::parse_from_rfc3339(raw)
+            .map(|time| Some(time.with_timezone(&chrono::Utc)))
+            .map_err(|e| format!("Invalid {} '{}': {}", key, raw, e)),
+        None => Ok(None),
+    };
+    Ok((parse("from")?, parse("to")?))
+}
+
+// Grafana's SimpleJSON datasource expects bare arrays rather than the
+// `{"success": ...}` envelope, and HTTP errors on failure.
+fn grafana_reply<T: serde::Serialize>(
+    result: Result<T, String>,
+) -> warp::reply::WithStatus<warp::reply::Json> {
+    match result {
+        Ok(body) => warp::reply::with_status(warp::reply::json(&body), StatusCode::OK),
+        Err(e) => warp::reply::with_status(
+            warp::reply::json(&serde_json::json!({"error": e})),
+            StatusCode::BAD_REQUEST,
+        ),
+    }
+}
+
+async fn grafana_search_handler(
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    Ok(grafana_reply(grafana::search(&state).await))
+}
+
+async fn grafana_query_handler(
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let query = match serde_json::from_value::<grafana::GrafanaQuery>(body) {
+        Ok(query) => query,
+        Err(e) => return Ok(grafana_reply::<()>(Err(format!("Invalid query: {}", e)))),
+    };
+    Ok(grafana_reply(grafana::query(query, &state).await))
+}
+
+async fn grafana_series_handler(
+    device_id: String,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(e) => return Ok(grafana_reply::<()>(Err(e))),
+    };
+    let result = grafana::series(device_id, from, to, None, &state).await;
+    Ok(grafana_reply(result.map(|series| series.datapoints)))
+}
+
+async fn get_status_handler(
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_connected_devices(&state).await {
         Ok(devices) => Ok(warp::reply::json(&serde_json::json!({
             "success": true,
-            "devices": devices,
+            "devices": devices,
+            "connecting": get_connecting_devices(&state).await,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_paused_handler(
+    device_id: String,
+    paused: bool,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match polling::set_paused(&device_id, paused, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_polling_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match polling::status(&device_id, &state).await {
+        Ok(polling) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "polling": polling,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+/// Parse the optional `interval_ms` query parameter
+fn query_poll_interval(query: &HashMap<String, String>) -> Result<Duration, String> {
+    match query.get("interval_ms") {
+        Some(raw) => raw
+            .parse::<u64>()
+            .map_err(|_| format!("Invalid interval_ms '{}'", raw))
+            .and_then(polling::poll_interval),
+        None => Ok(Duration::from_millis(polling::DEFAULT_POLL_INTERVAL_MS)),
+    }
+}
+
+async fn start_polling_handler(
+    device_id: String,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let interval = match query_poll_interval(&query) {
+        Ok(interval) => interval,
+        Err(e) => {
+            return Ok(warp::reply::json(
+                &serde_json::json!({"success": false, "error": e}),
+            ))
+        }
+    };
+
+    match polling::start(&device_id, interval, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn stop_polling_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match polling::stop(&device_id, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn capture_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(label) = body.get("label").and_then(|v| v.as_str()) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected {\"label\": <name>}",
+        })));
+    };
+
+    match capture_measurement(device_id, label.to_string(), &state).await {
+        Ok(capture) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "capture": capture,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_captures_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let captures = get_captures(device_id, &state).await;
+    Ok(warp::reply::json(&serde_json::json!({
+        "success": true,
+        "captures": captures,
+    })))
+}
+
+async fn clear_captures_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let message = clear_captures(device_id, &state).await;
+    Ok(warp::reply::json(
+        &serde_json::json!({"success": true, "message": message}),
+    ))
+}
+
+async fn get_timing_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_command_timing(device_id, &state).await {
+        Ok(timing) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "timing": timing,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_calibration_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_calibration_info(device_id, &state).await {
+        Ok(calibration) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "calibration": calibration,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_recording_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_recording_summary(device_id, &state).await {
+        Ok(recording) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "recording": recording,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_recording_handler(
+    device_id: String,
+    start: bool,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let result = if start {
+        start_recording(device_id, &state).await
+    } else {
+        stop_recording(device_id, &state).await
+    };
+    match result {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_hold_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(enabled) = body.get("enabled").and_then(|v| v.as_bool()) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected {\"enabled\": true|false}",
+        })));
+    };
+
+    match set_hold(device_id, enabled, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn trigger_capture_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match trigger_capture(device_id, &state).await {
+        Ok(measurement) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "measurement": measurement,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_commands_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_device_commands(device_id, &state).await {
+        Ok(commands) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "commands": commands,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_snapshot_handler(
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let snapshot = get_snapshot(&state).await;
+    Ok(warp::reply::json(&serde_json::json!({
+        "success": true,
+        "snapshot": snapshot,
+    })))
+}
+
+async fn get_metrics_handler(
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let metrics = get_metrics(&state).await;
+    Ok(warp::reply::json(&serde_json::json!({
+        "success": true,
+        "metrics": metrics,
+    })))
+}
+
+async fn get_events_handler(
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let events = get_connection_events(query.get("device_id").cloned(), &state).await;
+    Ok(warp::reply::json(&serde_json::json!({
+        "success": true,
+        "events": events,
+    })))
+}
+
+async fn probe_port_handler(body: serde_json::Value) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(port) = body.get("port").and_then(|v| v.as_str()) else {
+        return Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": "Missing port"}),
+        ));
+    };
+    let device_type_str = body
+        .get("device_type")
+        .and_then(|v| v.as_str())
+        .unwrap_or("Fluke289");
+    let timeout_ms = body
+        .get("timeout_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(3000);
+
+    let result = match parse_device_type(device_type_str) {
+        Ok(device_type) => probe_port(port, device_type, Duration::from_millis(timeout_ms)).await,
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(info) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "port": port,
+            "info": info,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn scan_handler(body: serde_json::Value) -> Result<impl warp::Reply, warp::Rejection> {
+    let timeout_ms = body
+        .get("timeout_ms")
+        .and_then(|v| v.as_u64())
+        .unwrap_or(3000);
+
+    match scan_ports(Duration::from_millis(timeout_ms)).await {
+        Ok(ports) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "ports": ports,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_clock_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_device_clock(device_id, &state).await {
+        Ok(time) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "time": time,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_clock_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    // Without an explicit time the meter is synchronised to the host clock.
+    let time = match body.get("time").and_then(|v| v.as_str()) {
+        Some(raw) => match chrono::DateTime::parse_from_rfc3339(raw) {
+            Ok(time) => time.with_timezone(&chrono::Utc),
+            Err(e) => {
+                return Ok(warp::reply::json(&serde_json::json!({
+                    "success": false,
+                    "error": format!("Invalid time '{}': {}", raw, e),
+                })))
+            }
+        },
+        None => chrono::Utc::now(),
+    };
+
+    match set_device_clock(device_id, time, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_settings_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_device_settings(device_id, &state).await {
+        Ok(settings) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "settings": settings,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn patch_settings_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let serde_json::Value::Object(patch) = body else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected an object of settings, e.g. {\"nplc\": 10}",
+        })));
+    };
+
+    match settings::patch_settings(device_id, patch, &state).await {
+        Ok(results) => Ok(warp::reply::json(&serde_json::json!({
+            "success": results.values().all(|outcome| outcome.success),
+            "results": results,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_frequency_filter_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let filter = match body.get("filter").cloned().map(serde_json::from_value) {
+        Some(Ok(filter)) => filter,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"filter\": \"Off\" | \"LowPass\"}",
+            })))
+        }
+    };
+
+    match set_frequency_filter(device_id, filter, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_display_brightness_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let level = match body.get("level").cloned().map(serde_json::from_value) {
+        Some(Ok(level)) => level,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"level\": <0-255>}",
+            })))
+        }
+    };
+
+    match set_display_brightness(device_id, level, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_display_contrast_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let level = match body.get("level").cloned().map(serde_json::from_value) {
+        Some(Ok(level)) => level,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"level\": <0-255>}",
+            })))
+        }
+    };
+
+    match set_display_contrast(device_id, level, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+/// Server-sent events of the raw serial traffic of a device, one `wire`
+/// event per chunk; `lagged` events count chunks dropped for a slow client
+async fn raw_stream_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let chunks = match subscribe_wire(&device_id, &state).await {
+        Ok(chunks) => chunks,
+        Err(e) => {
+            return Ok(
+                warp::reply::json(&serde_json::json!({"success": false, "error": e}))
+                    .into_response(),
+            )
+        }
+    };
+
+    let events = futures_util::stream::unfold(chunks, |mut chunks| async move {
+        let event = match chunks.recv().await {
+            Ok(chunk) => Event::default()
+                .event("wire")
+                .json_data(chunk)
+                .unwrap_or_default(),
+            Err(RecvError::Lagged(skipped)) => {
+                Event::default().event("lagged").data(skipped.to_string())
+            }
+            Err(RecvError::Closed) => return None,
+        };
+        Some((Ok::<_, Infallible>(event), chunks))
+    });
+    Ok(warp::sse::reply(warp::sse::keep_alive().stream(events)).into_response())
+}
+
+/// WebSocket of the live measurements of a device, one JSON text frame per
+/// update as on the TCP stream. Clients share the device's poller; a
+/// closing client leaves it running for the others, and a device
+/// disconnect closes the socket with the reason.
+async fn measurement_stream_handler(
+    device_id: String,
+    ws: warp::ws::Ws,
+    query: HashMap<String, String>,
+    state: Arc<Mutex<AppState>>,
+) -> Result<warp::reply::Response, warp::Rejection> {
+    let failure = |e: String| {
+        warp::reply::json(&serde_json::json!({"success": false, "error": e})).into_response()
+    };
+    let interval = match query_poll_interval(&query) {
+        Ok(interval) => interval,
+        Err(e) => return Ok(failure(e)),
+    };
+    let updates = match polling::subscribe(&device_id, interval, &state).await {
+        Ok(updates) => updates,
+        Err(e) => return Ok(failure(e)),
+    };
+
+    Ok(ws
+        .on_upgrade(move |socket| stream_measurements(socket, device_id, updates))
+        .into_response())
+}
+
+async fn stream_measurements(
+    mut socket: warp::ws::WebSocket,
+    device_id: String,
+    updates: tokio::sync::broadcast::Receiver<polling::Update>,
+) {
+    let mut updates = polling::LatestUpdates::new(updates);
+    tracing::info!(device_id, "WebSocket client subscribed");
+
+    loop {
+        tokio::select! {
+            update = updates.recv() => {
+                let Some(update) = update else {
+                    let reason = format!("Device {} disconnected", device_id);
+                    let _ = socket.send(warp::ws::Message::close_with(1001u16, reason)).await;
+                    break;
+                };
+                let frame = match update.to_json() {
+                    Ok(frame) => frame.to_string(),
+                    Err(error) => {
+                        tracing::warn!(device_id, %error, "Failed to serialize update");
+                        continue;
+                    }
+                };
+                if socket.send(warp::ws::Message::text(frame)).await.is_err() {
+                    break;
+                }
+            }
+            // Incoming frames are ignored; reading only serves to notice
+            // when the client goes away.
+            message = socket.next() => match message {
+                Some(Ok(message)) if !message.is_close() => {}
+                _ => break,
+            }
+        }
+    }
+
+    tracing::info!(
+        device_id,
+        dropped = updates.dropped(),
+        "WebSocket client unsubscribed"
+    );
+}
+
+async fn set_nplc_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(cycles) = body.get("nplc").and_then(|v| v.as_f64()) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected {\"nplc\": <cycles>}",
+        })));
+    };
+
+    match set_nplc(device_id, cycles, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_high_input_impedance_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(enabled) = body.get("enabled").and_then(|v| v.as_bool()) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected {\"enabled\": true|false}",
+        })));
+    };
+
+    match set_high_input_impedance(device_id, enabled, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_auto_save_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(enabled) = body.get("enabled").and_then(|v| v.as_bool()) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected {\"enabled\": true|false}",
+        })));
+    };
+
+    match set_auto_save(device_id, enabled, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_secondary_function_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let function = match body.get("function").cloned().map(serde_json::from_value) {
+        Some(Ok(function)) => function,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"function\": \"None\" | \"Hertz\" | \"DutyCycle\" | \"PulseWidth\" | \"Dbm\" | \"Dbv\" | \"DbmHertz\" | \"DbvHertz\" | \"CrestFactor\" | \"PeakMinMax\"}",
+            })))
+        }
+    };
+
+    match set_secondary_function(device_id, function, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_temperature_offset_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let Some(offset) = body.get("offset_celsius").and_then(|v| v.as_f64()) else {
+        return Ok(warp::reply::json(&serde_json::json!({
+            "success": false,
+            "error": "Expected {\"offset_celsius\": <number>}",
+        })));
+    };
+
+    match set_temperature_offset(device_id, offset, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_invert_sign_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let invert_sign = match body.get("invert_sign").cloned().map(serde_json::from_value) {
+        Some(Ok(invert_sign)) => invert_sign,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"invert_sign\": true | false | [\"current\", ...]}",
+            })))
+        }
+    };
+
+    match set_invert_sign(device_id, invert_sign, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_smoothing_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let smoothing = match body.get("smoothing").cloned().map(serde_json::from_value) {
+        Some(Ok(smoothing)) => smoothing,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"smoothing\": {\"kind\": \"ema\", \"alpha\": <0..1>} | {\"kind\": \"sma\", \"window\": <n>} | null}",
+            })))
+        }
+    };
+
+    match set_smoothing(device_id, smoothing, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_limits_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let limits = match body.get("limits").cloned().map(serde_json::from_value) {
+        Some(Ok(limits)) => limits,
+        _ => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"limits\": {\"nominal\": <n>, \"tolerance\": <n>, \"unit\": \"...\"} | null}",
+            })))
+        }
+    };
+
+    match set_limits(device_id, limits, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_limits_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_limits(device_id, &state).await {
+        Ok(status) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "limits": status.limits,
+            "counts": status.counts,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn reset_limit_counts_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match reset_limit_counts(device_id, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_db_reference_handler(
+    device_id: String,
+    capture: bool,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match set_db_reference(device_id, capture, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_zero_handler(
+    device_id: String,
+    zero: bool,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match set_zero(device_id, zero, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_profile_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match profile::export_profile(device_id, &state).await {
+        Ok(profile) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "profile": profile,
+        }))),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn import_profile_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let device_profile = match body.get("profile").cloned().map(serde_json::from_value) {
+        Some(Ok(device_profile)) => device_profile,
+        Some(Err(e)) => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": format!("Invalid profile: {}", e),
+            })))
+        }
+        None => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": "Expected {\"profile\": {...}} as returned by GET /device/{id}/profile",
+            })))
+        }
+    };
+
+    match profile::import_profile(device_id, device_profile, &state).await {
+        Ok(outcome) => Ok(warp::reply::json(&serde_json::json!({
+            "success": true,
+            "applied": outcome.applied,
+            "warnings": outcome.warnings,
         }))),
         Err(e) => Ok(warp::reply::json(
             &serde_json::json!({"success": false, "error": e}),
@@ -159,6 +2042,146 @@ async fn get_status_handler(
     }
 }
 
+async fn mock_control_handler(
+    device_id: String,
+    body: serde_json::Value,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    let config = match serde_json::from_value::<MockConfig>(body) {
+        Ok(config) => config,
+        Err(e) => {
+            return Ok(warp::reply::json(&serde_json::json!({
+                "success": false,
+                "error": format!("Invalid mock parameters: {}", e),
+            })))
+        }
+    };
+
+    match configure_mock(device_id, config, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_functions_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_available_functions(device_id, &state).await {
+        Ok(functions) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "functions": functions}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn self_test_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match run_self_test(device_id, &state).await {
+        Ok(result) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "result": result}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn list_setups_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match list_saved_setups(device_id, &state).await {
+        Ok(setups) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "setups": setups}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn recall_setup_handler(
+    device_id: String,
+    index: usize,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match recall_setup(device_id, index, &state).await {
+        Ok(message) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "message": message}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn list_saved_measurements_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match list_saved_measurements(device_id, &state).await {
+        Ok(saved) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "saved": saved}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_saved_measurement_handler(
+    device_id: String,
+    index: usize,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_saved_measurement(device_id, index, &state).await {
+        Ok(saved) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "saved": saved}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn get_display_preferences_handler(
+    device_id: String,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match get_display_preferences(device_id, &state).await {
+        Ok(preferences) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "preferences": preferences}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
+async fn set_display_preferences_handler(
+    device_id: String,
+    preferences: DisplayPreferences,
+    state: Arc<Mutex<AppState>>,
+) -> Result<impl warp::Reply, warp::Rejection> {
+    match set_display_preferences(device_id, preferences, &state).await {
+        Ok(preferences) => Ok(warp::reply::json(
+            &serde_json::json!({"success": true, "preferences": preferences}),
+        )),
+        Err(e) => Ok(warp::reply::json(
+            &serde_json::json!({"success": false, "error": e}),
+        )),
+    }
+}
+
 async fn get_ports_handler() -> Result<impl warp::Reply, warp::Rejection> {
     match get_available_ports() {
         Ok(ports) => Ok(warp::reply::json(&serde_json::json!({
@@ -170,3 +2193,94 @@ async fn get_ports_handler() -> Result<impl warp::Reply, warp::Rejection> {
         )),
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn echo(
+        limit: u64,
+    ) -> impl Filter<Extract = (impl warp::Reply,), Error = warp::Rejection> + Clone {
+        warp::post()
+            .and(json_body(limit))
+            .map(|body: serde_json::Value| warp::reply::json(&body))
+            .recover(handle_rejection)
+    }
+
+    #[tokio::test]
+    async fn oversized_body_is_rejected_with_413() {
+        let body = serde_json::json!({ "padding": "x".repeat(64) }).to_string();
+        let response = warp::test::request()
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(body)
+            .reply(&echo(32))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let reply: serde_json::Value = serde_json::from_slice(response.body()).unwrap();
+        assert_eq!(reply["success"], false);
+    }
+
+    #[tokio::test]
+    async fn body_within_the_limit_is_accepted() {
+        let response = warp::test::request()
+            .method("POST")
+            .header("content-type", "application/json")
+            .body(r#"{"ok":true}"#)
+            .reply(&echo(32))
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    async fn connect(body: serde_json::Value) -> serde_json::Value {
+        let state = Arc::new(Mutex::new(AppState::new()));
+        let route = warp::post()
+            .and(warp::query::<HashMap<String, String>>())
+            .and(warp::body::json())
+            .and(with_state(state))
+            .and_then(connect_device_handler);
+        let response = warp::test::request()
+            .method("POST")
+            .json(&body)
+            .reply(&route)
+            .await;
+
+        assert_eq!(response.status(), StatusCode::OK);
+        serde_json::from_slice(response.body()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn absent_device_type_connects_the_mock() {
+        let reply = connect(serde_json::json!({ "port": "COM_DEFAULT" })).await;
+
+        assert_eq!(reply["success"], true, "{}", reply);
+        let device = &reply["device"];
+        let device_type = device.get("device_type").or(device.get("deviceType"));
+        assert_eq!(device_type, Some(&serde_json::json!("Mock")), "{}", reply);
+    }
+
+    #[tokio::test]
+    async fn unknown_device_type_is_refused() {
+        let reply = connect(serde_json::json!({ "device_type": "Fluke298" })).await;
+
+        assert_eq!(reply["success"], false);
+        let error = reply["error"].as_str().unwrap();
+        assert!(error.contains("Fluke298"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn non_string_device_type_is_refused() {
+        let reply = connect(serde_json::json!({ "device_type": 289 })).await;
+
+        assert_eq!(reply["success"], false);
+        let error = reply["error"].as_str().unwrap();
+        assert!(
+            error.starts_with("device_type must be a string"),
+            "{}",
+            error
+        );
+        assert!(error.contains("Mock"), "{}", error);
+    }
+}
@@ -2,14 +2,19 @@ import type { DeviceInfo, MeasurementResponse } from '../types/deviceData.ts';
 
 const API_BASE = 'http://127.0.0.1:8080';
 
+// The backend emits snake_case by default and camelCase when built with the
+// `camel-case` feature; accept both spellings.
 type RawDeviceInfo = {
   id?: string | number;
   connected?: boolean;
   device_type?: string;
+  deviceType?: string;
   info?: {
     model?: string;
     serial_number?: string;
+    serialNumber?: string;
     software_version?: string;
+    softwareVersion?: string;
   } | null;
 };
 
@@ -46,10 +51,10 @@ type MeasurementApiResponse = {
 const normaliseDevice = (device: RawDeviceInfo): DeviceInfo => ({
   id: String(device.id ?? 'unknown-device'),
   connected: Boolean(device.connected),
-  deviceType: device.device_type ?? 'Unknown',
+  deviceType: device.device_type ?? device.deviceType ?? 'Unknown',
   model: device.info?.model ?? 'Unknown',
-  serialNumber: device.info?.serial_number ?? 'N/A',
-  softwareVersion: device.info?.software_version ?? 'N/A',
+  serialNumber: device.info?.serial_number ?? device.info?.serialNumber ?? 'N/A',
+  softwareVersion: device.info?.software_version ?? device.info?.softwareVersion ?? 'N/A',
 });
 
 const createRequest = (path: string, init?: RequestInit) => fetch(`${API_BASE}${path}`, init);
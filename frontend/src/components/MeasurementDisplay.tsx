@@ -1,6 +1,6 @@
 import { useState, useEffect, useRef } from 'react';
 import styled from 'styled-components';
-import type { MeasurementResponse } from '../types/deviceData.ts';
+import type { MeasurementResponse, UnitResponse } from '../types/deviceData.ts';
 
 const Card = styled.div`
   background: white;
@@ -165,7 +165,10 @@ export const MeasurementDisplay: React.FC<MeasurementDisplayProps> = ({
     }
   };
 
-  const getUnitDisplay = (unit: string) => {
+  const getUnitDisplay = (unit: UnitResponse) => {
+    if (typeof unit === 'object') {
+      return unit.Unknown || 'Unknown';
+    }
     const unitMap: { [key: string]: string } = {
       'VDC': 'V DC',
       'VAC': 'V AC',
@@ -1,5 +1,10 @@
 import { useCallback, useEffect, useRef, useState } from 'react';
-import type { DeviceInfo, MeasurementResponse, MeasurementSample } from '../types/deviceData.ts';
+import type {
+  DeviceInfo,
+  MeasurementResponse,
+  MeasurementSample,
+  UnitResponse,
+} from '../types/deviceData.ts';
 import {
   connectToDevice,
   disconnectFromDevice,
@@ -27,14 +32,20 @@ const UNIT_DISPLAY_MAP: Record<string, string> = {
   Farad: 'F',
   Celsius: '°C',
   Fahrenheit: '°F',
+  Kelvin: 'K',
   Percent: '%',
   DecibelM: 'dBm',
   DecibelV: 'dBV',
   Decibel: 'dB',
   CrestFactor: 'CF',
+  Unknown: 'Unknown',
 };
 
-const normaliseUnit = (unit?: string | null): string => {
+const normaliseUnit = (unit?: UnitResponse | null): string => {
+  if (unit && typeof unit === 'object') {
+    // An unrecognised unit carries the device's token; show that.
+    return unit.Unknown.trim() || 'Unknown';
+  }
   if (!unit) {
     return 'Unknown';
   }
@@ -7,12 +7,17 @@ export interface DeviceInfo {
   softwareVersion: string;
 }
 
+/** A unit name, or for a unit the backend didn't recognise the device's token */
+export type UnitResponse = string | { Unknown: string };
+
 export interface MeasurementResponse {
   value: number;
-  unit: string;
+  uncertainty?: number;
+  unit: UnitResponse;
   state?: string;
   attribute?: string;
   timestamp?: string;
+  sequence?: number;
 }
 
 export interface MeasurementSample {